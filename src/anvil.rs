@@ -0,0 +1,291 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::blocks::BlockRegistry;
+use crate::nbt::{self, Tag, invalid_data};
+use crate::voxel::{BlockId, VoxelWorld, AIR, CHUNK_SIZE};
+
+// Cargador de mundos guardados de Minecraft en formato Anvil (region/r.X.Z.mca).
+// Soporta chunks con paleta por sección: 1.13-1.17 (Level/Sections) y 1.18+ (sections/block_states).
+
+const SECTOR_SIZE: usize = 4096;
+const SECTION_VOLUME: usize = 4096;
+// Primera versión de datos (20w17a, 1.16) en la que los índices no cruzan de un long a otro
+const DATA_VERSION_NO_SPANNING: i32 = 2529;
+
+#[derive(Default, Clone, Copy)]
+pub struct WorldLoadOptions {
+    pub chunk_area: Option<(i32, i32, i32, i32)>, // (x0, z0, x1, z1) en chunks, inclusivo
+}
+
+impl WorldLoadOptions {
+    fn contains_chunk(&self, x: i32, z: i32) -> bool {
+        match self.chunk_area {
+            Some((x0, z0, x1, z1)) => x >= x0 && x <= x1 && z >= z0 && z <= z1,
+            None => true,
+        }
+    }
+
+    fn overlaps_region(&self, rx: i32, rz: i32) -> bool {
+        match self.chunk_area {
+            Some((x0, z0, x1, z1)) => {
+                rx * 32 <= x1 && rx * 32 + 31 >= x0 && rz * 32 <= z1 && rz * 32 + 31 >= z0
+            }
+            None => true,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LoadStats {
+    pub regions: usize,
+    pub chunks: usize,
+    pub sections: usize,
+    pub skipped_chunks: usize,
+}
+
+// Acepta un archivo .mca, una carpeta con archivos .mca o la carpeta de un mundo (usa region/)
+pub fn load_world(path: &Path, world: &mut VoxelWorld, registry: &mut BlockRegistry, options: &WorldLoadOptions) -> io::Result<LoadStats> {
+    let mut stats = LoadStats::default();
+
+    if path.is_file() {
+        load_region_file(path, world, registry, options, &mut stats)?;
+        return Ok(stats);
+    }
+
+    let region_dir = if path.join("region").is_dir() { path.join("region") } else { path.to_path_buf() };
+    let mut files: Vec<PathBuf> = fs::read_dir(&region_dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "mca"))
+        .collect();
+    files.sort();
+    if files.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("no hay archivos .mca en {}", region_dir.display())));
+    }
+
+    for file in files {
+        if let Some((rx, rz)) = region_coords(&file) && !options.overlaps_region(rx, rz) {
+            continue;
+        }
+        load_region_file(&file, world, registry, options, &mut stats)?;
+    }
+    Ok(stats)
+}
+
+// "r.-1.2.mca" -> (-1, 2)
+fn region_coords(path: &Path) -> Option<(i32, i32)> {
+    let name = path.file_stem()?.to_str()?;
+    let mut parts = name.split('.');
+    if parts.next()? != "r" { return None; }
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    Some((x, z))
+}
+
+pub fn load_region_file(path: &Path, world: &mut VoxelWorld, registry: &mut BlockRegistry, options: &WorldLoadOptions, stats: &mut LoadStats) -> io::Result<()> {
+    let data = fs::read(path)?;
+    stats.regions += 1;
+    // Regiones recién creadas pueden estar vacías o sin cabecera completa
+    if data.len() < SECTOR_SIZE * 2 {
+        return Ok(());
+    }
+
+    for i in 0..1024 {
+        let Some(offset) = chunk_offset(&data, i) else { continue };
+
+        match read_chunk(&data, offset).and_then(|tag| load_chunk(&tag, world, registry, options, stats)) {
+            Ok(()) => {}
+            Err(e) => {
+                eprintln!("Warning: skipping chunk {} in {}: {}", i, path.display(), e);
+                stats.skipped_chunks += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Posición en bytes del chunk `index` según la tabla de ubicaciones de la cabecera: 3 bytes de
+// sector inicial y 1 de cantidad de sectores; None si el chunk no se generó
+fn chunk_offset(header: &[u8], index: usize) -> Option<usize> {
+    let entry = header.get(index * 4..index * 4 + 4)?;
+    let sector = u32::from_be_bytes([0, entry[0], entry[1], entry[2]]) as usize;
+    if sector == 0 || entry[3] == 0 { None } else { Some(sector * SECTOR_SIZE) }
+}
+
+fn read_chunk(data: &[u8], offset: usize) -> io::Result<Tag> {
+    let header = data.get(offset..offset + 5).ok_or_else(|| invalid_data("sector fuera del archivo"))?;
+    let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let compression = header[4];
+    if length == 0 {
+        return Err(invalid_data("chunk vacío"));
+    }
+    let payload = data.get(offset + 5..offset + 4 + length).ok_or_else(|| invalid_data("chunk truncado"))?;
+    match compression {
+        1..=3 => nbt::parse_compressed(payload),
+        c if c & 0x80 != 0 => Err(invalid_data("chunk guardado en archivo .mcc externo (no soportado)")),
+        c => Err(invalid_data(format!("compresión {} no soportada", c))),
+    }
+}
+
+fn load_chunk(root: &Tag, world: &mut VoxelWorld, registry: &mut BlockRegistry, options: &WorldLoadOptions, stats: &mut LoadStats) -> io::Result<()> {
+    let data_version = root.get("DataVersion").and_then(Tag::as_i32).unwrap_or(0);
+    // Antes de 1.18 todo va dentro de "Level"
+    let level = root.get("Level").unwrap_or(root);
+    let chunk_x = level.get("xPos").and_then(Tag::as_i32).ok_or_else(|| invalid_data("chunk sin xPos"))?;
+    let chunk_z = level.get("zPos").and_then(Tag::as_i32).ok_or_else(|| invalid_data("chunk sin zPos"))?;
+    if !options.contains_chunk(chunk_x, chunk_z) {
+        return Ok(());
+    }
+
+    let Some(sections) = level.get("sections").or_else(|| level.get("Sections")).and_then(Tag::as_list) else {
+        return Ok(());
+    };
+
+    for section in sections {
+        let Some(section_y) = section.get("Y").and_then(Tag::as_i32) else { continue };
+        let (palette, states) = match section.get("block_states") {
+            Some(block_states) => (block_states.get("palette"), block_states.get("data")),
+            None => (section.get("Palette"), section.get("BlockStates")),
+        };
+        // Secciones sin paleta: formato numérico anterior a 1.13 o secciones solo de luz
+        let Some(palette) = palette.and_then(Tag::as_list) else { continue };
+
+        let ids: Vec<BlockId> = palette.iter()
+            .map(|entry| entry.get("Name").and_then(Tag::as_str).map(|n| registry.id_for(world, n)).unwrap_or(AIR))
+            .collect();
+        let blocks = unpack_section(&ids, states.and_then(Tag::as_long_array), data_version >= DATA_VERSION_NO_SPANNING);
+        if blocks.iter().any(|&id| id != AIR) {
+            world.set_chunk((chunk_x, section_y, chunk_z), blocks);
+            stats.sections += 1;
+        }
+    }
+    stats.chunks += 1;
+    Ok(())
+}
+
+// Desempaqueta los índices de paleta de una sección de 16x16x16 (orden y, z, x)
+fn unpack_section(ids: &[BlockId], states: Option<&[i64]>, no_spanning: bool) -> Vec<BlockId> {
+    let single = ids.first().copied().unwrap_or(AIR);
+    let states = match states {
+        Some(states) if ids.len() > 1 && !states.is_empty() => states,
+        _ => return vec![single; SECTION_VOLUME],
+    };
+
+    let bits = (usize::BITS - (ids.len() - 1).leading_zeros()).max(4) as usize;
+    let mask = (1u64 << bits) - 1;
+    let per_long = 64 / bits;
+
+    (0..SECTION_VOLUME).map(|index| {
+        let palette_index = if no_spanning {
+            let word = states.get(index / per_long).copied().unwrap_or(0) as u64;
+            (word >> ((index % per_long) * bits)) & mask
+        } else {
+            let bit = index * bits;
+            let (word, offset) = (bit / 64, bit % 64);
+            let mut value = states.get(word).copied().unwrap_or(0) as u64 >> offset;
+            if offset + bits > 64 {
+                value |= (states.get(word + 1).copied().unwrap_or(0) as u64) << (64 - offset);
+            }
+            value & mask
+        };
+        ids.get(palette_index as usize).copied().unwrap_or(AIR)
+    }).collect()
+}
+
+// El tamaño de sección de Anvil coincide con el de los chunks del VoxelWorld
+const _: () = assert!(CHUNK_SIZE == 16);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_with(entries: &[(usize, u32, u8)]) -> Vec<u8> {
+        let mut header = vec![0u8; SECTOR_SIZE];
+        for &(index, sector, count) in entries {
+            let bytes = sector.to_be_bytes();
+            header[index * 4..index * 4 + 4].copy_from_slice(&[bytes[1], bytes[2], bytes[3], count]);
+        }
+        header
+    }
+
+    #[test]
+    fn decodes_chunk_offsets() {
+        let header = header_with(&[(0, 2, 1), (1, 0x01_02_03, 4), (1023, 7, 2)]);
+        assert_eq!(chunk_offset(&header, 0), Some(2 * SECTOR_SIZE));
+        assert_eq!(chunk_offset(&header, 1), Some(0x01_02_03 * SECTOR_SIZE));
+        assert_eq!(chunk_offset(&header, 1023), Some(7 * SECTOR_SIZE));
+    }
+
+    #[test]
+    fn missing_chunks_have_no_offset() {
+        // Sin sector, o con sector pero cero sectores de largo
+        let header = header_with(&[(3, 5, 0)]);
+        assert_eq!(chunk_offset(&header, 2), None);
+        assert_eq!(chunk_offset(&header, 3), None);
+        assert_eq!(chunk_offset(&header[..8], 4), None);
+    }
+
+    // Sector con longitud, compresión y datos de un chunk
+    fn chunk_sector(payload: &[u8], compression: u8) -> Vec<u8> {
+        let mut sector = ((payload.len() + 1) as u32).to_be_bytes().to_vec();
+        sector.push(compression);
+        sector.extend_from_slice(payload);
+        sector
+    }
+
+    #[test]
+    fn reads_chunk_at_offset() {
+        // Compound raíz sin nombre con un Int "xPos" = 3, sin comprimir (tipo 3)
+        let nbt = [10, 0, 0, 3, 0, 4, b'x', b'P', b'o', b's', 0, 0, 0, 3, 0];
+        let mut data = vec![0u8; 2 * SECTOR_SIZE];
+        data.extend(chunk_sector(&nbt, 3));
+        let tag = read_chunk(&data, 2 * SECTOR_SIZE).unwrap();
+        assert_eq!(tag.get("xPos").and_then(Tag::as_i32), Some(3));
+    }
+
+    #[test]
+    fn rejects_bad_chunk_sectors() {
+        let nbt = [10, 0, 0, 0];
+        assert!(read_chunk(&chunk_sector(&nbt, 3), 100).is_err(), "sector fuera del archivo");
+        assert!(read_chunk(&[0, 0, 0, 0, 3], 0).is_err(), "chunk vacío");
+        let mut truncated = chunk_sector(&nbt, 3);
+        truncated.truncate(truncated.len() - 2);
+        assert!(read_chunk(&truncated, 0).is_err(), "chunk truncado");
+        assert!(read_chunk(&chunk_sector(&nbt, 0x82), 0).is_err(), "chunk en archivo .mcc");
+        assert!(read_chunk(&chunk_sector(&nbt, 9), 0).is_err(), "compresión desconocida");
+    }
+
+    #[test]
+    fn region_names_give_coordinates() {
+        assert_eq!(region_coords(Path::new("world/region/r.-1.2.mca")), Some((-1, 2)));
+        assert_eq!(region_coords(Path::new("r.0.0.mca")), Some((0, 0)));
+        assert_eq!(region_coords(Path::new("level.dat")), None);
+    }
+
+    #[test]
+    fn unpacks_palette_indices() {
+        let ids = [AIR, 7, 9];
+        // 4 bits por índice: sin cruzar longs caben 16 por long
+        let mut states = vec![0i64; SECTION_VOLUME / 16];
+        states[0] = 0x21;
+        let blocks = unpack_section(&ids, Some(&states), true);
+        assert_eq!(&blocks[..3], &[7, 9, AIR]);
+
+        // Un solo elemento en la paleta llena toda la sección
+        assert!(unpack_section(&[5], None, true).iter().all(|&id| id == 5));
+    }
+
+    #[test]
+    fn unpacks_indices_spanning_longs() {
+        // 32 entradas: 5 bits por índice, el índice 12 ocupa los bits 60..65
+        let ids: Vec<BlockId> = (0..32).collect();
+        let mut states = vec![0i64; SECTION_VOLUME * 5 / 64];
+        let value: u64 = 0b10110;
+        states[0] = (value << 60) as i64;
+        states[1] = (value >> 4) as i64;
+        assert_eq!(unpack_section(&ids, Some(&states), false)[12], 0b10110);
+        // Con el formato nuevo el long se completa con 12 índices y el 12 empieza en el siguiente
+        assert_eq!(unpack_section(&ids, Some(&states), true)[12], (value >> 4) as BlockId);
+    }
+}
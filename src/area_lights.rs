@@ -0,0 +1,413 @@
+use crate::geometry::*;
+use crate::materials::{MaterialId, MaterialRegistry};
+use crate::raytracer::{Emitter, HitInfo};
+use crate::shapes::{Primitive, Span};
+
+// Superficie emisiva que se puede muestrear como luz de área
+pub enum AreaLightShape {
+    Sphere { center: Vec3, radius: f32 },
+    // Paralelogramo: una esquina y los dos lados que salen de ella (caras de cubos y bloques)
+    Quad { corner: Vec3, edge_u: Vec3, edge_v: Vec3 },
+    Triangle { v0: Vec3, edge1: Vec3, edge2: Vec3 },
+}
+
+pub struct AreaLight {
+    pub shape: AreaLightShape,
+    pub material: MaterialId,
+    // UV de la textura en la esquina (o v0) y al final de cada lado; las esferas usan el
+    // mapeo esférico de Sphere
+    pub uvs: [(f32, f32); 3],
+}
+
+// Punto elegido sobre una luz de área: dónde está, la UV de la textura del emisor ahí, su
+// material y la densidad por ángulo sólido con que se eligió
+pub struct LightSample {
+    pub point: Vec3,
+    pub uv: (f32, f32),
+    pub material: MaterialId,
+    pub pdf: f32,
+}
+
+// UV de las caras de cubos y bloques (ver Cube::intersect) con los lados que les da
+// `area_lights`: en las caras X e Y la textura va con los lados intercambiados
+pub fn face_uvs(axis: usize) -> [(f32, f32); 3] {
+    if axis == 2 { [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)] } else { [(0.0, 0.0), (0.0, 1.0), (1.0, 0.0)] }
+}
+
+impl AreaLight {
+    pub fn sphere(center: Vec3, radius: f32, material: MaterialId) -> Self {
+        AreaLight { shape: AreaLightShape::Sphere { center, radius }, material, uvs: [(0.0, 0.0); 3] }
+    }
+
+    pub fn quad(corner: Vec3, edge_u: Vec3, edge_v: Vec3, material: MaterialId) -> Self {
+        AreaLight { shape: AreaLightShape::Quad { corner, edge_u, edge_v }, material, uvs: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)] }
+    }
+
+    pub fn triangle(v0: Vec3, v1: Vec3, v2: Vec3, material: MaterialId) -> Self {
+        AreaLight { shape: AreaLightShape::Triangle { v0, edge1: v1 - v0, edge2: v2 - v0 }, material, uvs: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)] }
+    }
+
+    pub fn with_uvs(mut self, uvs: [(f32, f32); 3]) -> Self {
+        self.uvs = uvs;
+        self
+    }
+
+    pub fn area(&self) -> f32 {
+        match self.shape {
+            AreaLightShape::Sphere { radius, .. } => 4.0 * std::f32::consts::PI * radius * radius,
+            AreaLightShape::Quad { edge_u, edge_v, .. } => edge_u.cross(edge_v).length(),
+            AreaLightShape::Triangle { edge1, edge2, .. } => 0.5 * edge1.cross(edge2).length(),
+        }
+    }
+
+    // Punto de la luz visto desde `origin`, su UV y la densidad por ángulo sólido con que se
+    // eligió. Las esferas se muestrean dentro del cono que ocupan (ninguna muestra cae en la
+    // cara oculta); las caras y triángulos, uniformes por área
+    pub fn sample_from(&self, origin: Vec3, u1: f32, u2: f32) -> Option<(Vec3, (f32, f32), f32)> {
+        let (point, (a, b)) = match self.shape {
+            AreaLightShape::Sphere { center, radius } => {
+                let to_center = center - origin;
+                let distance_squared = to_center.length_squared();
+                if distance_squared > radius * radius {
+                    let distance = distance_squared.sqrt();
+                    let sin2_max = radius * radius / distance_squared;
+                    let one_minus_cos_max = sin2_max / (1.0 + (1.0 - sin2_max).sqrt());
+                    let one_minus_cos = u1 * one_minus_cos_max;
+                    let cos_theta = 1.0 - one_minus_cos;
+                    let sin_theta = (one_minus_cos * (2.0 - one_minus_cos)).max(0.0).sqrt();
+                    let phi = 2.0 * std::f32::consts::PI * u2;
+                    let w = to_center / distance;
+                    let (u, v) = orthonormal_basis(w);
+                    let direction = u * (sin_theta * phi.cos()) + v * (sin_theta * phi.sin()) + w * cos_theta;
+                    // Primer corte de esa dirección con la esfera
+                    let along = distance * cos_theta;
+                    let t = along - (radius * radius - distance_squared + along * along).max(0.0).sqrt();
+                    let point = origin + direction * t;
+                    return Some((point, sphere_uv((point - center) / radius), 1.0 / (2.0 * std::f32::consts::PI * one_minus_cos_max)));
+                }
+                // Desde adentro se ve toda la esfera: punto uniforme por área
+                let z = 1.0 - 2.0 * u1;
+                let r = (1.0 - z * z).max(0.0).sqrt();
+                let phi = 2.0 * std::f32::consts::PI * u2;
+                let normal = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+                let pdf = self.pdf_from(origin, center + normal * radius)?;
+                return Some((center + normal * radius, sphere_uv(normal), pdf));
+            }
+            AreaLightShape::Quad { corner, edge_u, edge_v } => (corner + edge_u * u1 + edge_v * u2, (u1, u2)),
+            AreaLightShape::Triangle { v0, edge1, edge2 } => {
+                // Reflejar la mitad fuera del triángulo mantiene la densidad uniforme
+                let (a, b) = if u1 + u2 > 1.0 { (1.0 - u1, 1.0 - u2) } else { (u1, u2) };
+                (v0 + edge1 * a + edge2 * b, (a, b))
+            }
+        };
+        let [uv0, uv1, uv2] = self.uvs;
+        let uv = (uv0.0 + (uv1.0 - uv0.0) * a + (uv2.0 - uv0.0) * b, uv0.1 + (uv1.1 - uv0.1) * a + (uv2.1 - uv0.1) * b);
+        let pdf = self.pdf_from(origin, point)?;
+        Some((point, uv, pdf))
+    }
+
+    // Densidad por ángulo sólido con que `sample_from` devuelve `point` visto desde `origin`
+    pub fn pdf_from(&self, origin: Vec3, point: Vec3) -> Option<f32> {
+        let to_point = point - origin;
+        let distance_squared = to_point.length_squared();
+        let normal = match self.shape {
+            AreaLightShape::Sphere { center, radius } => {
+                let center_distance_squared = (center - origin).length_squared();
+                if center_distance_squared > radius * radius {
+                    let sin2_max = radius * radius / center_distance_squared;
+                    let one_minus_cos_max = sin2_max / (1.0 + (1.0 - sin2_max).sqrt());
+                    return Some(1.0 / (2.0 * std::f32::consts::PI * one_minus_cos_max));
+                }
+                (point - center).normalize()
+            }
+            AreaLightShape::Quad { edge_u, edge_v, .. } => edge_u.cross(edge_v).normalize(),
+            AreaLightShape::Triangle { edge1, edge2, .. } => edge1.cross(edge2).normalize(),
+        };
+        let cos_light = normal.dot(to_point).abs() / distance_squared.sqrt();
+        if cos_light < 1e-6 {
+            return None;
+        }
+        Some(distance_squared / (cos_light * self.area()))
+    }
+
+    // Si `point` está sobre la superficie de esta luz (para reconocer la esfera que golpeó un rayo)
+    fn contains(&self, point: Vec3) -> bool {
+        match self.shape {
+            AreaLightShape::Sphere { center, radius } => ((point - center).length() - radius).abs() < 1e-3 * (1.0 + radius),
+            _ => false,
+        }
+    }
+
+    // La misma luz en el espacio de la escena; las esferas solo admiten escala uniforme
+    pub fn transformed(&self, transform: &Transform) -> Option<AreaLight> {
+        let m = &transform.matrix;
+        let shape = match self.shape {
+            AreaLightShape::Sphere { center, radius } => {
+                AreaLightShape::Sphere { center: m.transform_point(center), radius: radius * transform.uniform_scale()? }
+            }
+            AreaLightShape::Quad { corner, edge_u, edge_v } => AreaLightShape::Quad {
+                corner: m.transform_point(corner),
+                edge_u: m.transform_vector(edge_u),
+                edge_v: m.transform_vector(edge_v),
+            },
+            AreaLightShape::Triangle { v0, edge1, edge2 } => AreaLightShape::Triangle {
+                v0: m.transform_point(v0),
+                edge1: m.transform_vector(edge1),
+                edge2: m.transform_vector(edge2),
+            },
+        };
+        Some(AreaLight { shape, material: self.material, uvs: self.uvs })
+    }
+}
+
+// Mapeo UV esférico de Sphere para un punto con normal `normal`
+fn sphere_uv(normal: Vec3) -> (f32, f32) {
+    let theta = (-normal.y).acos();
+    let phi = (-normal.z).atan2(normal.x) + std::f32::consts::PI;
+    (phi / (2.0 * std::f32::consts::PI), theta / std::f32::consts::PI)
+}
+
+// Emisor que se ve pero no ilumina: no se muestrea como luz de área y el trazado de caminos
+// no suma su brillo en los rebotes. Es para los que solo marcan dónde está otra luz (la esfera
+// del sol sigue a la primera luz de la escena y con las dos iluminando habría dos soles)
+pub struct WithoutAreaLights<P: Primitive>(pub P);
+
+impl<P: Primitive> Primitive for WithoutAreaLights<P> {
+    fn intersect(&self, ray: &Ray, time: f32) -> Option<HitInfo> {
+        let mut hit = self.0.intersect(ray, time)?;
+        hit.emitter = Emitter::Proxy;
+        Some(hit)
+    }
+
+    fn get_bounds(&self, time: f32) -> (Vec3, Vec3) {
+        self.0.get_bounds(time)
+    }
+
+    fn is_animated(&self) -> bool {
+        self.0.is_animated()
+    }
+
+    fn spans(&self, ray: &Ray, time: f32) -> Option<Vec<Span>> {
+        self.0.spans(ray, time)
+    }
+
+    fn occluded(&self, ray: &Ray, max_t: f32, time: f32) -> bool {
+        self.0.occluded(ray, max_t, time)
+    }
+}
+
+// Todas las superficies emisivas de la escena, elegidas con probabilidad proporcional a su
+// potencia (área por brillo del material). Así la probabilidad por unidad de área de un punto
+// en una cara o triángulo depende solo de su material, y un rayo que choca con un emisor
+// marcado como luz (ver Emitter) puede calcular con qué densidad lo habría elegido el muestreo
+// de luces sin saber qué superficie es; las esferas, que se muestrean por cono, se reconocen
+// por el punto golpeado
+pub struct AreaLights {
+    lights: Vec<AreaLight>,
+    cumulative: Vec<f32>, // Potencia acumulada hasta cada luz inclusive
+    density: Vec<f32>,    // Probabilidad por área de cada material en caras y triángulos
+    spheres: Vec<usize>,
+}
+
+impl AreaLights {
+    pub fn new() -> Self {
+        AreaLights { lights: Vec::new(), cumulative: Vec::new(), density: Vec::new(), spheres: Vec::new() }
+    }
+
+    pub fn collect(objects: &[Box<dyn Primitive>], materials: &MaterialRegistry) -> Self {
+        let mut found = Vec::new();
+        for object in objects {
+            object.area_lights(materials, &mut found);
+        }
+        let mut lights = Vec::new();
+        let mut cumulative = Vec::new();
+        let mut total = 0.0;
+        for light in found {
+            let power = light.area() * luminance(materials[light.material].emissive);
+            if power > 0.0 && power.is_finite() {
+                total += power;
+                cumulative.push(total);
+                lights.push(light);
+            }
+        }
+        let mut density = Vec::new();
+        let mut spheres = Vec::new();
+        for (index, light) in lights.iter().enumerate() {
+            if let AreaLightShape::Sphere { .. } = light.shape {
+                spheres.push(index);
+                continue;
+            }
+            let material = light.material as usize;
+            if density.len() <= material {
+                density.resize(material + 1, 0.0);
+            }
+            density[material] = luminance(materials[light.material].emissive) / total;
+        }
+        AreaLights { lights, cumulative, density, spheres }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    // Elige una luz con `u` y un punto sobre ella con `u1`, `u2` visto desde `origin`
+    pub fn sample(&self, origin: Vec3, u: f32, u1: f32, u2: f32) -> Option<LightSample> {
+        let total = *self.cumulative.last()?;
+        let index = self.cumulative.partition_point(|&c| c <= u * total).min(self.lights.len() - 1);
+        let light = &self.lights[index];
+        let (point, uv, pdf) = light.sample_from(origin, u1, u2)?;
+        Some(LightSample { point, uv, material: light.material, pdf: self.power(index) / total * pdf })
+    }
+
+    fn power(&self, index: usize) -> f32 {
+        self.cumulative[index] - if index > 0 { self.cumulative[index - 1] } else { 0.0 }
+    }
+
+    // Densidad por ángulo sólido con que `sample` desde `origin` habría devuelto el punto
+    // emisivo `hit` que encontró un rayo; 0 si la superficie no es una de las luces
+    pub fn pdf(&self, origin: Vec3, hit: &HitInfo) -> f32 {
+        let (point, normal, material) = (hit.point, hit.normal, hit.material);
+        let density = match hit.emitter {
+            Emitter::Unsampled | Emitter::Proxy => return 0.0,
+            Emitter::Sphere => {
+                let total = self.cumulative.last().copied().unwrap_or(0.0);
+                return self.spheres.iter()
+                    .map(|&index| (index, &self.lights[index]))
+                    .find(|(_, light)| light.material == material && light.contains(point))
+                    .and_then(|(index, light)| Some(self.power(index) / total * light.pdf_from(origin, point)?))
+                    .unwrap_or(0.0);
+            }
+            Emitter::Surface => self.density.get(material as usize).copied().unwrap_or(0.0),
+        };
+        if density == 0.0 {
+            return 0.0;
+        }
+        let to_point = point - origin;
+        let distance_squared = to_point.length_squared();
+        let cos_light = normal.dot(to_point).abs() / distance_squared.sqrt();
+        if cos_light < 1e-6 {
+            return 0.0;
+        }
+        density * distance_squared / cos_light
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Material;
+    use crate::shapes::{Cube, SpinningCube, Sphere};
+
+    // Generador fijo para que las pruebas sean reproducibles
+    fn lcg(state: &mut u32) -> f32 {
+        *state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+        (*state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    // Un brillo distinto por material para que la elección entre luces no sea pareja
+    fn emissive_materials() -> MaterialRegistry {
+        let mut materials = MaterialRegistry::new();
+        materials.add(Material::emissive(Vec3::new(1.0, 0.8, 0.5), 4.0));
+        materials.add(Material::emissive(Vec3::new(0.3, 0.5, 1.0), 1.5));
+        materials
+    }
+
+    fn hit_at(point: Vec3, normal: Vec3, material: MaterialId, emitter: Emitter) -> HitInfo {
+        HitInfo { t: 1.0, point, normal, material, uv: (0.0, 0.0), emitter }
+    }
+
+    // Normal de la cara de un cubo sin rotar en la que está `point`
+    fn cube_normal(center: Vec3, point: Vec3) -> Vec3 {
+        let d = point - center;
+        if d.x.abs() >= d.y.abs().max(d.z.abs()) {
+            Vec3::new(d.x.signum(), 0.0, 0.0)
+        } else if d.y.abs() >= d.z.abs() {
+            Vec3::new(0.0, d.y.signum(), 0.0)
+        } else {
+            Vec3::new(0.0, 0.0, d.z.signum())
+        }
+    }
+
+    // Cada muestra debe traer la misma densidad que `pdf` da después para ese punto
+    fn assert_sample_pdf_matches(lights: &AreaLights, origin: Vec3, normal_at: impl Fn(&LightSample) -> (Vec3, Emitter)) {
+        let mut state = 5;
+        let mut samples = 0;
+        for _ in 0..500 {
+            let Some(sample) = lights.sample(origin, lcg(&mut state), lcg(&mut state), lcg(&mut state)) else { continue };
+            let (normal, emitter) = normal_at(&sample);
+            let pdf = lights.pdf(origin, &hit_at(sample.point, normal, sample.material, emitter));
+            assert!(sample.pdf > 0.0 && sample.pdf.is_finite(), "densidad inválida: {}", sample.pdf);
+            assert!((pdf - sample.pdf).abs() <= 1e-3 * sample.pdf, "pdf {} distinto de la muestra {} en {:?}", pdf, sample.pdf, sample.point);
+            samples += 1;
+        }
+        assert!(samples > 400, "demasiadas muestras descartadas: {samples}");
+    }
+
+    #[test]
+    fn sphere_pdf_matches_sample_from_outside_and_inside() {
+        let materials = emissive_materials();
+        let (center, radius) = (Vec3::new(1.0, 2.0, -1.0), 1.5);
+        let objects: Vec<Box<dyn Primitive>> = vec![Box::new(Sphere::new(center, radius, 0))];
+        let lights = AreaLights::collect(&objects, &materials);
+        let normal_at = |sample: &LightSample| ((sample.point - center) / radius, Emitter::Sphere);
+
+        assert_sample_pdf_matches(&lights, Vec3::new(6.0, -1.0, 3.0), normal_at);
+        assert_sample_pdf_matches(&lights, center + Vec3::new(0.3, -0.5, 0.2), normal_at);
+    }
+
+    #[test]
+    fn quad_pdf_matches_sample() {
+        let materials = emissive_materials();
+        let center = Vec3::new(0.0, 1.0, 0.0);
+        let objects: Vec<Box<dyn Primitive>> = vec![Box::new(Cube::new(center, 2.0, 0))];
+        let lights = AreaLights::collect(&objects, &materials);
+        assert_eq!(lights.len(), 6);
+
+        assert_sample_pdf_matches(&lights, Vec3::new(3.0, 2.5, 4.0), |sample| (cube_normal(center, sample.point), Emitter::Surface));
+    }
+
+    #[test]
+    fn mixed_lights_share_the_selection_probability() {
+        let materials = emissive_materials();
+        let (sphere_center, radius) = (Vec3::new(-3.0, 1.0, 0.0), 0.7);
+        let cube_center = Vec3::new(2.0, 0.5, 1.0);
+        let objects: Vec<Box<dyn Primitive>> = vec![
+            Box::new(Sphere::new(sphere_center, radius, 0)),
+            Box::new(Cube::new(cube_center, 1.0, 1)),
+        ];
+        let lights = AreaLights::collect(&objects, &materials);
+
+        assert_sample_pdf_matches(&lights, Vec3::new(0.0, 4.0, 5.0), |sample| {
+            if sample.material == 0 {
+                ((sample.point - sphere_center) / radius, Emitter::Sphere)
+            } else {
+                (cube_normal(cube_center, sample.point), Emitter::Surface)
+            }
+        });
+    }
+
+    #[test]
+    fn surfaces_outside_the_list_have_zero_pdf() {
+        // Un cubo que gira con el mismo material que un bloque muestreado no es una luz
+        let materials = emissive_materials();
+        let objects: Vec<Box<dyn Primitive>> = vec![
+            Box::new(Cube::new(Vec3::new(0.0, 0.0, 0.0), 1.0, 0)),
+            Box::new(SpinningCube::new(Vec3::new(3.0, 0.0, 0.0), 1.0, 0, 1.0)),
+            Box::new(WithoutAreaLights(Sphere::new(Vec3::new(-3.0, 0.0, 0.0), 0.5, 0))),
+        ];
+        let lights = AreaLights::collect(&objects, &materials);
+        assert_eq!(lights.len(), 6, "solo las caras del cubo fijo son luces");
+
+        let origin = Vec3::new(0.0, 5.0, 0.0);
+        for (target, expected) in [(Vec3::new(0.0, 0.0, 0.0), true), (Vec3::new(3.0, 0.0, 0.0), false), (Vec3::new(-3.0, 0.0, 0.0), false)] {
+            let ray = Ray::new(origin, target - origin);
+            let hit = objects.iter().filter_map(|object| object.intersect(&ray, 0.3)).min_by(|a, b| a.t.total_cmp(&b.t)).unwrap();
+            let pdf = lights.pdf(origin, &hit);
+            assert_eq!(pdf > 0.0, expected, "pdf {} para el objeto en {:?}", pdf, target);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use crate::geometry::Vec3;
+use crate::materials::{Material, MaterialRegistry};
+use crate::voxel::{BlockId, VoxelWorld, AIR};
+
+// Asigna IDs del VoxelWorld a nombres de bloque de Minecraft ("minecraft:netherrack"),
+// registrando el material de cada tipo una sola vez en la tabla de la escena
+pub struct BlockRegistry<'a> {
+    ids: HashMap<String, BlockId>,
+    materials: &'a mut MaterialRegistry,
+}
+
+impl<'a> BlockRegistry<'a> {
+    pub fn new(materials: &'a mut MaterialRegistry) -> Self {
+        BlockRegistry { ids: HashMap::new(), materials }
+    }
+
+    pub fn id_for(&mut self, world: &mut VoxelWorld, name: &str) -> BlockId {
+        let name = block_name(name);
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = match block_material(name) {
+            Some(material) => world.add_block_type(self.materials.add(material)),
+            None => AIR,
+        };
+        self.ids.insert(name.to_string(), id);
+        id
+    }
+}
+
+// Quita el espacio de nombres y las propiedades: "minecraft:lava[level=0]" -> "lava"
+pub fn block_name(name: &str) -> &str {
+    let name = name.split('[').next().unwrap_or(name);
+    name.strip_prefix("minecraft:").unwrap_or(name)
+}
+
+// Material para un bloque; None para aire y para bloques que no ocupan el cubo completo
+// (plantas, antorchas, carteles...), que en la grilla se dibujan como vacío
+pub fn block_material(name: &str) -> Option<Material> {
+    let name = block_name(name);
+    if is_non_solid(name) {
+        return None;
+    }
+
+    let flat = |r: f32, g: f32, b: f32| Material::new().with_properties(Vec3::new(r, g, b), 0.05, 0.0, 0.0);
+    let material = match name {
+        "netherrack" | "nether_quartz_ore" | "nether_gold_ore" => Material::netherrack(),
+        "obsidian" | "crying_obsidian" => Material::obsidian(),
+        "lava" => Material::lava(),
+        "nether_portal" => Material::nether_portal(),
+        "glowstone" => Material::glowstone(),
+        "stone" | "cobblestone" | "andesite" | "stone_bricks" => Material::stone(),
+        "shroomlight" => Material::emissive(Vec3::new(1.0, 0.6, 0.3), 3.0),
+        "magma_block" => Material::emissive(Vec3::new(0.8, 0.25, 0.05), 2.0),
+        // Dieléctricos: el tinte por espesor es el color tras atravesar un bloque entero
+        "water" => Material::new()
+            .with_properties(Vec3::new(0.15, 0.3, 0.7), 0.5, 0.7, 0.2)
+            .with_refraction_index(1.33)
+            .with_absorption(Vec3::new(0.45, 0.75, 0.85), 1.0),
+        "ice" | "packed_ice" => Material::new()
+            .with_properties(Vec3::new(0.6, 0.75, 0.95), 0.6, 0.5, 0.2)
+            .with_refraction_index(1.31)
+            .with_absorption(Vec3::new(0.8, 0.9, 0.97), 1.0),
+        "glass" => Material::new()
+            .with_properties(Vec3::new(0.9, 0.95, 1.0), 0.8, 0.85, 0.1)
+            .with_refraction_index(1.5)
+            .with_absorption(Vec3::new(0.92, 0.97, 0.95), 1.0),
+        "tinted_glass" => stained_glass(Vec3::new(0.2, 0.17, 0.23)),
+        "gold_block" | "raw_gold_block" => Material::new()
+            .with_metallic_roughness(Vec3::new(1.0, 0.78, 0.34), 1.0, 0.3),
+        "iron_block" | "raw_iron_block" => Material::new()
+            .with_metallic_roughness(Vec3::new(0.77, 0.78, 0.78), 1.0, 0.4),
+        "copper_block" | "raw_copper_block" => Material::new()
+            .with_metallic_roughness(Vec3::new(0.95, 0.64, 0.54), 1.0, 0.35),
+        "bedrock" => flat(0.25, 0.25, 0.25),
+        "deepslate" | "cobbled_deepslate" | "tuff" => flat(0.3, 0.3, 0.33),
+        "granite" => flat(0.6, 0.42, 0.36),
+        "diorite" | "calcite" | "quartz_block" => flat(0.85, 0.85, 0.83),
+        "dirt" | "coarse_dirt" | "rooted_dirt" | "farmland" => flat(0.45, 0.32, 0.22),
+        "grass_block" => flat(0.35, 0.6, 0.25),
+        "sand" => flat(0.86, 0.8, 0.6),
+        "red_sand" => flat(0.75, 0.4, 0.2),
+        "gravel" => flat(0.5, 0.48, 0.47),
+        "clay" => flat(0.62, 0.65, 0.72),
+        "snow_block" | "snow" => flat(0.95, 0.97, 1.0),
+        "soul_sand" | "soul_soil" => flat(0.32, 0.24, 0.2),
+        "basalt" | "polished_basalt" | "smooth_basalt" => flat(0.3, 0.3, 0.32),
+        "blackstone" | "polished_blackstone" | "gilded_blackstone" => flat(0.17, 0.14, 0.17),
+        "nether_bricks" | "nether_brick_fence" | "red_nether_bricks" => flat(0.27, 0.13, 0.15),
+        "crimson_nylium" => flat(0.55, 0.1, 0.1),
+        "warped_nylium" => flat(0.15, 0.45, 0.4),
+        "nether_wart_block" => flat(0.5, 0.05, 0.05),
+        "warped_wart_block" => flat(0.1, 0.5, 0.5),
+        "crimson_stem" | "crimson_hyphae" => flat(0.45, 0.15, 0.2),
+        "warped_stem" | "warped_hyphae" => flat(0.2, 0.35, 0.4),
+        "ancient_debris" => flat(0.4, 0.3, 0.27),
+        "bone_block" => flat(0.88, 0.86, 0.75),
+        _ if name.ends_with("_stained_glass") => stained_glass(dye_color(name.trim_end_matches("_stained_glass"))),
+        _ if name.ends_with("_log") || name.ends_with("_wood") => flat(0.4, 0.3, 0.18),
+        _ if name.ends_with("_planks") => flat(0.65, 0.5, 0.3),
+        _ if name.ends_with("_leaves") => flat(0.2, 0.45, 0.15),
+        _ if name.ends_with("_ore") => Material::stone(),
+        _ => {
+            // Bloque sin color conocido: gris con un matiz estable según el nombre
+            let hash = name.bytes().fold(2166136261u32, |h, b| (h ^ b as u32).wrapping_mul(16777619));
+            let tint = |shift: u32| 0.45 + ((hash >> shift) & 0xff) as f32 / 255.0 * 0.25;
+            flat(tint(0), tint(8), tint(16))
+        }
+    };
+    Some(material)
+}
+
+fn stained_glass(color: Vec3) -> Material {
+    Material::new()
+        .with_properties(color, 0.8, 0.85, 0.0)
+        .with_refraction_index(1.5)
+        .with_absorption(color, 1.0)
+}
+
+// Colores de los 16 tintes del juego
+fn dye_color(dye: &str) -> Vec3 {
+    let (r, g, b) = match dye {
+        "white" => (0.95, 0.95, 0.95),
+        "orange" => (0.95, 0.55, 0.2),
+        "magenta" => (0.8, 0.35, 0.8),
+        "light_blue" => (0.45, 0.7, 0.9),
+        "yellow" => (0.95, 0.9, 0.3),
+        "lime" => (0.5, 0.8, 0.2),
+        "pink" => (0.95, 0.55, 0.7),
+        "gray" => (0.3, 0.3, 0.3),
+        "light_gray" => (0.6, 0.6, 0.6),
+        "cyan" => (0.2, 0.55, 0.6),
+        "purple" => (0.5, 0.25, 0.7),
+        "blue" => (0.2, 0.3, 0.75),
+        "brown" => (0.45, 0.3, 0.2),
+        "green" => (0.3, 0.45, 0.15),
+        "red" => (0.7, 0.2, 0.2),
+        "black" => (0.1, 0.1, 0.1),
+        _ => (0.9, 0.9, 0.9),
+    };
+    Vec3::new(r, g, b)
+}
+
+fn is_non_solid(name: &str) -> bool {
+    const EXACT: &[&str] = &[
+        "air", "cave_air", "void_air", "grass", "short_grass", "tall_grass", "fern", "large_fern",
+        "dead_bush", "vine", "fire", "soul_fire", "lever", "ladder", "redstone_wire", "tripwire",
+        "seagrass", "tall_seagrass", "kelp", "kelp_plant", "sugar_cane", "cobweb", "light",
+        "structure_void", "nether_sprouts", "weeping_vines", "weeping_vines_plant",
+        "twisting_vines", "twisting_vines_plant",
+    ];
+    const SUFFIXES: &[&str] = &[
+        "_flower", "_sapling", "torch", "_button", "_sign", "rail", "_pressure_plate", "_banner",
+        "_carpet", "_roots", "_fungus", "_mushroom", "_coral", "_coral_fan", "_tulip", "_orchid",
+    ];
+    EXACT.contains(&name)
+        || SUFFIXES.iter().any(|s| name.ends_with(s))
+        || matches!(name, "dandelion" | "poppy" | "allium" | "azure_bluet" | "oxeye_daisy" | "cornflower" | "lily_of_the_valley")
+}
@@ -0,0 +1,366 @@
+use std::f32::consts::PI;
+use std::sync::OnceLock;
+
+use crate::geometry::*;
+use crate::materials::Material;
+
+// Rugosidad mínima (alfa de GGX): un lóbulo más fino ya no deja brillo de las luces puntuales
+// y sus densidades se salen del rango de f32
+const MIN_ALPHA: f32 = 0.002;
+// Puntos por lado de la tabla de albedo especular, en coseno de la vista y en rugosidad
+const ALBEDO_SIZE: usize = 32;
+// Muestras por lado (estratificadas) con que se integra cada punto de la tabla
+const ALBEDO_SAMPLES: usize = 16;
+
+// BSDF metallic-roughness de la parte opaca de una superficie: difuso de Lambert más un lóbulo
+// especular de microfacetas con distribución GGX, enmascaramiento de Smith y Fresnel de
+// Schlick. Los metales tiñen el reflejo con su color y no tienen difuso; los dieléctricos
+// reflejan según `specular` (0.5 es el 4% habitual) y lo que no reflejan pasa al difuso. Todas
+// las direcciones salen del punto: `view` hacia el observador y `light` hacia la luz
+pub struct Bsdf {
+    pub diffuse: Vec3,
+    f0: Vec3,   // Reflectancia a incidencia normal
+    alpha: f32, // Rugosidad al cuadrado
+}
+
+impl Bsdf {
+    pub fn new(material: &Material, albedo: Vec3) -> Self {
+        let metallic = material.metallic.clamp(0.0, 1.0);
+        let dielectric = 0.08 * material.specular.clamp(0.0, 1.0);
+        Bsdf {
+            diffuse: albedo * (1.0 - metallic),
+            f0: Vec3::new(dielectric, dielectric, dielectric).interpolate(albedo, metallic),
+            alpha: (material.roughness * material.roughness).max(MIN_ALPHA),
+        }
+    }
+
+    // Cuánto refleja el lóbulo especular visto con coseno `cos_view`, para decidir si vale la
+    // pena trazar el reflejo
+    pub fn reflectance(&self, cos_view: f32) -> Vec3 {
+        schlick(self.f0, cos_view)
+    }
+
+    // BSDF por el coseno de la luz: lo que llega por `light` y sale hacia `view`
+    pub fn eval(&self, normal: Vec3, view: Vec3, light: Vec3) -> Vec3 {
+        self.eval_diffuse(normal, view, light) + self.eval_specular(normal, view, light)
+    }
+
+    // Solo la parte difusa, para luces que el reflejo trazado ya encuentra por su cuenta. Recibe
+    // lo que el lóbulo especular no refleja en total desde `view`, así entre los dos nunca
+    // devuelven más luz de la que llega (con el Fresnel de cada luz, una superficie lisa vista
+    // de costado reflejaba casi todo y además seguía siendo difusa)
+    pub fn eval_diffuse(&self, normal: Vec3, view: Vec3, light: Vec3) -> Vec3 {
+        let (n_v, n_l) = (normal.dot(view), normal.dot(light));
+        if n_v <= 0.0 || n_l <= 0.0 {
+            return Vec3::zero();
+        }
+        let (scale, bias) = specular_albedo(n_v, self.alpha);
+        let reflected = self.f0 * scale + Vec3::new(bias, bias, bias);
+        self.diffuse * (Vec3::new(1.0, 1.0, 1.0) - reflected) * (n_l / PI)
+    }
+
+    fn eval_specular(&self, normal: Vec3, view: Vec3, light: Vec3) -> Vec3 {
+        let (n_v, n_l) = (normal.dot(view), normal.dot(light));
+        if n_v <= 0.0 || n_l <= 0.0 {
+            return Vec3::zero();
+        }
+        let half = (view + light).normalize();
+        let fresnel = schlick(self.f0, view.dot(half).max(0.0));
+        // D·G·F / (4 n·v n·l), ya multiplicado por n·l
+        fresnel * (ggx(normal.dot(half), self.alpha) * self.masking(n_v, n_l) / (4.0 * n_v))
+    }
+
+    // Densidad por ángulo sólido con que `sample` elige `light`
+    pub fn pdf(&self, normal: Vec3, view: Vec3, light: Vec3) -> f32 {
+        let (n_v, n_l) = (normal.dot(view), normal.dot(light));
+        if n_v <= 0.0 || n_l <= 0.0 {
+            return 0.0;
+        }
+        let half = (view + light).normalize();
+        // Normales visibles: D·G1(v)·(v·h)/(n·v) por el jacobiano 1/(4 v·h) del reflejo
+        let specular = ggx(normal.dot(half), self.alpha) / ((1.0 + lambda(n_v, self.alpha)) * 4.0 * n_v);
+        let p = self.specular_probability(n_v);
+        p * specular + (1.0 - p) * n_l / PI
+    }
+
+    // Elige con `u` el lóbulo y con `u1`, `u2` una dirección de llegada: dirección, BSDF por
+    // coseno sobre densidad, y densidad
+    pub fn sample(&self, normal: Vec3, view: Vec3, u: f32, u1: f32, u2: f32) -> Option<(Vec3, Vec3, f32)> {
+        let n_v = normal.dot(view);
+        if n_v <= 0.0 {
+            return None;
+        }
+        let light = if u < self.specular_probability(n_v) {
+            (-view).reflect(self.sample_half(normal, view, u1, u2))
+        } else {
+            // Coseno: un punto uniforme del disco unitario proyectado al hemisferio
+            let (tangent, bitangent) = orthonormal_basis(normal);
+            let (r, phi) = (u1.sqrt(), 2.0 * PI * u2);
+            tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * (1.0 - u1).max(0.0).sqrt()
+        };
+        let pdf = self.pdf(normal, view, light);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some((light, self.eval(normal, view, light) / pdf, pdf))
+    }
+
+    // Solo el lóbulo especular, para el reflejo del modo Whitted: dirección y peso F·G2/G1,
+    // que es lo que queda de BSDF por coseno sobre densidad
+    pub fn sample_specular(&self, normal: Vec3, view: Vec3, u1: f32, u2: f32) -> Option<(Vec3, Vec3)> {
+        let n_v = normal.dot(view);
+        if n_v <= 0.0 {
+            return None;
+        }
+        let half = self.sample_half(normal, view, u1, u2);
+        let light = (-view).reflect(half);
+        let n_l = normal.dot(light);
+        if n_l <= 0.0 {
+            return None;
+        }
+        let weight = self.masking(n_v, n_l) * (1.0 + lambda(n_v, self.alpha));
+        Some((light, schlick(self.f0, view.dot(half).max(0.0)) * weight))
+    }
+
+    // Probabilidad de muestrear el lóbulo especular según cuánto aporta frente al difuso
+    fn specular_probability(&self, n_v: f32) -> f32 {
+        let specular = luminance(schlick(self.f0, n_v));
+        let diffuse = luminance(self.diffuse) * (1.0 - specular);
+        if diffuse <= 0.0 {
+            return 1.0;
+        }
+        (specular / (specular + diffuse)).clamp(0.1, 0.9)
+    }
+
+    // Smith correlado en altura: G2 = 1 / (1 + Λ(v) + Λ(l))
+    fn masking(&self, n_v: f32, n_l: f32) -> f32 {
+        1.0 / (1.0 + lambda(n_v, self.alpha) + lambda(n_l, self.alpha))
+    }
+
+    // Normal de microfaceta entre las visibles desde `view` (Heitz 2018): se estira la vista
+    // para que la GGX quede como un hemisferio y se elige un punto de su proyección
+    fn sample_half(&self, normal: Vec3, view: Vec3, u1: f32, u2: f32) -> Vec3 {
+        let (tangent, bitangent) = orthonormal_basis(normal);
+        let local = Vec3::new(view.dot(tangent) * self.alpha, view.dot(bitangent) * self.alpha, view.dot(normal)).normalize();
+        let length_squared = local.x * local.x + local.y * local.y;
+        let t1 = if length_squared > 1e-7 { Vec3::new(-local.y, local.x, 0.0) / length_squared.sqrt() } else { Vec3::new(1.0, 0.0, 0.0) };
+        let t2 = local.cross(t1);
+        let (r, phi) = (u1.sqrt(), 2.0 * PI * u2);
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + local.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).max(0.0).sqrt() + s * r * phi.sin();
+        let m = t1 * p1 + t2 * p2 + local * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+        let half = Vec3::new(m.x * self.alpha, m.y * self.alpha, m.z.max(1e-6)).normalize();
+        tangent * half.x + bitangent * half.y + normal * half.z
+    }
+}
+
+// Fracción de la luz que el lóbulo especular refleja en total visto con coseno `n_v`, como
+// f0·escala + sesgo (Schlick es lineal en f0). Se integra una sola vez, para una tabla de
+// cosenos y rugosidades, con las mismas normales visibles y pesos que `sample_specular`; entre
+// puntos de la tabla se interpola
+fn specular_albedo(n_v: f32, alpha: f32) -> (f32, f32) {
+    static TABLE: OnceLock<Vec<(f32, f32)>> = OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let mut table = Vec::with_capacity(ALBEDO_SIZE * ALBEDO_SIZE);
+        for j in 0..ALBEDO_SIZE {
+            let roughness = j as f32 / (ALBEDO_SIZE - 1) as f32;
+            let bsdf = Bsdf { diffuse: Vec3::zero(), f0: Vec3::zero(), alpha: (roughness * roughness).max(MIN_ALPHA) };
+            for i in 0..ALBEDO_SIZE {
+                let n_v = (i as f32 / (ALBEDO_SIZE - 1) as f32).max(1e-3);
+                let view = Vec3::new((1.0 - n_v * n_v).sqrt(), 0.0, n_v);
+                let (mut scale, mut bias) = (0.0, 0.0);
+                for s in 0..ALBEDO_SAMPLES * ALBEDO_SAMPLES {
+                    let u1 = ((s / ALBEDO_SAMPLES) as f32 + 0.5) / ALBEDO_SAMPLES as f32;
+                    let u2 = ((s % ALBEDO_SAMPLES) as f32 + 0.5) / ALBEDO_SAMPLES as f32;
+                    let half = bsdf.sample_half(normal, view, u1, u2);
+                    let n_l = normal.dot((-view).reflect(half));
+                    if n_l <= 0.0 {
+                        continue;
+                    }
+                    let weight = bsdf.masking(n_v, n_l) * (1.0 + lambda(n_v, bsdf.alpha));
+                    let k = (1.0 - view.dot(half)).clamp(0.0, 1.0).powi(5);
+                    scale += weight * (1.0 - k);
+                    bias += weight * k;
+                }
+                let count = (ALBEDO_SAMPLES * ALBEDO_SAMPLES) as f32;
+                table.push((scale / count, bias / count));
+            }
+        }
+        table
+    });
+    let x = (n_v * (ALBEDO_SIZE - 1) as f32).clamp(0.0, (ALBEDO_SIZE - 1) as f32);
+    let y = (alpha.sqrt() * (ALBEDO_SIZE - 1) as f32).clamp(0.0, (ALBEDO_SIZE - 1) as f32);
+    let (i, j) = ((x as usize).min(ALBEDO_SIZE - 2), (y as usize).min(ALBEDO_SIZE - 2));
+    let (fx, fy) = (x - i as f32, y - j as f32);
+    let at = |i: usize, j: usize| table[j * ALBEDO_SIZE + i];
+    let lerp = |a: (f32, f32), b: (f32, f32), f: f32| (a.0 + (b.0 - a.0) * f, a.1 + (b.1 - a.1) * f);
+    lerp(lerp(at(i, j), at(i + 1, j), fx), lerp(at(i, j + 1), at(i + 1, j + 1), fx), fy)
+}
+
+// Reflectancia de Fresnel exacta (sin polarizar) de la superficie entre dos dieléctricos, con
+// `cos_i` el coseno de incidencia y `eta` el cociente de índices de refracción del lado del
+// rayo sobre el del otro lado; 1 si hay reflexión interna total
+pub fn fresnel_dielectric(cos_i: f32, eta: f32) -> f32 {
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let perpendicular = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let parallel = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    0.5 * (perpendicular * perpendicular + parallel * parallel)
+}
+
+fn schlick(f0: Vec3, cosine: f32) -> Vec3 {
+    let k = (1.0 - cosine).clamp(0.0, 1.0).powi(5);
+    f0 + (Vec3::new(1.0, 1.0, 1.0) - f0) * k
+}
+
+// Distribución de normales GGX / Trowbridge-Reitz
+fn ggx(n_h: f32, alpha: f32) -> f32 {
+    if n_h <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    let d = n_h * n_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+// Λ de Smith para GGX, con `cosine` el coseno entre la dirección y la normal
+fn lambda(cosine: f32, alpha: f32) -> f32 {
+    let c2 = cosine * cosine;
+    let tan2 = (1.0 - c2).max(0.0) / c2.max(1e-8);
+    0.5 * ((1.0 + alpha * alpha * tan2).sqrt() - 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generador fijo para que las pruebas sean reproducibles
+    fn lcg(state: &mut u32) -> f32 {
+        *state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+        (*state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn bsdf(roughness: f32, metallic: f32) -> Bsdf {
+        let mut material = Material::new();
+        material.roughness = roughness;
+        material.metallic = metallic;
+        Bsdf::new(&material, Vec3::new(1.0, 1.0, 1.0))
+    }
+
+    // Vista con coseno `cos_view` respecto de la normal (0, 0, 1)
+    fn view_at(cos_view: f32) -> Vec3 {
+        Vec3::new((1.0 - cos_view * cos_view).sqrt(), 0.0, cos_view)
+    }
+
+    const NORMAL: Vec3 = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+
+    #[test]
+    fn sample_agrees_with_eval_and_pdf() {
+        let mut state = 1;
+        for (roughness, metallic) in [(0.1, 0.0), (0.5, 0.0), (0.9, 0.3), (0.3, 1.0)] {
+            let bsdf = bsdf(roughness, metallic);
+            for cos_view in [0.1, 0.5, 0.95] {
+                let view = view_at(cos_view);
+                for _ in 0..200 {
+                    let Some((light, weight, pdf)) = bsdf.sample(NORMAL, view, lcg(&mut state), lcg(&mut state), lcg(&mut state)) else { continue };
+                    let expected_pdf = bsdf.pdf(NORMAL, view, light);
+                    assert!((pdf - expected_pdf).abs() <= 1e-4 * expected_pdf, "pdf {} contra {}", pdf, expected_pdf);
+                    let expected = bsdf.eval(NORMAL, view, light) / expected_pdf;
+                    assert!((weight - expected).length() <= 1e-4 * (1.0 + expected.length()), "peso {:?} contra {:?}", weight, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pdf_matches_the_sampled_directions() {
+        // Si `pdf` es la densidad con que `sample` elige, el promedio de n·l / pdf estima
+        // la integral del coseno sobre el hemisferio, que es π
+        let mut state = 2;
+        for (roughness, metallic) in [(0.3, 0.0), (0.7, 0.0), (0.6, 1.0)] {
+            let bsdf = bsdf(roughness, metallic);
+            for cos_view in [0.3, 0.9] {
+                let view = view_at(cos_view);
+                let count = 100_000;
+                let total: f32 = (0..count)
+                    .filter_map(|_| bsdf.sample(NORMAL, view, lcg(&mut state), lcg(&mut state), lcg(&mut state)))
+                    .map(|(light, _, pdf)| NORMAL.dot(light) / pdf)
+                    .sum();
+                let estimate = total / count as f32;
+                assert!((estimate - PI).abs() < 0.05 * PI, "rugosidad {roughness}, metálico {metallic}, n·v {cos_view}: {estimate}");
+            }
+        }
+    }
+
+    #[test]
+    fn white_furnace_does_not_create_energy() {
+        let mut state = 3;
+        for roughness in [0.05, 0.3, 0.6, 1.0] {
+            for metallic in [0.0, 0.5, 1.0] {
+                let bsdf = bsdf(roughness, metallic);
+                for cos_view in [0.05, 0.4, 1.0] {
+                    let view = view_at(cos_view);
+                    let count = 20_000;
+                    let total = (0..count)
+                        .filter_map(|_| bsdf.sample(NORMAL, view, lcg(&mut state), lcg(&mut state), lcg(&mut state)))
+                        .fold(Vec3::zero(), |acc, (_, weight, _)| acc + weight);
+                    let albedo = total / count as f32;
+                    for channel in [albedo.x, albedo.y, albedo.z] {
+                        assert!(channel <= 1.01, "rugosidad {roughness}, metálico {metallic}, n·v {cos_view}: refleja {channel}");
+                        // Un dieléctrico blanco devuelve en difuso lo que no refleja
+                        if metallic == 0.0 {
+                            assert!(channel >= 0.97, "rugosidad {roughness}, n·v {cos_view}: pierde energía, refleja {channel}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fresnel_dielectric_limits() {
+        let normal_incidence = fresnel_dielectric(1.0, 1.0 / 1.5);
+        assert!((normal_incidence - 0.04).abs() < 1e-4, "a incidencia normal: {normal_incidence}");
+        assert!((fresnel_dielectric(1.0, 1.5) - normal_incidence).abs() < 1e-6, "igual desde los dos lados");
+        // Saliendo del vidrio el ángulo crítico es asin(1/1.5), unos 41.8°
+        assert_eq!(fresnel_dielectric(0.5, 1.5), 1.0);
+        assert_eq!(fresnel_dielectric(0.0, 1.5), 1.0);
+        assert!(fresnel_dielectric(0.8, 1.5) < 1.0);
+        // Entrando, sube hasta 1 recién en incidencia rasante
+        assert!((fresnel_dielectric(0.0, 1.0 / 1.5) - 1.0).abs() < 1e-4);
+        let mut previous = 0.0;
+        for cos_i in [1.0, 0.8, 0.6, 0.4, 0.2, 0.05] {
+            let reflectance = fresnel_dielectric(cos_i, 1.0 / 1.5);
+            assert!(reflectance >= previous, "crece hacia el borde");
+            previous = reflectance;
+        }
+    }
+
+    #[test]
+    fn finest_lobe_stays_finite() {
+        let bsdf = bsdf(0.0, 1.0);
+        assert_eq!(bsdf.alpha, MIN_ALPHA);
+        let mut state = 4;
+        for cos_view in [0.001, 0.05, 0.5, 1.0] {
+            let view = view_at(cos_view);
+            for _ in 0..500 {
+                let (u1, u2) = (lcg(&mut state), lcg(&mut state));
+                if let Some((light, weight)) = bsdf.sample_specular(NORMAL, view, u1, u2) {
+                    assert!(NORMAL.dot(light) > 0.0);
+                    for channel in [weight.x, weight.y, weight.z] {
+                        assert!(channel.is_finite() && (0.0..=1.0 + 1e-4).contains(&channel), "n·v {cos_view}: peso {:?}", weight);
+                    }
+                }
+                if let Some((_, weight, pdf)) = bsdf.sample(NORMAL, view, lcg(&mut state), u1, u2) {
+                    assert!(pdf.is_finite() && pdf > 0.0, "n·v {cos_view}: pdf {pdf}");
+                    assert!(weight.x.is_finite() && weight.y.is_finite() && weight.z.is_finite(), "n·v {cos_view}: peso {:?}", weight);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,529 @@
+use crate::geometry::*;
+use crate::raytracer::HitInfo;
+
+// BVH sobre una lista de cajas (objetos de la escena o triángulos de una malla), armado con
+// la heurística de área de superficie (SAH) por bins y guardado como un arreglo plano de nodos
+// en orden de profundidad: el hijo izquierdo va justo después de su padre. Las primitivas
+// sin límites finitos (planos infinitos) quedan fuera del árbol y se prueban siempre.
+
+// Costos relativos de visitar un nodo y de probar una primitiva
+const TRAVERSAL_COST: f32 = 1.0;
+const INTERSECT_COST: f32 = 2.0;
+const BINS: usize = 12;
+// Hojas más grandes que esto se parten aunque la SAH diga que no conviene
+const MAX_LEAF_SIZE: usize = 8;
+// Profundidad máxima del árbol, que acota la pila del recorrido; más abajo todo queda en una hoja
+const MAX_DEPTH: usize = 64;
+
+struct BvhNode {
+    bounds_min: Vec3,
+    bounds_max: Vec3,
+    // Hoja: primera posición en `indices`; nodo interior: índice del hijo derecho
+    offset: usize,
+    // Cantidad de primitivas de la hoja, cero en nodos interiores
+    count: usize,
+}
+
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>, // Índices en la lista original, agrupados por hoja
+    unbounded: Vec<usize>,
+    // Nodos con algún elemento que se mueve, de mayor a menor índice: cada hijo va
+    // antes que su padre, así al reajustar las cajas se recorren en este orden
+    dynamic: Vec<usize>,
+}
+
+// Resumen del árbol armado
+pub struct BvhStats {
+    pub nodes: usize,
+    pub leaves: usize,
+    pub max_depth: usize,
+    pub min_leaf: usize,
+    pub max_leaf: usize,
+    pub average_leaf: f32,
+    // Costo esperado de un rayo según la SAH, en unidades de INTERSECT_COST y TRAVERSAL_COST
+    pub sah_cost: f32,
+    pub unbounded: usize,
+    // Nodos que se reajustan en cada cuadro
+    pub dynamic: usize,
+}
+
+struct Item {
+    index: usize,
+    bounds: (Vec3, Vec3),
+    centroid: Vec3,
+}
+
+const EMPTY: (Vec3, Vec3) = (
+    Vec3 { x: f32::INFINITY, y: f32::INFINITY, z: f32::INFINITY },
+    Vec3 { x: f32::NEG_INFINITY, y: f32::NEG_INFINITY, z: f32::NEG_INFINITY },
+);
+
+fn merge(a: (Vec3, Vec3), b: (Vec3, Vec3)) -> (Vec3, Vec3) {
+    (a.0.min(b.0), a.1.max(b.1))
+}
+
+fn surface_area((min, max): (Vec3, Vec3)) -> f32 {
+    let extent = max - min;
+    if extent.x < 0.0 || extent.y < 0.0 || extent.z < 0.0 {
+        return 0.0;
+    }
+    2.0 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x)
+}
+
+fn axis_value(v: Vec3, axis: usize) -> f32 {
+    match axis {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    }
+}
+
+fn is_finite((min, max): (Vec3, Vec3)) -> bool {
+    [min.x, min.y, min.z, max.x, max.y, max.z].iter().all(|v| v.is_finite())
+}
+
+impl Bvh {
+    pub fn build(bounds: &[(Vec3, Vec3)]) -> Bvh {
+        let (mut items, mut unbounded) = (Vec::new(), Vec::new());
+        for (index, &b) in bounds.iter().enumerate() {
+            if is_finite(b) {
+                items.push(Item { index, bounds: b, centroid: (b.0 + b.1) * 0.5 });
+            } else {
+                unbounded.push(index);
+            }
+        }
+        let mut nodes = Vec::with_capacity(2 * items.len());
+        if !items.is_empty() {
+            build_recursive(&mut nodes, &mut items, 0, 1);
+        }
+        Bvh { nodes, indices: items.iter().map(|item| item.index).collect(), unbounded, dynamic: Vec::new() }
+    }
+
+    // Indica qué elementos cambian de caja con el tiempo: sus hojas y todos los nodos por
+    // encima son los que `refit` vuelve a calcular. Los elementos sin límites quedan fuera
+    // del árbol y no hace falta marcarlos
+    pub fn mark_dynamic(&mut self, items: &[usize]) {
+        let mut moving = vec![false; self.indices.len() + self.unbounded.len()];
+        for &index in items {
+            moving[index] = true;
+        }
+        let mut dirty = vec![false; self.nodes.len()];
+        for i in (0..self.nodes.len()).rev() {
+            let node = &self.nodes[i];
+            dirty[i] = if node.count > 0 {
+                self.indices[node.offset..node.offset + node.count].iter().any(|&index| moving[index])
+            } else {
+                dirty[i + 1] || dirty[node.offset]
+            };
+        }
+        self.dynamic = (0..self.nodes.len()).rev().filter(|&i| dirty[i]).collect();
+    }
+
+    // Reajusta las cajas de los nodos marcados con las cajas actuales de los elementos, sin
+    // cambiar la forma del árbol; `bounds` da la caja del elemento con ese índice
+    pub fn refit<F: Fn(usize) -> (Vec3, Vec3)>(&mut self, bounds: &F) {
+        for &i in &self.dynamic {
+            let node = &self.nodes[i];
+            let (min, max) = if node.count > 0 {
+                self.indices[node.offset..node.offset + node.count].iter().fold(EMPTY, |acc, &index| merge(acc, bounds(index)))
+            } else {
+                let (left, right) = (&self.nodes[i + 1], &self.nodes[node.offset]);
+                merge((left.bounds_min, left.bounds_max), (right.bounds_min, right.bounds_max))
+            };
+            self.nodes[i].bounds_min = min;
+            self.nodes[i].bounds_max = max;
+        }
+    }
+
+    // `intersect_leaf` prueba el elemento con ese índice de la lista usada al construir el BVH.
+    // El recorrido baja primero al hijo más cercano y descarta las cajas que empiezan más
+    // lejos que el mejor impacto encontrado
+    pub fn intersect<F: Fn(usize) -> Option<HitInfo>>(&self, ray: &Ray, intersect_leaf: &F) -> Option<HitInfo> {
+        let mut best_hit: Option<HitInfo> = None;
+        let mut best_t = f32::INFINITY;
+        let consider = |idx: usize, best_hit: &mut Option<HitInfo>, best_t: &mut f32| {
+            if let Some(hit) = intersect_leaf(idx)
+                && hit.t > 0.001 && hit.t < *best_t {
+                *best_t = hit.t;
+                *best_hit = Some(hit);
+            }
+        };
+        // Los planos infinitos primero: un suelo cercano poda buena parte del árbol
+        for &idx in &self.unbounded {
+            consider(idx, &mut best_hit, &mut best_t);
+        }
+        if self.nodes.is_empty() {
+            return best_hit;
+        }
+
+        let inv_dir = Vec3::new(
+            if ray.direction.x.abs() < 1e-8 { 1e32 } else { 1.0 / ray.direction.x },
+            if ray.direction.y.abs() < 1e-8 { 1e32 } else { 1.0 / ray.direction.y },
+            if ray.direction.z.abs() < 1e-8 { 1e32 } else { 1.0 / ray.direction.z },
+        );
+        let entry = |node: &BvhNode, max_t: f32| ray_aabb_entry(ray.origin, inv_dir, node.bounds_min, node.bounds_max, max_t);
+        if entry(&self.nodes[0], best_t).is_none() {
+            return best_hit;
+        }
+
+        // Hijos lejanos pendientes con su distancia de entrada
+        let mut stack = [(0usize, 0.0f32); MAX_DEPTH];
+        let mut pending = 0;
+        let mut current = 0;
+        loop {
+            let node = &self.nodes[current];
+            let next = if node.count > 0 {
+                for &idx in &self.indices[node.offset..node.offset + node.count] {
+                    consider(idx, &mut best_hit, &mut best_t);
+                }
+                None
+            } else {
+                let (left, right) = (current + 1, node.offset);
+                match (entry(&self.nodes[left], best_t), entry(&self.nodes[right], best_t)) {
+                    (Some(t_left), Some(t_right)) => {
+                        let (near, far, t_far) = if t_left <= t_right { (left, right, t_right) } else { (right, left, t_left) };
+                        stack[pending] = (far, t_far);
+                        pending += 1;
+                        Some(near)
+                    }
+                    (Some(_), None) => Some(left),
+                    (None, Some(_)) => Some(right),
+                    (None, None) => None,
+                }
+            };
+            current = match next {
+                Some(child) => child,
+                None => loop {
+                    if pending == 0 {
+                        return best_hit;
+                    }
+                    pending -= 1;
+                    let (index, t_entry) = stack[pending];
+                    if t_entry < best_t {
+                        break index;
+                    }
+                },
+            };
+        }
+    }
+
+    // Consulta de cualquier impacto para sombras: `occluded_leaf` dice si el elemento corta
+    // el rayo antes de `max_t`, y el recorrido termina con el primero que lo haga, sin
+    // importar el orden
+    pub fn occluded<F: Fn(usize) -> bool>(&self, ray: &Ray, max_t: f32, occluded_leaf: &F) -> bool {
+        if self.unbounded.iter().any(|&idx| occluded_leaf(idx)) {
+            return true;
+        }
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let inv_dir = Vec3::new(
+            if ray.direction.x.abs() < 1e-8 { 1e32 } else { 1.0 / ray.direction.x },
+            if ray.direction.y.abs() < 1e-8 { 1e32 } else { 1.0 / ray.direction.y },
+            if ray.direction.z.abs() < 1e-8 { 1e32 } else { 1.0 / ray.direction.z },
+        );
+        // Nodos pendientes; cada nivel deja a lo sumo un hermano esperando
+        let mut stack = [0usize; MAX_DEPTH + 1];
+        let mut pending = 1;
+        while pending > 0 {
+            pending -= 1;
+            let current = stack[pending];
+            let node = &self.nodes[current];
+            if ray_aabb_entry(ray.origin, inv_dir, node.bounds_min, node.bounds_max, max_t).is_none() {
+                continue;
+            }
+            if node.count > 0 {
+                if self.indices[node.offset..node.offset + node.count].iter().any(|&idx| occluded_leaf(idx)) {
+                    return true;
+                }
+            } else {
+                stack[pending] = node.offset;
+                stack[pending + 1] = current + 1;
+                pending += 2;
+            }
+        }
+        false
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        let mut stats = BvhStats {
+            nodes: self.nodes.len(),
+            leaves: 0,
+            max_depth: 0,
+            min_leaf: 0,
+            max_leaf: 0,
+            average_leaf: 0.0,
+            sah_cost: 0.0,
+            unbounded: self.unbounded.len(),
+            dynamic: self.dynamic.len(),
+        };
+        if self.nodes.is_empty() {
+            return stats;
+        }
+        let root_area = surface_area((self.nodes[0].bounds_min, self.nodes[0].bounds_max)).max(f32::MIN_POSITIVE);
+        stats.min_leaf = usize::MAX;
+        let mut stack = vec![(0, 1)];
+        while let Some((index, depth)) = stack.pop() {
+            let node = &self.nodes[index];
+            // Probabilidad de que un rayo que toca la raíz toque también este nodo
+            let probability = surface_area((node.bounds_min, node.bounds_max)) / root_area;
+            stats.max_depth = stats.max_depth.max(depth);
+            if node.count > 0 {
+                stats.leaves += 1;
+                stats.min_leaf = stats.min_leaf.min(node.count);
+                stats.max_leaf = stats.max_leaf.max(node.count);
+                stats.sah_cost += probability * node.count as f32 * INTERSECT_COST;
+            } else {
+                stats.sah_cost += probability * TRAVERSAL_COST;
+                stack.push((index + 1, depth + 1));
+                stack.push((node.offset, depth + 1));
+            }
+        }
+        stats.average_leaf = self.indices.len() as f32 / stats.leaves as f32;
+        stats
+    }
+}
+
+fn build_recursive(nodes: &mut Vec<BvhNode>, items: &mut [Item], start: usize, depth: usize) -> usize {
+    let bounds = items.iter().fold(EMPTY, |acc, item| merge(acc, item.bounds));
+    let node_index = nodes.len();
+    nodes.push(BvhNode { bounds_min: bounds.0, bounds_max: bounds.1, offset: start, count: items.len() });
+    if depth >= MAX_DEPTH {
+        return node_index;
+    }
+    if let Some(mid) = sah_split(items, bounds) {
+        let (left, right) = items.split_at_mut(mid);
+        build_recursive(nodes, left, start, depth + 1);
+        let right_index = build_recursive(nodes, right, start + mid, depth + 1);
+        nodes[node_index].offset = right_index;
+        nodes[node_index].count = 0;
+    }
+    node_index
+}
+
+// Ordena `items` en dos grupos y devuelve dónde empieza el segundo, o None si conviene
+// dejar una hoja. Los centroides se reparten en bins sobre el eje más largo y se evalúa
+// el costo SAH de cortar entre cada par de bins.
+fn sah_split(items: &mut [Item], bounds: (Vec3, Vec3)) -> Option<usize> {
+    if items.len() <= 1 {
+        return None;
+    }
+    let (centroid_min, centroid_max) = items.iter().fold(EMPTY, |acc, item| merge(acc, (item.centroid, item.centroid)));
+    let extent = centroid_max - centroid_min;
+    let axis = if extent.x > extent.y && extent.x > extent.z { 0 } else if extent.y > extent.z { 1 } else { 2 };
+    let axis_min = axis_value(centroid_min, axis);
+    let axis_extent = axis_value(extent, axis);
+
+    // Todos los centroides en el mismo punto: ningún corte separa nada
+    if axis_extent <= 0.0 {
+        return (items.len() > MAX_LEAF_SIZE).then_some(items.len() / 2);
+    }
+
+    let bin_of = |item: &Item| (((axis_value(item.centroid, axis) - axis_min) / axis_extent * BINS as f32) as usize).min(BINS - 1);
+    let mut bins = [(EMPTY, 0usize); BINS];
+    for item in items.iter() {
+        let bin = &mut bins[bin_of(item)];
+        bin.0 = merge(bin.0, item.bounds);
+        bin.1 += 1;
+    }
+
+    // Área y cantidad de todo lo que queda a la derecha de cada corte
+    let mut right_area = [0.0; BINS];
+    let mut right_count = [0; BINS];
+    let mut acc = (EMPTY, 0);
+    for i in (1..BINS).rev() {
+        acc = (merge(acc.0, bins[i].0), acc.1 + bins[i].1);
+        right_area[i] = surface_area(acc.0);
+        right_count[i] = acc.1;
+    }
+
+    let parent_area = surface_area(bounds).max(f32::MIN_POSITIVE);
+    let mut best = (f32::INFINITY, 0);
+    let mut acc = (EMPTY, 0);
+    for i in 0..BINS - 1 {
+        acc = (merge(acc.0, bins[i].0), acc.1 + bins[i].1);
+        if acc.1 == 0 || right_count[i + 1] == 0 {
+            continue;
+        }
+        let cost = TRAVERSAL_COST
+            + INTERSECT_COST * (surface_area(acc.0) * acc.1 as f32 + right_area[i + 1] * right_count[i + 1] as f32) / parent_area;
+        if cost < best.0 {
+            best = (cost, i);
+        }
+    }
+
+    let leaf_cost = items.len() as f32 * INTERSECT_COST;
+    if items.len() <= MAX_LEAF_SIZE && best.0 >= leaf_cost {
+        return None;
+    }
+
+    let mut mid = 0;
+    for i in 0..items.len() {
+        if bin_of(&items[i]) <= best.1 {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+    if mid == 0 || mid == items.len() {
+        // Centroides amontonados en un bin: mitades por cantidad
+        mid = items.len() / 2;
+        items.select_nth_unstable_by(mid, |a, b| axis_value(a.centroid, axis).total_cmp(&axis_value(b.centroid, axis)));
+    }
+    Some(mid)
+}
+
+// Distancia a la que el rayo entra en la caja (cero si arranca adentro), o None si no la
+// toca antes de `max_t`
+fn ray_aabb_entry(origin: Vec3, inv_dir: Vec3, bmin: Vec3, bmax: Vec3, max_t: f32) -> Option<f32> {
+    let (t0x, t1x) = ((bmin.x - origin.x) * inv_dir.x, (bmax.x - origin.x) * inv_dir.x);
+    let (t0y, t1y) = ((bmin.y - origin.y) * inv_dir.y, (bmax.y - origin.y) * inv_dir.y);
+    let (t0z, t1z) = ((bmin.z - origin.z) * inv_dir.z, (bmax.z - origin.z) * inv_dir.z);
+    let t_enter = t0x.min(t1x).max(t0y.min(t1y)).max(t0z.min(t1z)).max(0.0);
+    let t_exit = t0x.max(t1x).min(t0y.max(t1y)).min(t0z.max(t1z)).min(max_t);
+    (t_enter <= t_exit).then_some(t_enter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::{Cube, Plane, Primitive, SpinningCube, Sphere};
+
+    // Generador fijo para que las pruebas sean reproducibles
+    fn lcg(state: &mut u32) -> f32 {
+        *state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+        (*state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn random_point(state: &mut u32, scale: f32) -> Vec3 {
+        Vec3::new(lcg(state) - 0.5, lcg(state) - 0.5, lcg(state) - 0.5) * scale
+    }
+
+    // Esferas y cubos repartidos al azar más un suelo infinito; el material de cada objeto
+    // es su índice, así se sabe cuál devolvió el impacto
+    fn random_scene(state: &mut u32) -> Vec<Box<dyn Primitive>> {
+        let mut objects: Vec<Box<dyn Primitive>> = Vec::new();
+        for i in 0..120 {
+            let center = random_point(state, 20.0);
+            let size = 0.2 + lcg(state) * 1.5;
+            if i % 2 == 0 {
+                objects.push(Box::new(Sphere::new(center, size, i)));
+            } else {
+                objects.push(Box::new(Cube::new(center, size, i)));
+            }
+        }
+        objects.push(Box::new(Plane::new(Vec3::new(0.0, -8.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 120)));
+        objects
+    }
+
+    // Impacto más cercano probando todos los objetos, como lo haría la escena sin BVH
+    fn brute_force(objects: &[Box<dyn Primitive>], ray: &Ray, time: f32) -> Option<HitInfo> {
+        objects.iter()
+            .filter_map(|object| object.intersect(ray, time))
+            .filter(|hit| hit.t > 0.001)
+            .min_by(|a, b| a.t.total_cmp(&b.t))
+    }
+
+    fn build(objects: &[Box<dyn Primitive>], time: f32) -> Bvh {
+        let bounds: Vec<(Vec3, Vec3)> = objects.iter().map(|object| object.get_bounds(time)).collect();
+        Bvh::build(&bounds)
+    }
+
+    fn assert_same_hit(found: Option<HitInfo>, expected: Option<HitInfo>, ray: &Ray) {
+        match (found, expected) {
+            (Some(found), Some(expected)) => {
+                assert!((found.t - expected.t).abs() < 1e-4, "distinto t para {:?}: {} vs {}", ray.direction, found.t, expected.t);
+                assert_eq!(found.material, expected.material, "distinto objeto para {:?}", ray.direction);
+            }
+            (None, None) => {}
+            (found, expected) => panic!(
+                "el BVH y la fuerza bruta no coinciden para {:?}: {:?} vs {:?}",
+                ray.direction, found.map(|hit| hit.t), expected.map(|hit| hit.t)
+            ),
+        }
+    }
+
+    #[test]
+    fn intersect_matches_brute_force() {
+        let mut state = 7;
+        let objects = random_scene(&mut state);
+        let bvh = build(&objects, 0.0);
+        assert_eq!(bvh.stats().unbounded, 1, "el plano infinito debe quedar fuera del árbol");
+
+        let mut hits = 0;
+        for _ in 0..2000 {
+            let ray = Ray::new(random_point(&mut state, 30.0), random_point(&mut state, 1.0));
+            let found = bvh.intersect(&ray, &|idx| objects[idx].intersect(&ray, 0.0));
+            let expected = brute_force(&objects, &ray, 0.0);
+            hits += expected.is_some() as usize;
+            assert_same_hit(found, expected, &ray);
+        }
+        assert!(hits > 500, "muy pocos rayos chocan con algo ({hits}), la prueba no dice mucho");
+    }
+
+    #[test]
+    fn occluded_matches_brute_force() {
+        let mut state = 11;
+        let objects = random_scene(&mut state);
+        let bvh = build(&objects, 0.0);
+
+        let (mut blocked, mut clear) = (0, 0);
+        for _ in 0..2000 {
+            let origin = random_point(&mut state, 30.0);
+            let target = random_point(&mut state, 30.0);
+            let ray = Ray::new(origin, target - origin);
+            let max_t = (target - origin).length();
+            let found = bvh.occluded(&ray, max_t, &|idx| objects[idx].occluded(&ray, max_t, 0.0));
+            let expected = objects.iter().any(|object| object.occluded(&ray, max_t, 0.0));
+            assert_eq!(found, expected, "sombra distinta para {:?} -> {:?}", origin, target);
+            if expected { blocked += 1 } else { clear += 1 }
+        }
+        assert!(blocked > 100 && clear > 100, "hace falta ver ambos casos: {blocked} tapados, {clear} libres");
+    }
+
+    #[test]
+    fn empty_and_unbounded_only() {
+        let ray = Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.1, -1.0, 0.0));
+        let empty = Bvh::build(&[]);
+        assert!(empty.intersect(&ray, &|_| unreachable!()).is_none());
+        assert!(!empty.occluded(&ray, 100.0, &|_| unreachable!()));
+
+        let plane = Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 0);
+        let bvh = Bvh::build(&[plane.get_bounds(0.0)]);
+        let hit = bvh.intersect(&ray, &|_| plane.intersect(&ray, 0.0)).expect("el rayo baja hacia el plano");
+        assert!((hit.point.y).abs() < 1e-4);
+        assert!(bvh.occluded(&ray, 100.0, &|_| plane.occluded(&ray, 100.0, 0.0)));
+    }
+
+    #[test]
+    fn refit_follows_spinning_cube() {
+        let mut state = 3;
+        let mut objects: Vec<Box<dyn Primitive>> = (0..30)
+            .map(|i| Box::new(Sphere::new(random_point(&mut state, 20.0) + Vec3::new(0.0, 0.0, 30.0), 0.5, i)) as Box<dyn Primitive>)
+            .collect();
+        let spinning = objects.len();
+        objects.push(Box::new(SpinningCube::new(Vec3::new(0.0, 0.0, 0.0), 2.0, 99, 1.0)));
+        assert!(objects[spinning].is_animated());
+
+        let mut bvh = build(&objects, 0.0);
+        bvh.mark_dynamic(&[spinning]);
+        assert!(bvh.stats().dynamic > 0);
+
+        // Girado 45°, la esquina del cubo llega a x = √2, fuera de la caja que tenía al
+        // armar el árbol; este rayo solo lo encuentra si la caja se reajustó
+        let time = std::f32::consts::FRAC_PI_4;
+        let ray = Ray::new(Vec3::new(1.2, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0));
+        let expected = brute_force(&objects, &ray, time);
+        assert_eq!(expected.as_ref().map(|hit| hit.material), Some(99), "el rayo debe tocar la esquina del cubo girado");
+
+        bvh.refit(&|i| objects[i].get_bounds(time));
+        let found = bvh.intersect(&ray, &|idx| objects[idx].intersect(&ray, time));
+        assert_same_hit(found, expected, &ray);
+        assert!(bvh.occluded(&ray, 20.0, &|idx| objects[idx].occluded(&ray, 20.0, time)));
+
+        // Al volver a la posición inicial la caja se achica y el rayo pasa de largo
+        bvh.refit(&|i| objects[i].get_bounds(0.0));
+        assert_same_hit(bvh.intersect(&ray, &|idx| objects[idx].intersect(&ray, 0.0)), brute_force(&objects, &ray, 0.0), &ray);
+    }
+}
@@ -0,0 +1,231 @@
+use std::path::PathBuf;
+
+use crate::anvil::WorldLoadOptions;
+use crate::geometry::Vec3;
+use crate::image_io::image_format;
+use crate::parallel;
+use crate::raytracer::{Camera, CameraView, RenderOptions, ShadowMode};
+use crate::schematic::{Rotation, StructurePlacement};
+
+pub const USAGE: &str = "\
+Uso: Proyecto2_Mine [opciones]
+
+Sin opciones abre la ventana interactiva.
+
+  --headless <archivo>     Renderizar una imagen fija (.png o .ppm) sin ventana
+  --width <px>             Ancho de la imagen (por defecto 512)
+  --height <px>            Alto de la imagen (por defecto 384)
+  --time <t>               Tiempo de la animación en segundos (por defecto 0)
+  --speed <s>              Velocidad del ciclo día/noche (por defecto 1.0)
+  --scene <archivo>        Cargar una escena descrita en TOML (ver scenes/nether.toml) o un modelo .gltf/.glb
+  --world <ruta>           Cargar un mundo guardado (archivo .mca, carpeta region/ o del mundo)
+  --world-chunks <x0,z0,x1,z1>  Limitar la carga del mundo a ese rango de chunks
+  --terrain <semilla>      Generar un terreno del Nether con esa semilla en lugar de la escena fija
+  --terrain-size <x,y,z>   Tamaño en bloques del terreno generado (por defecto 64,40,64)
+  --schem <archivo>        Colocar un esquemático (.schem) o estructura (.nbt); se puede repetir
+  --at <x,y,z>             Esquina mínima donde va el último --schem (por defecto 0,0,0)
+  --rotate <grados>        Girar el último --schem 0, 90, 180 o 270 grados (horario)
+  --camera <x,y,z>         Posición de la cámara
+  --target <x,y,z>         Punto al que mira la cámara
+  --fov <grados>           Campo de visión vertical (por defecto 45)
+  --shadows <modo>         none | sun | full (por defecto full)
+  --depth <n>              Profundidad máxima de recursión (por defecto 4)
+  --shadow-samples <n>     Rayos de sombra por luz con tamaño, en grilla k×k (por defecto 4)
+  --samples <n>            Trazado de caminos con n muestras por píxel en vez del trazado directo
+  --threads <n>            Hilos de render (por defecto, todos los núcleos)
+  -h, --help               Mostrar esta ayuda";
+
+pub struct CliArgs {
+    pub headless_output: Option<PathBuf>,
+    pub width: usize,
+    pub height: usize,
+    pub time: f32,
+    pub day_speed: f32,
+    pub scene: Option<PathBuf>,
+    pub world: Option<PathBuf>,
+    pub world_options: WorldLoadOptions,
+    pub terrain_seed: Option<u32>,
+    pub terrain_size: (i32, i32, i32),
+    pub structures: Vec<StructurePlacement>,
+    pub camera_position: Option<Vec3>,
+    pub camera_target: Option<Vec3>,
+    pub fov: Option<f32>,
+    pub shadow_mode: ShadowMode,
+    pub max_depth: i32,
+    pub shadow_samples: u32,
+    pub path_samples: Option<u32>,
+    pub threads: usize,
+    pub show_help: bool,
+}
+
+impl Default for CliArgs {
+    fn default() -> Self {
+        CliArgs {
+            headless_output: None,
+            width: 512,
+            height: 384,
+            time: 0.0,
+            day_speed: 1.0,
+            scene: None,
+            world: None,
+            world_options: WorldLoadOptions::default(),
+            terrain_seed: None,
+            terrain_size: (64, 40, 64),
+            structures: Vec::new(),
+            camera_position: None,
+            camera_target: None,
+            fov: None,
+            shadow_mode: ShadowMode::Full,
+            max_depth: 4,
+            shadow_samples: 4,
+            path_samples: None,
+            threads: parallel::default_thread_count(),
+            show_help: false,
+        }
+    }
+}
+
+impl CliArgs {
+    // `default_view` es la vista de la escena para lo que no se haya pasado por línea de comandos
+    pub fn build_camera(&self, width: usize, height: usize, default_view: CameraView) -> Camera {
+        Camera::new(
+            self.camera_position.unwrap_or(default_view.position),
+            self.camera_target.unwrap_or(default_view.target),
+            Vec3::new(0.0, 1.0, 0.0),
+            self.fov.unwrap_or(default_view.fov),
+            width as f32 / height as f32,
+        )
+    }
+
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            shadow_mode: self.shadow_mode,
+            max_depth: self.max_depth,
+            far_simplify_distance: f32::INFINITY,
+            threads: self.threads,
+            shadow_samples: self.shadow_samples,
+        }
+    }
+}
+
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<CliArgs, String> {
+    let mut parsed = CliArgs::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("falta el valor de {}", arg));
+        match arg.as_str() {
+            "--headless" => {
+                let path = PathBuf::from(value()?);
+                // Mejor avisar ahora que después de todo el render
+                if image_format(&path).is_none() {
+                    return Err(format!("formato de imagen no soportado: '{}' (usar .png o .ppm)", path.display()));
+                }
+                parsed.headless_output = Some(path);
+            }
+            "--width" => parsed.width = parse_number(&arg, &value()?)?,
+            "--height" => parsed.height = parse_number(&arg, &value()?)?,
+            "--time" => parsed.time = parse_number(&arg, &value()?)?,
+            "--speed" => parsed.day_speed = parse_number(&arg, &value()?)?,
+            "--scene" => parsed.scene = Some(PathBuf::from(value()?)),
+            "--world" => parsed.world = Some(PathBuf::from(value()?)),
+            "--world-chunks" => {
+                let area = parse_list::<i32>(&arg, &value()?, 4)?;
+                parsed.world_options.chunk_area = Some((
+                    area[0].min(area[2]), area[1].min(area[3]),
+                    area[0].max(area[2]), area[1].max(area[3]),
+                ));
+            }
+            "--terrain" => parsed.terrain_seed = Some(parse_number(&arg, &value()?)?),
+            "--terrain-size" => {
+                let size = parse_list::<i32>(&arg, &value()?, 3)?;
+                if size.iter().any(|&s| s <= 0) {
+                    return Err("--terrain-size debe ser mayor que cero en cada eje".to_string());
+                }
+                parsed.terrain_size = (size[0], size[1], size[2]);
+            }
+            "--schem" => parsed.structures.push(StructurePlacement {
+                path: PathBuf::from(value()?),
+                origin: (0, 0, 0),
+                rotation: Rotation::None,
+            }),
+            "--at" => {
+                let at = parse_list::<i32>(&arg, &value()?, 3)?;
+                last_structure(&mut parsed, &arg)?.origin = (at[0], at[1], at[2]);
+            }
+            "--rotate" => {
+                let degrees: i32 = parse_number(&arg, &value()?)?;
+                let rotation = Rotation::from_degrees(degrees)
+                    .ok_or_else(|| format!("--rotate solo acepta múltiplos de 90, se recibió {}", degrees))?;
+                last_structure(&mut parsed, &arg)?.rotation = rotation;
+            }
+            "--camera" => parsed.camera_position = Some(parse_vec3(&arg, &value()?)?),
+            "--target" => parsed.camera_target = Some(parse_vec3(&arg, &value()?)?),
+            "--fov" => parsed.fov = Some(parse_number(&arg, &value()?)?),
+            "--shadows" => {
+                parsed.shadow_mode = match value()?.as_str() {
+                    "none" => ShadowMode::None,
+                    "sun" => ShadowMode::SunOnly,
+                    "full" => ShadowMode::Full,
+                    other => return Err(format!("modo de sombras desconocido '{}'", other)),
+                }
+            }
+            "--depth" => parsed.max_depth = parse_number(&arg, &value()?)?,
+            "--shadow-samples" => {
+                let samples: u32 = parse_number(&arg, &value()?)?;
+                if samples == 0 {
+                    return Err("--shadow-samples debe ser mayor que cero".to_string());
+                }
+                parsed.shadow_samples = samples;
+            }
+            "--samples" => {
+                let samples: u32 = parse_number(&arg, &value()?)?;
+                if samples == 0 {
+                    return Err("--samples debe ser mayor que cero".to_string());
+                }
+                parsed.path_samples = Some(samples);
+            }
+            "--threads" => {
+                let threads: usize = parse_number(&arg, &value()?)?;
+                parsed.threads = if threads == 0 { parallel::default_thread_count() } else { threads };
+            }
+            "-h" | "--help" => parsed.show_help = true,
+            _ => return Err(format!("opción desconocida '{}'", arg)),
+        }
+    }
+
+    if parsed.width == 0 || parsed.height == 0 {
+        return Err("la resolución debe ser mayor que cero".to_string());
+    }
+    if parsed.scene.is_some() && parsed.world.is_some() {
+        return Err("--scene y --world no se pueden usar juntos".to_string());
+    }
+    if parsed.terrain_seed.is_some() && (parsed.scene.is_some() || parsed.world.is_some()) {
+        return Err("--terrain no se combina con --scene ni --world".to_string());
+    }
+    if parsed.scene.is_some() && !parsed.structures.is_empty() {
+        return Err("--schem no se combina con --scene; usar [[structures]] en el archivo de escena".to_string());
+    }
+    Ok(parsed)
+}
+
+// --at y --rotate modifican el --schem anterior
+fn last_structure<'a>(parsed: &'a mut CliArgs, flag: &str) -> Result<&'a mut StructurePlacement, String> {
+    parsed.structures.last_mut().ok_or_else(|| format!("{} debe ir después de --schem", flag))
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, text: &str) -> Result<T, String> {
+    text.trim().parse().map_err(|_| format!("valor inválido para {}: '{}'", flag, text))
+}
+
+fn parse_list<T: std::str::FromStr>(flag: &str, text: &str, count: usize) -> Result<Vec<T>, String> {
+    let parts: Vec<&str> = text.split(',').collect();
+    if parts.len() != count {
+        return Err(format!("{} espera {} valores separados por comas, se recibió '{}'", flag, count, text));
+    }
+    parts.iter().map(|p| parse_number(flag, p)).collect()
+}
+
+fn parse_vec3(flag: &str, text: &str) -> Result<Vec3, String> {
+    let v = parse_list::<f32>(flag, text, 3)?;
+    Ok(Vec3::new(v[0], v[1], v[2]))
+}
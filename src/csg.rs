@@ -0,0 +1,105 @@
+use crate::geometry::*;
+use crate::raytracer::{Emitter, HitInfo};
+use crate::shapes::{Primitive, Span};
+
+// Geometría sólida constructiva: combina los tramos de entrada/salida de dos sólidos,
+// por ejemplo para abrir una puerta en un bloque de obsidiana o ahuecar una caverna
+#[derive(Clone, Copy, PartialEq)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    // El primer sólido menos el segundo
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            CsgOp::Union => in_a || in_b,
+            CsgOp::Intersection => in_a && in_b,
+            CsgOp::Difference => in_a && !in_b,
+        }
+    }
+}
+
+pub struct Csg {
+    pub op: CsgOp,
+    pub a: Box<dyn Primitive>,
+    pub b: Box<dyn Primitive>,
+    bounds: (Vec3, Vec3),
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, a: Box<dyn Primitive>, b: Box<dyn Primitive>) -> Self {
+        let mut csg = Csg { op, a, b, bounds: (Vec3::zero(), Vec3::zero()) };
+        csg.bounds = csg.combined_bounds(0.0);
+        csg
+    }
+
+    fn combined_bounds(&self, time: f32) -> (Vec3, Vec3) {
+        let (a_min, a_max) = self.a.get_bounds(time);
+        let (b_min, b_max) = self.b.get_bounds(time);
+        match self.op {
+            CsgOp::Union => (a_min.min(b_min), a_max.max(b_max)),
+            // Una intersección vacía queda con una caja invertida que ningún rayo toca
+            CsgOp::Intersection => (a_min.max(b_min), a_max.min(b_max)),
+            CsgOp::Difference => (a_min, a_max),
+        }
+    }
+}
+
+impl Primitive for Csg {
+    fn intersect(&self, ray: &Ray, time: f32) -> Option<HitInfo> {
+        // La primera frontera del resultado por delante del origen, sea entrada o salida; no
+        // entrega luces de área, así que sus caras no se marcan como tales
+        let mut hit = self.spans(ray, time)?
+            .into_iter()
+            .flat_map(|span| [span.enter, span.exit])
+            .find(|hit| hit.t > 0.001)?;
+        hit.emitter = Emitter::Unsampled;
+        Some(hit)
+    }
+
+    fn get_bounds(&self, time: f32) -> (Vec3, Vec3) {
+        if self.is_animated() { self.combined_bounds(time) } else { self.bounds }
+    }
+
+    fn is_animated(&self) -> bool {
+        self.a.is_animated() || self.b.is_animated()
+    }
+
+    fn spans(&self, ray: &Ray, time: f32) -> Option<Vec<Span>> {
+        let a = self.a.spans(ray, time).unwrap_or_default();
+        let b = self.b.spans(ray, time).unwrap_or_default();
+
+        // Recorre las fronteras de ambos en orden y abre o cierra un tramo cada vez que
+        // cambia si el punto está dentro del resultado
+        let mut events: Vec<(HitInfo, bool, bool)> = Vec::with_capacity(2 * (a.len() + b.len()));
+        for (spans, from_a) in [(a, true), (b, false)] {
+            for span in spans {
+                events.push((span.enter, from_a, true));
+                events.push((span.exit, from_a, false));
+            }
+        }
+        events.sort_by(|x, y| x.0.t.total_cmp(&y.0.t));
+
+        let (mut in_a, mut in_b) = (false, false);
+        let mut open: Option<HitInfo> = None;
+        let mut result = Vec::new();
+        for (mut hit, from_a, entering) in events {
+            if from_a { in_a = entering } else { in_b = entering }
+            // Las paredes del sólido restado miran hacia adentro del hueco
+            if !from_a && self.op == CsgOp::Difference {
+                hit.normal = -hit.normal;
+            }
+            let inside = self.op.inside(in_a, in_b);
+            match open.take() {
+                Some(enter) if !inside => result.push(Span { enter, exit: hit }),
+                Some(enter) => open = Some(enter),
+                None if inside => open = Some(hit),
+                None => {}
+            }
+        }
+        Some(result)
+    }
+}
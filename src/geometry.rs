@@ -180,6 +180,222 @@ impl Ray {
     }
 }
 
+// Matriz 4x4 para transformaciones afines, m[fila][columna], aplicada a vectores columna
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn identity() -> Self {
+        Mat4 { m: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]] }
+    }
+
+    pub fn translation(t: Vec3) -> Self {
+        let mut result = Mat4::identity();
+        result.m[0][3] = t.x;
+        result.m[1][3] = t.y;
+        result.m[2][3] = t.z;
+        result
+    }
+
+    pub fn scale(s: Vec3) -> Self {
+        let mut result = Mat4::identity();
+        result.m[0][0] = s.x;
+        result.m[1][1] = s.y;
+        result.m[2][2] = s.z;
+        result
+    }
+
+    // Mismo sentido de giro que Vec3::rotate_x/y/z
+    pub fn rotation_x(angle: f32) -> Self {
+        let (sin_a, cos_a) = angle.sin_cos();
+        let mut result = Mat4::identity();
+        result.m[1][1] = cos_a; result.m[1][2] = -sin_a;
+        result.m[2][1] = sin_a; result.m[2][2] = cos_a;
+        result
+    }
+
+    pub fn rotation_y(angle: f32) -> Self {
+        let (sin_a, cos_a) = angle.sin_cos();
+        let mut result = Mat4::identity();
+        result.m[0][0] = cos_a; result.m[0][2] = -sin_a;
+        result.m[2][0] = sin_a; result.m[2][2] = cos_a;
+        result
+    }
+
+    pub fn rotation_z(angle: f32) -> Self {
+        let (sin_a, cos_a) = angle.sin_cos();
+        let mut result = Mat4::identity();
+        result.m[0][0] = cos_a; result.m[0][1] = -sin_a;
+        result.m[1][0] = sin_a; result.m[1][1] = cos_a;
+        result
+    }
+
+    // Rotación por ángulos de Euler en el orden que usa Cube: primero Z, luego X, luego Y
+    pub fn rotation_euler(angles: Vec3) -> Self {
+        Mat4::rotation_y(angles.y) * Mat4::rotation_x(angles.x) * Mat4::rotation_z(angles.z)
+    }
+
+    // Cuaternión unitario (x, y, z, w)
+    pub fn from_quaternion(q: [f32; 4]) -> Self {
+        let [x, y, z, w] = q;
+        let mut result = Mat4::identity();
+        result.m[0][0] = 1.0 - 2.0 * (y * y + z * z);
+        result.m[0][1] = 2.0 * (x * y - z * w);
+        result.m[0][2] = 2.0 * (x * z + y * w);
+        result.m[1][0] = 2.0 * (x * y + z * w);
+        result.m[1][1] = 1.0 - 2.0 * (x * x + z * z);
+        result.m[1][2] = 2.0 * (y * z - x * w);
+        result.m[2][0] = 2.0 * (x * z - y * w);
+        result.m[2][1] = 2.0 * (y * z + x * w);
+        result.m[2][2] = 1.0 - 2.0 * (x * x + y * y);
+        result
+    }
+
+    // Traslación * rotación * escala, como en los nodos de glTF
+    pub fn from_trs(translation: Vec3, rotation: [f32; 4], scale: Vec3) -> Self {
+        Mat4::translation(translation) * Mat4::from_quaternion(rotation) * Mat4::scale(scale)
+    }
+
+    // 16 valores por columnas (el orden de glTF y OpenGL)
+    pub fn from_column_major(values: &[f32; 16]) -> Self {
+        let mut result = Mat4::identity();
+        for (col, column) in values.chunks_exact(4).enumerate() {
+            for (row, &value) in column.iter().enumerate() {
+                result.m[row][col] = value;
+            }
+        }
+        result
+    }
+
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3],
+        )
+    }
+
+    // Direcciones: sin traslación
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    // Determinante de la parte lineal; negativo si la transformación refleja
+    pub fn determinant(&self) -> f32 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    // Normales: se transforman con la inversa transpuesta para soportar escalas no uniformes
+    // (la matriz de cofactores es esa inversa multiplicada por el determinante)
+    pub fn transform_normal(&self, n: Vec3) -> Vec3 {
+        let m = &self.m;
+        let cofactor = Vec3::new(
+            (m[1][1] * m[2][2] - m[1][2] * m[2][1]) * n.x + (m[1][2] * m[2][0] - m[1][0] * m[2][2]) * n.y + (m[1][0] * m[2][1] - m[1][1] * m[2][0]) * n.z,
+            (m[0][2] * m[2][1] - m[0][1] * m[2][2]) * n.x + (m[0][0] * m[2][2] - m[0][2] * m[2][0]) * n.y + (m[0][1] * m[2][0] - m[0][0] * m[2][1]) * n.z,
+            (m[0][1] * m[1][2] - m[0][2] * m[1][1]) * n.x + (m[0][2] * m[1][0] - m[0][0] * m[1][2]) * n.y + (m[0][0] * m[1][1] - m[0][1] * m[1][0]) * n.z,
+        );
+        (cofactor * self.determinant().signum()).normalize()
+    }
+
+    // Inversa de una transformación afín; None si la matriz aplasta algún eje
+    pub fn inverse(&self) -> Option<Mat4> {
+        let determinant = self.determinant();
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        let m = &self.m;
+        let mut result = Mat4::identity();
+        // Parte lineal: transpuesta de los cofactores dividida por el determinante
+        for row in 0..3 {
+            for col in 0..3 {
+                let (r0, r1) = ((col + 1) % 3, (col + 2) % 3);
+                let (c0, c1) = ((row + 1) % 3, (row + 2) % 3);
+                result.m[row][col] = (m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]) / determinant;
+            }
+        }
+        let translation = result.transform_vector(Vec3::new(m[0][3], m[1][3], m[2][3]));
+        result.m[0][3] = -translation.x;
+        result.m[1][3] = -translation.y;
+        result.m[2][3] = -translation.z;
+        Some(result)
+    }
+}
+
+// Transformación con su inversa ya calculada, para pasar rayos al espacio de un objeto
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    pub matrix: Mat4,
+    pub inverse: Mat4,
+}
+
+impl Transform {
+    pub fn new(matrix: Mat4) -> Option<Self> {
+        Some(Transform { matrix, inverse: matrix.inverse()? })
+    }
+
+    // Caja alineada a los ejes que contiene la caja `bounds` ya transformada
+    pub fn transform_bounds(&self, bounds: (Vec3, Vec3)) -> (Vec3, Vec3) {
+        let (min, max) = bounds;
+        let mut result = (Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY), Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY));
+        for corner in 0..8 {
+            let p = Vec3::new(
+                if corner & 1 == 0 { min.x } else { max.x },
+                if corner & 2 == 0 { min.y } else { max.y },
+                if corner & 4 == 0 { min.z } else { max.z },
+            );
+            let p = self.matrix.transform_point(p);
+            result = (result.0.min(p), result.1.max(p));
+        }
+        result
+    }
+
+    // Factor de escala si es el mismo en los tres ejes (las esferas siguen siendo esferas)
+    pub fn uniform_scale(&self) -> Option<f32> {
+        let axes = [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)]
+            .map(|axis| self.matrix.transform_vector(axis).length());
+        if (axes[0] - axes[1]).abs() > 1e-4 * axes[0] || (axes[0] - axes[2]).abs() > 1e-4 * axes[0] {
+            return None;
+        }
+        Some(axes[0])
+    }
+}
+
+impl std::ops::Mul for Mat4 {
+    type Output = Mat4;
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut result = [[0.0; 4]; 4];
+        for (row, result_row) in result.iter_mut().enumerate() {
+            for (col, value) in result_row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[row][k] * other.m[k][col]).sum();
+            }
+        }
+        Mat4 { m: result }
+    }
+}
+
+// Dos ejes perpendiculares a `w` (unitario) y entre sí
+pub fn orthonormal_basis(w: Vec3) -> (Vec3, Vec3) {
+    let helper = if w.x.abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+    let u = w.cross(helper).normalize();
+    (u, w.cross(u))
+}
+
+// Brillo percibido de un color lineal
+pub fn luminance(color: Vec3) -> f32 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
 // Funciones de ruido para texturas procedurales
 pub fn hash_vec(p: Vec3) -> f32 {
     let p3 = (p * 0.1031).fract();
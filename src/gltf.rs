@@ -0,0 +1,801 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::geometry::{Mat4, Vec3};
+use crate::materials::{Material, MaterialId, MaterialRegistry};
+use crate::nbt::invalid_data;
+use crate::raytracer::{CameraView, Light};
+use crate::shapes::Triangle;
+
+// Importador de glTF 2.0: .gltf (buffers externos o en data URIs base64) y .glb binario.
+// Se cargan las mallas con la jerarquía de nodos, las cámaras en perspectiva, las luces
+// KHR_lights_punctual y los materiales metallic-roughness; las texturas de imagen no se soportan.
+
+// Extensiones que se pueden exigir sin que cambie el resultado
+const SUPPORTED_EXTENSIONS: &[&str] = &[
+    "KHR_lights_punctual",
+    "KHR_materials_emissive_strength",
+    "KHR_materials_ior",
+    "KHR_materials_transmission",
+    "KHR_materials_volume",
+    "KHR_mesh_quantization",
+    "KHR_texture_transform",
+];
+
+// glTF usa candelas para luces puntuales y focos; una luz de 1000 W exportada
+// desde Blender (~54 cd) queda con intensidad 1.0
+const CANDELA_PER_INTENSITY: f32 = 54.35;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Document {
+    asset: Asset,
+    scene: Option<usize>,
+    #[serde(default)]
+    scenes: Vec<SceneNodes>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    meshes: Vec<MeshDef>,
+    #[serde(default)]
+    accessors: Vec<Accessor>,
+    #[serde(default)]
+    buffer_views: Vec<BufferView>,
+    #[serde(default)]
+    buffers: Vec<Buffer>,
+    #[serde(default)]
+    materials: Vec<MaterialDef>,
+    #[serde(default)]
+    cameras: Vec<CameraDef>,
+    #[serde(default)]
+    extensions_required: Vec<String>,
+    #[serde(default)]
+    extensions: DocumentExtensions,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct SceneNodes {
+    #[serde(default)]
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize, Default)]
+struct DocumentExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    lights_punctual: Option<LightList>,
+}
+
+#[derive(Deserialize)]
+struct LightList {
+    lights: Vec<LightDef>,
+}
+
+#[derive(Deserialize)]
+struct LightDef {
+    #[serde(rename = "type")]
+    kind: String,
+    color: Option<[f32; 3]>,
+    intensity: Option<f32>,
+    spot: Option<SpotDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpotDef {
+    inner_cone_angle: Option<f32>,
+    outer_cone_angle: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct Node {
+    camera: Option<usize>,
+    mesh: Option<usize>,
+    #[serde(default)]
+    children: Vec<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+    #[serde(default)]
+    extensions: NodeExtensions,
+}
+
+#[derive(Deserialize, Default)]
+struct NodeExtensions {
+    #[serde(rename = "KHR_lights_punctual")]
+    lights_punctual: Option<NodeLight>,
+}
+
+#[derive(Deserialize)]
+struct NodeLight {
+    light: usize,
+}
+
+#[derive(Deserialize)]
+struct MeshDef {
+    primitives: Vec<PrimitiveDef>,
+}
+
+#[derive(Deserialize)]
+struct PrimitiveDef {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    mode: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<serde::de::IgnoredAny>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Buffer {
+    uri: Option<String>,
+    byte_length: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MaterialDef {
+    #[serde(default)]
+    pbr_metallic_roughness: PbrDef,
+    emissive_factor: Option<[f32; 3]>,
+    alpha_mode: Option<String>,
+    #[serde(default)]
+    extensions: MaterialExtensions,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct PbrDef {
+    base_color_factor: Option<[f32; 4]>,
+    metallic_factor: Option<f32>,
+    roughness_factor: Option<f32>,
+}
+
+#[derive(Deserialize, Default)]
+struct MaterialExtensions {
+    #[serde(rename = "KHR_materials_emissive_strength")]
+    emissive_strength: Option<EmissiveStrength>,
+    #[serde(rename = "KHR_materials_ior")]
+    ior: Option<Ior>,
+    #[serde(rename = "KHR_materials_transmission")]
+    transmission: Option<Transmission>,
+    #[serde(rename = "KHR_materials_volume")]
+    volume: Option<Volume>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmissiveStrength {
+    emissive_strength: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct Ior {
+    ior: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Transmission {
+    transmission_factor: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Volume {
+    attenuation_color: Option<[f32; 3]>,
+    attenuation_distance: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct CameraDef {
+    #[serde(rename = "type")]
+    kind: String,
+    perspective: Option<Perspective>,
+}
+
+#[derive(Deserialize)]
+struct Perspective {
+    yfov: f32,
+}
+
+// Contenido importado, ya transformado a coordenadas de la escena
+pub struct GltfScene {
+    pub triangles: Vec<Triangle>,
+    pub lights: Vec<Light>,
+    pub cameras: Vec<CameraView>,
+}
+
+// `transform` ubica el modelo en la escena; sus materiales se agregan a `registry` y
+// `material_override` los reemplaza a todos
+pub fn load_gltf(path: &Path, transform: Mat4, material_override: Option<MaterialId>, registry: &mut MaterialRegistry) -> io::Result<GltfScene> {
+    import_gltf(&fs::read(path)?, path, transform, material_override, registry)
+}
+
+// Importa el contenido de un .gltf o .glb ya leído; `path` ubica los buffers externos
+fn import_gltf(data: &[u8], path: &Path, transform: Mat4, material_override: Option<MaterialId>, registry: &mut MaterialRegistry) -> io::Result<GltfScene> {
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let (json, bin) = if data.starts_with(b"glTF") { parse_glb(data)? } else { (data, None) };
+
+    let doc: Document = serde_json::from_slice(json).map_err(|e| invalid_data(format!("JSON inválido: {}", e)))?;
+    if !doc.asset.version.starts_with("2.") {
+        return Err(invalid_data(format!("versión de glTF {} no soportada (se espera 2.0)", doc.asset.version)));
+    }
+    if let Some(extension) = doc.extensions_required.iter().find(|e| !SUPPORTED_EXTENSIONS.contains(&e.as_str())) {
+        return Err(invalid_data(format!("extensión requerida no soportada: {}", extension)));
+    }
+
+    let buffers = doc.buffers.iter().enumerate()
+        .map(|(i, buffer)| load_buffer(buffer, i, bin, base_dir))
+        .collect::<io::Result<Vec<_>>>()?;
+    let materials = doc.materials.iter()
+        .map(|def| material_override.unwrap_or_else(|| registry.add(build_material(def))))
+        .collect();
+
+    let roots: Vec<usize> = match doc.scene.or(if doc.scenes.is_empty() { None } else { Some(0) }) {
+        Some(index) => doc.scenes.get(index).ok_or_else(|| invalid_data(format!("escena {} no existe", index)))?.nodes.clone(),
+        // Sin escenas se toman los nodos que no son hijos de ningún otro
+        None => {
+            let mut is_child = vec![false; doc.nodes.len()];
+            for child in doc.nodes.iter().flat_map(|n| &n.children) {
+                if let Some(flag) = is_child.get_mut(*child) { *flag = true; }
+            }
+            (0..doc.nodes.len()).filter(|&i| !is_child[i]).collect()
+        }
+    };
+
+    let mut importer = Importer {
+        doc: &doc,
+        buffers,
+        materials,
+        default_material: material_override.unwrap_or_else(|| registry.add(Material::new())),
+        output: GltfScene { triangles: Vec::new(), lights: Vec::new(), cameras: Vec::new() },
+        degenerate: 0,
+        skipped_primitives: 0,
+    };
+    for root in roots {
+        importer.visit(root, transform, 0)?;
+    }
+
+    if importer.degenerate > 0 {
+        println!("Skipped {} degenerate triangle(s) in {}", importer.degenerate, path.display());
+    }
+    if importer.skipped_primitives > 0 {
+        eprintln!("Warning: {}: skipped {} point/line primitive(s)", path.display(), importer.skipped_primitives);
+    }
+    Ok(importer.output)
+}
+
+// Contenedor binario: cabecera de 12 bytes y chunks JSON y BIN
+fn parse_glb(data: &[u8]) -> io::Result<(&[u8], Option<&[u8]>)> {
+    let read_u32 = |offset: usize| data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    let version = read_u32(4).ok_or_else(|| invalid_data("cabecera GLB truncada"))?;
+    if version != 2 {
+        return Err(invalid_data(format!("versión de GLB {} no soportada", version)));
+    }
+    let length = (read_u32(8).ok_or_else(|| invalid_data("cabecera GLB truncada"))? as usize).min(data.len());
+
+    let (mut json, mut bin) = (None, None);
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let chunk_length = read_u32(offset).unwrap_or(0) as usize;
+        let chunk_type = read_u32(offset + 4).unwrap_or(0);
+        let chunk = data.get(offset + 8..offset + 8 + chunk_length).ok_or_else(|| invalid_data("chunk de GLB truncado"))?;
+        match chunk_type {
+            0x4E4F534A => json = json.or(Some(chunk)),
+            0x004E4942 => bin = bin.or(Some(chunk)),
+            _ => {}
+        }
+        offset += 8 + chunk_length;
+    }
+    Ok((json.ok_or_else(|| invalid_data("el GLB no tiene chunk JSON"))?, bin))
+}
+
+fn load_buffer(buffer: &Buffer, index: usize, bin: Option<&[u8]>, base_dir: &Path) -> io::Result<Vec<u8>> {
+    let data = match &buffer.uri {
+        // En un .glb el primer buffer sin uri es el chunk BIN
+        None if index == 0 => bin.ok_or_else(|| invalid_data("el buffer 0 no tiene uri ni chunk BIN"))?.to_vec(),
+        None => return Err(invalid_data(format!("buffer {} sin uri", index))),
+        Some(uri) if uri.starts_with("data:") => {
+            let (_, payload) = uri.split_once(";base64,").ok_or_else(|| invalid_data("solo se soportan data URIs en base64"))?;
+            decode_base64(payload)?
+        }
+        Some(uri) => {
+            let file = base_dir.join(percent_decode(uri));
+            fs::read(&file).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", file.display(), e)))?
+        }
+    };
+    if data.len() < buffer.byte_length {
+        return Err(invalid_data(format!("buffer {} tiene {} bytes, se esperaban {}", index, data.len(), buffer.byte_length)));
+    }
+    Ok(data)
+}
+
+fn decode_base64(text: &str) -> io::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(text.len() * 3 / 4);
+    let mut accumulator: u32 = 0;
+    let mut bits = 0;
+    for byte in text.bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => return Err(invalid_data("data URI con base64 inválido")),
+        };
+        accumulator = (accumulator << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((accumulator >> bits) as u8);
+        }
+    }
+    Ok(output)
+}
+
+// Las uri relativas pueden venir con escapes %XX (por ejemplo espacios como %20)
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| uri.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => { decoded.push(byte); i += 3; }
+            None => { decoded.push(bytes[i]); i += 1; }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// El modelo metallic-roughness de glTF es el mismo de la BSDF; el dieléctrico refleja el 4%
+fn build_material(def: &MaterialDef) -> Material {
+    let pbr = &def.pbr_metallic_roughness;
+    let [r, g, b, alpha] = pbr.base_color_factor.unwrap_or([1.0; 4]);
+    let metallic = pbr.metallic_factor.unwrap_or(1.0).clamp(0.0, 1.0);
+    let roughness = pbr.roughness_factor.unwrap_or(1.0).clamp(0.0, 1.0);
+
+    let mut material = Material::new();
+    material.albedo = Vec3::new(r, g, b);
+    material.metallic = metallic;
+    material.roughness = roughness;
+    material.specular = 0.5;
+
+    if def.alpha_mode.as_deref() == Some("BLEND") {
+        material.transparency = 1.0 - alpha.clamp(0.0, 1.0);
+    }
+    let extensions = &def.extensions;
+    if let Some(transmission) = &extensions.transmission {
+        material.transparency = material.transparency.max(transmission.transmission_factor.unwrap_or(0.0).clamp(0.0, 1.0));
+    }
+    // El IOR por defecto de glTF es 1.5; solo importa si el material deja pasar luz refractada
+    match &extensions.ior {
+        Some(ior) => material.refraction_index = ior.ior.unwrap_or(1.5).max(1.0),
+        None if extensions.transmission.is_some() => material.refraction_index = 1.5,
+        None => {}
+    }
+    // Tinte del volumen: el color que queda tras recorrer la distancia de atenuación
+    if let Some(Volume { attenuation_color: Some([r, g, b]), attenuation_distance: Some(distance) }) = &extensions.volume
+        && *distance > 0.0 {
+        material = material.with_absorption(Vec3::new(*r, *g, *b), *distance);
+    }
+
+    let [er, eg, eb] = def.emissive_factor.unwrap_or([0.0; 3]);
+    let strength = extensions.emissive_strength.as_ref().and_then(|e| e.emissive_strength).unwrap_or(1.0);
+    material.emissive = Vec3::new(er, eg, eb) * strength;
+    material
+}
+
+struct Importer<'a> {
+    doc: &'a Document,
+    buffers: Vec<Vec<u8>>,
+    materials: Vec<MaterialId>,
+    default_material: MaterialId,
+    output: GltfScene,
+    degenerate: usize,
+    skipped_primitives: usize,
+}
+
+// Vista de los bytes de un accessor dentro de su buffer
+struct AccessorData<'a> {
+    bytes: &'a [u8],
+    stride: usize,
+    count: usize,
+    components: usize,
+    component_type: u32,
+    normalized: bool,
+}
+
+impl AccessorData<'_> {
+    fn component_size(&self) -> usize {
+        match self.component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            _ => 4,
+        }
+    }
+
+    fn raw(&self, element: usize, component: usize) -> &[u8] {
+        let size = self.component_size();
+        let offset = element * self.stride + component * size;
+        &self.bytes[offset..offset + size]
+    }
+
+    fn float(&self, element: usize, component: usize) -> f32 {
+        let b = self.raw(element, component);
+        let normalized = self.normalized;
+        match self.component_type {
+            5120 if normalized => (b[0] as i8 as f32 / 127.0).max(-1.0),
+            5120 => b[0] as i8 as f32,
+            5121 if normalized => b[0] as f32 / 255.0,
+            5121 => b[0] as f32,
+            5122 if normalized => (i16::from_le_bytes([b[0], b[1]]) as f32 / 32767.0).max(-1.0),
+            5122 => i16::from_le_bytes([b[0], b[1]]) as f32,
+            5123 if normalized => u16::from_le_bytes([b[0], b[1]]) as f32 / 65535.0,
+            5123 => u16::from_le_bytes([b[0], b[1]]) as f32,
+            5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32,
+            _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        }
+    }
+
+    fn index(&self, element: usize) -> usize {
+        let b = self.raw(element, 0);
+        match self.component_type {
+            5121 => b[0] as usize,
+            5123 => u16::from_le_bytes([b[0], b[1]]) as usize,
+            _ => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize,
+        }
+    }
+}
+
+impl Importer<'_> {
+    fn visit(&mut self, index: usize, parent: Mat4, depth: usize) -> io::Result<()> {
+        let doc = self.doc;
+        let node = doc.nodes.get(index).ok_or_else(|| invalid_data(format!("nodo {} no existe", index)))?;
+        // Un ciclo en la jerarquía haría la recursión infinita
+        if depth > doc.nodes.len() {
+            return Err(invalid_data("la jerarquía de nodos tiene un ciclo"));
+        }
+
+        let local = match &node.matrix {
+            Some(matrix) => Mat4::from_column_major(matrix),
+            None => Mat4::from_trs(
+                vec3(node.translation.unwrap_or([0.0; 3])),
+                node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]),
+                vec3(node.scale.unwrap_or([1.0; 3])),
+            ),
+        };
+        let world = parent * local;
+
+        if let Some(mesh) = node.mesh {
+            self.add_mesh(mesh, &world)?;
+        }
+        if let Some(camera) = node.camera {
+            let def = doc.cameras.get(camera).ok_or_else(|| invalid_data(format!("cámara {} no existe", camera)))?;
+            match (def.kind.as_str(), &def.perspective) {
+                ("perspective", Some(perspective)) => {
+                    // Las cámaras miran hacia -Z local; el objetivo queda a 10 unidades para el enfoque
+                    let position = world.transform_point(Vec3::zero());
+                    let forward = world.transform_vector(Vec3::new(0.0, 0.0, -1.0)).normalize();
+                    self.output.cameras.push(CameraView { position, target: position + forward * 10.0, fov: perspective.yfov.to_degrees() });
+                }
+                (kind, _) => eprintln!("Warning: {} camera {} ignored", kind, camera),
+            }
+        }
+        if let Some(light) = &node.extensions.lights_punctual {
+            let light = self.build_light(light.light, &world)?;
+            self.output.lights.push(light);
+        }
+
+        for &child in &node.children {
+            self.visit(child, world, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    fn build_light(&self, index: usize, world: &Mat4) -> io::Result<Light> {
+        let def = self.doc.extensions.lights_punctual.as_ref()
+            .and_then(|list| list.lights.get(index))
+            .ok_or_else(|| invalid_data(format!("luz {} no existe", index)))?;
+        let color = vec3(def.color.unwrap_or([1.0; 3]));
+        let intensity = def.intensity.unwrap_or(1.0);
+        // Las luces apuntan hacia -Z local
+        let position = world.transform_point(Vec3::zero());
+        let direction = world.transform_vector(Vec3::new(0.0, 0.0, -1.0));
+
+        Ok(match def.kind.as_str() {
+            "directional" => Light::directional(direction, color, intensity),
+            "point" => Light::point(position, color, intensity / CANDELA_PER_INTENSITY),
+            "spot" => {
+                let spot = def.spot.as_ref();
+                let inner = spot.and_then(|s| s.inner_cone_angle).unwrap_or(0.0);
+                let outer = spot.and_then(|s| s.outer_cone_angle).unwrap_or(std::f32::consts::FRAC_PI_4);
+                Light::spot(position, direction, color, intensity / CANDELA_PER_INTENSITY, inner, outer)
+            }
+            other => return Err(invalid_data(format!("tipo de luz desconocido '{}'", other))),
+        })
+    }
+
+    fn accessor(&self, index: usize) -> io::Result<AccessorData<'_>> {
+        let doc = self.doc;
+        let error = |message: &str| invalid_data(format!("accessor {}: {}", index, message));
+        let accessor = doc.accessors.get(index).ok_or_else(|| error("no existe"))?;
+        if accessor.sparse.is_some() {
+            return Err(error("los accessors dispersos (sparse) no se soportan"));
+        }
+        let components = match accessor.kind.as_str() {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" => 4,
+            other => return Err(error(&format!("tipo {} no soportado", other))),
+        };
+        if !matches!(accessor.component_type, 5120..=5123 | 5125 | 5126) {
+            return Err(error(&format!("componentType {} inválido", accessor.component_type)));
+        }
+        let view_index = accessor.buffer_view.ok_or_else(|| error("sin bufferView"))?;
+        let view = doc.buffer_views.get(view_index).ok_or_else(|| error("bufferView no existe"))?;
+        let buffer = self.buffers.get(view.buffer).ok_or_else(|| error("buffer no existe"))?;
+
+        let mut data = AccessorData {
+            bytes: &[],
+            stride: 0,
+            count: accessor.count,
+            components,
+            component_type: accessor.component_type,
+            normalized: accessor.normalized,
+        };
+        let element_size = components * data.component_size();
+        data.stride = view.byte_stride.unwrap_or(element_size);
+        let view_bytes = buffer.get(view.byte_offset..view.byte_offset + view.byte_length)
+            .ok_or_else(|| error("bufferView se sale del buffer"))?;
+        let needed = if accessor.count == 0 { 0 } else { (accessor.count - 1) * data.stride + element_size };
+        data.bytes = view_bytes.get(accessor.byte_offset..accessor.byte_offset + needed)
+            .ok_or_else(|| error("se sale de su bufferView"))?;
+        Ok(data)
+    }
+
+    fn add_mesh(&mut self, index: usize, world: &Mat4) -> io::Result<()> {
+        let doc = self.doc;
+        let mesh = doc.meshes.get(index).ok_or_else(|| invalid_data(format!("malla {} no existe", index)))?;
+        // Una transformación que refleja invierte el sentido de las caras
+        let mirrored = world.determinant() < 0.0;
+
+        for primitive in &mesh.primitives {
+            let mode = primitive.mode.unwrap_or(4);
+            if !(4..=6).contains(&mode) {
+                self.skipped_primitives += 1;
+                continue;
+            }
+            let position_index = *primitive.attributes.get("POSITION")
+                .ok_or_else(|| invalid_data(format!("malla {}: primitiva sin POSITION", index)))?;
+            let positions_data = self.accessor(position_index)?;
+            if positions_data.components != 3 {
+                return Err(invalid_data(format!("accessor {}: POSITION debe ser VEC3", position_index)));
+            }
+            let positions: Vec<Vec3> = (0..positions_data.count)
+                .map(|i| world.transform_point(Vec3::new(positions_data.float(i, 0), positions_data.float(i, 1), positions_data.float(i, 2))))
+                .collect();
+            let normals: Option<Vec<Vec3>> = match primitive.attributes.get("NORMAL") {
+                Some(&normal_index) => {
+                    let data = self.accessor(normal_index)?;
+                    if data.components != 3 || data.count != positions.len() {
+                        return Err(invalid_data(format!("accessor {}: NORMAL debe ser VEC3 con un valor por vértice", normal_index)));
+                    }
+                    Some((0..data.count).map(|i| world.transform_normal(Vec3::new(data.float(i, 0), data.float(i, 1), data.float(i, 2)))).collect())
+                }
+                None => None,
+            };
+            // glTF pone el origen de las UV arriba a la izquierda; OBJ y las texturas, abajo
+            let uvs: Option<Vec<(f32, f32)>> = match primitive.attributes.get("TEXCOORD_0") {
+                Some(&uv_index) => {
+                    let data = self.accessor(uv_index)?;
+                    Some((0..data.count).map(|i| (data.float(i, 0), 1.0 - data.float(i, 1))).collect())
+                }
+                None => None,
+            };
+            let indices: Vec<usize> = match primitive.indices {
+                Some(indices_index) => {
+                    let data = self.accessor(indices_index)?;
+                    if data.components != 1 || !matches!(data.component_type, 5121 | 5123 | 5125) {
+                        return Err(invalid_data(format!("accessor {}: índices inválidos", indices_index)));
+                    }
+                    (0..data.count).map(|i| data.index(i)).collect()
+                }
+                None => (0..positions.len()).collect(),
+            };
+            if let Some(&bad) = indices.iter().find(|&&i| i >= positions.len() || uvs.as_ref().is_some_and(|uvs| i >= uvs.len())) {
+                return Err(invalid_data(format!("malla {}: índice {} fuera de rango", index, bad)));
+            }
+
+            let corners: Vec<[usize; 3]> = match mode {
+                4 => indices.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect(),
+                // Tira: los triángulos impares se invierten para mantener el sentido
+                5 => (0..indices.len().saturating_sub(2))
+                    .map(|i| if i % 2 == 0 { [indices[i], indices[i + 1], indices[i + 2]] } else { [indices[i], indices[i + 2], indices[i + 1]] })
+                    .collect(),
+                _ => (1..indices.len().saturating_sub(1)).map(|i| [indices[0], indices[i], indices[i + 1]]).collect(),
+            };
+            let material = primitive.material
+                .map(|i| self.materials.get(i).copied().ok_or_else(|| invalid_data(format!("material {} no existe", i))))
+                .transpose()?
+                .unwrap_or(self.default_material);
+
+            for [a, mut b, mut c] in corners {
+                if mirrored {
+                    std::mem::swap(&mut b, &mut c);
+                }
+                let (v0, v1, v2) = (positions[a], positions[b], positions[c]);
+                if (v1 - v0).cross(v2 - v0).length_squared() < 1e-20 {
+                    self.degenerate += 1;
+                    continue;
+                }
+                let triangle = match &uvs {
+                    Some(uvs) => Triangle::new_with_uvs(v0, v1, v2, uvs[a], uvs[b], uvs[c], material),
+                    None => Triangle::new(v0, v1, v2, material),
+                };
+                self.output.triangles.push(match &normals {
+                    Some(normals) => triangle.with_vertex_normals(normals[a], normals[b], normals[c]),
+                    None => triangle,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn vec3(v: [f32; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_base64(data: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut text = String::new();
+        for chunk in data.chunks(3) {
+            let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                text.push(if i <= chunk.len() { ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char } else { '=' });
+            }
+        }
+        text
+    }
+
+    // Un triángulo: 3 posiciones float (36 bytes) y 3 índices u16 (6 bytes, más 2 de relleno)
+    fn triangle_buffer() -> Vec<u8> {
+        let mut buffer = Vec::new();
+        for v in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0] {
+            buffer.extend_from_slice(&v.to_le_bytes());
+        }
+        for i in [0u16, 1, 2, 0] {
+            buffer.extend_from_slice(&i.to_le_bytes());
+        }
+        buffer
+    }
+
+    fn triangle_document(buffer_uri: Option<&str>, position_count: usize) -> String {
+        let uri = buffer_uri.map(|uri| format!(r#""uri": "{}", "#, uri)).unwrap_or_default();
+        format!(r#"{{
+            "asset": {{ "version": "2.0" }},
+            "nodes": [{{ "mesh": 0, "translation": [0, 5, 0] }}],
+            "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1 }}] }}],
+            "accessors": [
+                {{ "bufferView": 0, "componentType": 5126, "count": {}, "type": "VEC3" }},
+                {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+            ],
+            "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+                {{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }}
+            ],
+            "buffers": [{{ {}"byteLength": 44 }}]
+        }}"#, position_count, uri)
+    }
+
+    fn import(data: &[u8]) -> io::Result<GltfScene> {
+        import_gltf(data, Path::new("prueba.gltf"), Mat4::identity(), None, &mut MaterialRegistry::new())
+    }
+
+    fn assert_triangle(scene: &GltfScene) {
+        assert_eq!(scene.triangles.len(), 1);
+        let triangle = &scene.triangles[0];
+        // El nodo sube el triángulo 5 unidades
+        assert_eq!(triangle.v0, Vec3::new(0.0, 5.0, 0.0));
+        assert_eq!(triangle.v1, Vec3::new(1.0, 5.0, 0.0));
+        assert_eq!(triangle.v2, Vec3::new(0.0, 7.0, 0.0));
+    }
+
+    #[test]
+    fn decodes_base64() {
+        assert_eq!(decode_base64("TWFu").unwrap(), b"Man");
+        assert_eq!(decode_base64("TWE=").unwrap(), b"Ma");
+        assert_eq!(decode_base64("T Q==").unwrap(), b"M");
+        assert!(decode_base64("TW*u").is_err());
+        let data: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode_base64(&encode_base64(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn reads_accessors_from_data_uri() {
+        let uri = format!("data:application/octet-stream;base64,{}", encode_base64(&triangle_buffer()));
+        assert_triangle(&import(triangle_document(Some(&uri), 3).as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn reads_accessors_from_glb() {
+        let mut json = triangle_document(None, 3).into_bytes();
+        while !json.len().is_multiple_of(4) { json.push(b' '); }
+        let bin = triangle_buffer();
+
+        let mut glb = b"glTF".to_vec();
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&0x4E4F534Au32.to_le_bytes());
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&0x004E4942u32.to_le_bytes());
+        glb.extend_from_slice(&bin);
+        assert_triangle(&import(&glb).unwrap());
+    }
+
+    #[test]
+    fn rejects_accessor_outside_buffer_view() {
+        // 4 posiciones no caben en los 36 bytes del bufferView
+        let uri = format!("data:application/octet-stream;base64,{}", encode_base64(&triangle_buffer()));
+        assert!(import(triangle_document(Some(&uri), 4).as_bytes()).is_err());
+        // Buffer más corto que su byteLength
+        let short = format!("data:application/octet-stream;base64,{}", encode_base64(&triangle_buffer()[..40]));
+        assert!(import(triangle_document(Some(&short), 3).as_bytes()).is_err());
+    }
+
+    #[test]
+    fn decodes_normalized_components() {
+        let bytes = [255u8, 0, 0x00, 0x80, 0xff, 0x7f];
+        let unsigned = AccessorData { bytes: &bytes, stride: 1, count: 2, components: 1, component_type: 5121, normalized: true };
+        assert_eq!((unsigned.float(0, 0), unsigned.float(1, 0)), (1.0, 0.0));
+        let signed = AccessorData { bytes: &bytes[2..], stride: 2, count: 2, components: 1, component_type: 5122, normalized: true };
+        assert_eq!((signed.float(0, 0), signed.float(1, 0)), (-1.0, 1.0));
+    }
+}
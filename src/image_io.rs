@@ -0,0 +1,121 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+pub enum ImageFormat { Ppm, Png }
+
+// Formato que corresponde a la extensión del archivo, si es uno de los soportados
+pub fn image_format(path: &Path) -> Option<ImageFormat> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "ppm" => Some(ImageFormat::Ppm),
+        "png" => Some(ImageFormat::Png),
+        _ => None,
+    }
+}
+
+// Guarda un buffer 0x00RRGGBB (el mismo formato que usa la ventana) según la extensión
+pub fn save_image(path: &Path, width: usize, height: usize, pixels: &[u32]) -> io::Result<()> {
+    assert_eq!(pixels.len(), width * height, "el buffer no coincide con la resolución");
+
+    // La extensión se revisa antes de crear el archivo, para no dejar uno vacío
+    let format = image_format(path).ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("formato de imagen no soportado: '{}' (usar .png o .ppm)", path.display()),
+    ))?;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    match format {
+        ImageFormat::Ppm => write_ppm(&mut writer, width, height, pixels)?,
+        ImageFormat::Png => write_png(&mut writer, width, height, pixels)?,
+    }
+    writer.flush()
+}
+
+fn pixel_rgb(pixel: u32) -> [u8; 3] {
+    [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]
+}
+
+pub fn write_ppm<W: Write>(out: &mut W, width: usize, height: usize, pixels: &[u32]) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", width, height)?;
+    for &pixel in pixels {
+        out.write_all(&pixel_rgb(pixel))?;
+    }
+    Ok(())
+}
+
+// PNG RGB de 8 bits. Los datos van en bloques deflate sin comprimir para no depender
+// de una biblioteca de compresión; cualquier visor los lee igual.
+pub fn write_png<W: Write>(out: &mut W, width: usize, height: usize, pixels: &[u32]) -> io::Result<()> {
+    out.write_all(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'])?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]); // 8 bits, RGB, deflate, filtro 0, sin entrelazado
+    write_png_chunk(out, b"IHDR", &header)?;
+
+    // Cada fila lleva un byte de filtro (0 = ninguno) seguido de los píxeles
+    let mut raw = Vec::with_capacity(height * (width * 3 + 1));
+    for row in pixels.chunks(width) {
+        raw.push(0);
+        for &pixel in row {
+            raw.extend_from_slice(&pixel_rgb(pixel));
+        }
+    }
+    write_png_chunk(out, b"IDAT", &zlib_stored(&raw))?;
+    write_png_chunk(out, b"IEND", &[])
+}
+
+fn write_png_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let crc = crc32_update(crc32_update(0xffff_ffff, kind), data) ^ 0xffff_ffff;
+    out.write_all(&crc.to_be_bytes())
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = 65535;
+    let mut out = Vec::with_capacity(data.len() + data.len() / MAX_BLOCK * 5 + 11);
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(if is_final { 1 } else { 0 });
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
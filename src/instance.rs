@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use crate::area_lights::AreaLight;
+use crate::geometry::*;
+use crate::materials::MaterialRegistry;
+use crate::raytracer::{Emitter, HitInfo};
+use crate::shapes::{Primitive, Span};
+
+// Primitiva con una transformación afín propia: el rayo pasa al espacio del objeto y la
+// normal vuelve al de la escena, así cualquier primitiva se puede rotar, escalar y reflejar
+pub struct Transformed<P: Primitive> {
+    pub primitive: P,
+    transform: Transform,
+    bounds: (Vec3, Vec3),
+}
+
+// Geometría compartida: la misma malla o modelo de bloques ubicado muchas veces
+// sin copiar sus triángulos
+pub type Instance = Transformed<Arc<dyn Primitive>>;
+
+impl<P: Primitive> Transformed<P> {
+    pub fn new(primitive: P, transform: Transform) -> Self {
+        let bounds = transform.transform_bounds(primitive.get_bounds(0.0));
+        Transformed { primitive, transform, bounds }
+    }
+
+    // Rayo en el espacio del objeto y el largo de su dirección antes de normalizarla
+    fn local_ray(&self, ray: &Ray) -> (Ray, f32) {
+        let local_direction = self.transform.inverse.transform_vector(ray.direction);
+        (Ray::new(self.transform.inverse.transform_point(ray.origin), local_direction), local_direction.length())
+    }
+
+    // Ray::new normaliza la dirección: la distancia local se divide por este largo
+    // para volver a medirla en unidades de la escena
+    fn to_world(&self, ray: &Ray, scale: f32, mut hit: HitInfo) -> HitInfo {
+        hit.t /= scale;
+        hit.point = ray.position_at(hit.t);
+        hit.normal = self.transform.matrix.transform_normal(hit.normal);
+        // Las luces que `area_lights` descarta tampoco quedan marcadas en el impacto
+        hit.emitter = match hit.emitter {
+            Emitter::Surface | Emitter::Sphere if self.primitive.is_animated() => Emitter::Unsampled,
+            Emitter::Sphere if self.transform.uniform_scale().is_none() => Emitter::Unsampled,
+            emitter => emitter,
+        };
+        hit
+    }
+}
+
+impl<P: Primitive> Primitive for Transformed<P> {
+    fn intersect(&self, ray: &Ray, time: f32) -> Option<HitInfo> {
+        let (local_ray, scale) = self.local_ray(ray);
+        let hit = self.primitive.intersect(&local_ray, time)?;
+        Some(self.to_world(ray, scale, hit))
+    }
+
+    // La caja guardada sirve mientras la primitiva no se mueva
+    fn get_bounds(&self, time: f32) -> (Vec3, Vec3) {
+        if self.primitive.is_animated() {
+            self.transform.transform_bounds(self.primitive.get_bounds(time))
+        } else {
+            self.bounds
+        }
+    }
+
+    fn is_animated(&self) -> bool {
+        self.primitive.is_animated()
+    }
+
+    fn spans(&self, ray: &Ray, time: f32) -> Option<Vec<Span>> {
+        let (local_ray, scale) = self.local_ray(ray);
+        let spans = self.primitive.spans(&local_ray, time)?;
+        Some(spans.into_iter().map(|span| Span {
+            enter: self.to_world(ray, scale, span.enter),
+            exit: self.to_world(ray, scale, span.exit),
+        }).collect())
+    }
+
+    // El largo máximo también pasa a unidades del objeto
+    fn occluded(&self, ray: &Ray, max_t: f32, time: f32) -> bool {
+        let (local_ray, scale) = self.local_ray(ray);
+        self.primitive.occluded(&local_ray, max_t * scale, time)
+    }
+
+    // Las luces de la primitiva pasan al espacio de la escena; si se mueve, sus luces quedarían
+    // en el lugar de partida, así que no se muestrean
+    fn area_lights(&self, materials: &MaterialRegistry, out: &mut Vec<AreaLight>) {
+        if self.primitive.is_animated() {
+            return;
+        }
+        let mut local = Vec::new();
+        self.primitive.area_lights(materials, &mut local);
+        out.extend(local.iter().filter_map(|light| light.transformed(&self.transform)));
+    }
+}
+
+impl<P: Primitive + ?Sized> Primitive for Arc<P> {
+    fn intersect(&self, ray: &Ray, time: f32) -> Option<HitInfo> {
+        (**self).intersect(ray, time)
+    }
+
+    fn get_bounds(&self, time: f32) -> (Vec3, Vec3) {
+        (**self).get_bounds(time)
+    }
+
+    fn is_animated(&self) -> bool {
+        (**self).is_animated()
+    }
+
+    fn spans(&self, ray: &Ray, time: f32) -> Option<Vec<Span>> {
+        (**self).spans(ray, time)
+    }
+
+    fn occluded(&self, ray: &Ray, max_t: f32, time: f32) -> bool {
+        (**self).occluded(ray, max_t, time)
+    }
+
+    fn area_lights(&self, materials: &MaterialRegistry, out: &mut Vec<AreaLight>) {
+        (**self).area_lights(materials, out)
+    }
+}
+
+impl<P: Primitive + ?Sized> Primitive for Box<P> {
+    fn intersect(&self, ray: &Ray, time: f32) -> Option<HitInfo> {
+        (**self).intersect(ray, time)
+    }
+
+    fn get_bounds(&self, time: f32) -> (Vec3, Vec3) {
+        (**self).get_bounds(time)
+    }
+
+    fn is_animated(&self) -> bool {
+        (**self).is_animated()
+    }
+
+    fn spans(&self, ray: &Ray, time: f32) -> Option<Vec<Span>> {
+        (**self).spans(ray, time)
+    }
+
+    fn occluded(&self, ray: &Ray, max_t: f32, time: f32) -> bool {
+        (**self).occluded(ray, max_t, time)
+    }
+
+    fn area_lights(&self, materials: &MaterialRegistry, out: &mut Vec<AreaLight>) {
+        (**self).area_lights(materials, out)
+    }
+}
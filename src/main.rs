@@ -2,7 +2,27 @@ mod geometry;
 mod raytracer;
 mod materials;
 mod shapes;
+mod bvh;
 mod texture;
+mod cli;
+mod image_io;
+mod parallel;
+mod voxel;
+mod nbt;
+mod blocks;
+mod anvil;
+mod schematic;
+mod scene_file;
+mod mesh;
+mod obj;
+mod gltf;
+mod instance;
+mod sdf;
+mod csg;
+mod terrain;
+mod pathtracer;
+mod area_lights;
+mod bsdf;
 
 use minifb::{Key, Window, WindowOptions};
 use std::time::Instant;
@@ -11,7 +31,11 @@ use raytracer::*;
 use geometry::*;
 use materials::*;
 use shapes::*;
-use texture::*;
+use voxel::*;
+use blocks::BlockRegistry;
+use schematic::{Schematic, StructurePlacement};
+use area_lights::WithoutAreaLights;
+use terrain::TerrainGenerator;
 
 const WIDTH: usize = 512;
 const HEIGHT: usize = 384;
@@ -23,9 +47,28 @@ struct RenderState {
     max_depth: i32,
     ultra_mode: bool,
     checker_phase: bool,
+    path_tracing: bool,
 }
 
 fn main() {
+    let args = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            eprintln!("{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.show_help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    let (mut scene, default_view) = load_scene(&args);
+    if let Some(output) = &args.headless_output {
+        run_headless(&args, output, &mut scene, default_view);
+        return;
+    }
+
     let mut window_options = WindowOptions::default();
     window_options.scale = minifb::Scale::X2;
     window_options.resize = true;
@@ -43,21 +86,13 @@ fn main() {
     window.limit_update_rate(Some(std::time::Duration::from_micros(16666)));
 
     // Cámara inicial posicionada para ver la escena
-    let mut camera = Camera::new(
-        Vec3::new(20.0, 8.0, 20.0),
-        Vec3::new(0.0, 3.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-        45.0,
-        WIDTH as f32 / HEIGHT as f32,
-    );
+    let mut camera = args.build_camera(WIDTH, HEIGHT, default_view);
 
-    let mut scene = create_nether_scene();
-    build_scene_bvh(&mut scene);
     let mut frame_buffer = vec![0u32; WIDTH * HEIGHT];
     let mut prev_full_buffer = vec![0u32; WIDTH * HEIGHT];
     let mut lowres_buffer: Vec<u32> = Vec::new();
     let mut prev_lowres_buffer: Vec<u32> = Vec::new();
-    let mut time = 0.0f32;
+    let mut time = args.time;
     let mut fps_counter = 0;
     let mut fps_timer = Instant::now();
     let mut rotation_y = 0.0f32;
@@ -71,19 +106,27 @@ fn main() {
     println!("1-4: Resolution scale, Y/U/I: Shadows None/SunOnly/Full, F/G: Max depth +/-");
     println!("N/M: Animation speed -/+");
     println!("Z: Ultra mode (checkerboard + temporal reuse)");
+    println!("P: Path tracing (accumulates while the camera is still)");
     println!("Mouse: Look around (drag)");
     println!("Scroll: Zoom in/out");
     println!("ESC: Exit");
     println!("====================================");
 
+    // Con --scene, los cambios en el archivo se aplican sin cerrar la ventana
+    let mut scene_watcher = args.scene.as_deref().map(scene_file::SceneWatcher::new);
+
     let mut render_state = RenderState { 
         scale_factor: 3, 
         shadow_mode: raytracer::ShadowMode::None, 
         max_depth: 2, 
         ultra_mode: true, 
-        checker_phase: false 
+        checker_phase: false,
+        path_tracing: args.path_samples.is_some(),
     };
-    let mut day_speed: f32 = 1.0;  // Velocidad ciclo día/noche
+    // Muestras del trazado de caminos y la vista con la que se tomaron
+    let mut accumulator = pathtracer::Accumulator::new(WIDTH, HEIGHT);
+    let mut accumulated_view = None;
+    let mut day_speed: f32 = args.day_speed;  // Velocidad ciclo día/noche
 
     while window.is_open() && !window.is_key_down(Key::Escape) {
         let start_time = Instant::now();
@@ -92,9 +135,17 @@ fn main() {
         input_state.move_speed = 5.0 * delta_time;
         handle_input(&window, &mut camera, &mut rotation_y, &mut input_state);
         
-        // Ciclo día/noche automático
-        time += 0.016;
+        if let Some(watcher) = &mut scene_watcher
+            && reload_scene(watcher, &mut scene) {
+            accumulator.reset();
+        }
+        
+        // Ciclo día/noche automático; se detiene mientras se acumulan muestras
+        if !render_state.path_tracing {
+            time += 0.016;
+        }
         update_nether_scene(&mut scene, time, day_speed);
+        refit_scene_bvh(&mut scene, time);
         
         let render_start = Instant::now();
         if window.is_key_pressed(Key::Key1, minifb::KeyRepeat::No) { render_state.scale_factor = 1; }
@@ -107,16 +158,31 @@ fn main() {
         if window.is_key_pressed(Key::U, minifb::KeyRepeat::No) { render_state.shadow_mode = raytracer::ShadowMode::SunOnly; println!("Shadows: SunOnly"); }
         if window.is_key_pressed(Key::I, minifb::KeyRepeat::No) { render_state.shadow_mode = raytracer::ShadowMode::Full; println!("Shadows: Full"); }
         if window.is_key_pressed(Key::Z, minifb::KeyRepeat::No) { render_state.ultra_mode = !render_state.ultra_mode; println!("Ultra mode: {}", if render_state.ultra_mode { "ON" } else { "OFF" }); }
-        if window.is_key_pressed(Key::N, minifb::KeyRepeat::No) { day_speed = (day_speed - 0.05).max(0.02); println!("Animation speed: {:.2}", day_speed); }
-        if window.is_key_pressed(Key::M, minifb::KeyRepeat::No) { day_speed = (day_speed + 0.05).min(1.0); println!("Animation speed: {:.2}", day_speed); }
+        if window.is_key_pressed(Key::P, minifb::KeyRepeat::No) { render_state.path_tracing = !render_state.path_tracing; accumulator.reset(); println!("Path tracing: {}", if render_state.path_tracing { "ON" } else { "OFF" }); }
+        if window.is_key_pressed(Key::N, minifb::KeyRepeat::No) { day_speed = (day_speed - 0.05).max(0.02); accumulator.reset(); println!("Animation speed: {:.2}", day_speed); }
+        if window.is_key_pressed(Key::M, minifb::KeyRepeat::No) { day_speed = (day_speed + 0.05).min(1.0); accumulator.reset(); println!("Animation speed: {:.2}", day_speed); }
         
         let opts = raytracer::RenderOptions { 
             shadow_mode: render_state.shadow_mode, 
             max_depth: render_state.max_depth, 
-            far_simplify_distance: 20.0 
+            far_simplify_distance: 20.0,
+            threads: args.threads,
+            shadow_samples: args.shadow_samples,
         };
         
-        if render_state.ultra_mode {
+        if render_state.path_tracing {
+            // Mover o girar la cámara descarta lo acumulado
+            let view = (camera.position, camera.target, camera.fov, rotation_y);
+            if accumulated_view != Some(view) {
+                accumulator.reset();
+                accumulated_view = Some(view);
+            }
+            if args.path_samples.is_none_or(|target| accumulator.samples() < target) {
+                let frame = camera.build_frame(WIDTH, HEIGHT);
+                accumulator.add_pass(&scene, &frame, time, rotation_y, args.threads);
+                accumulator.resolve(&mut frame_buffer, color_to_u32);
+            }
+        } else if render_state.ultra_mode {
             render_checkerboard_scaled(
                 &scene,
                 &camera,
@@ -146,7 +212,11 @@ fn main() {
         fps_counter += 1;
         if fps_timer.elapsed().as_secs_f32() >= 1.0 {
             let fps = fps_counter as f32 / fps_timer.elapsed().as_secs_f32();
-            println!("FPS: {:.1}, Render: {:.2}ms", fps, render_time.as_secs_f32() * 1000.0);
+            if render_state.path_tracing {
+                println!("FPS: {:.1}, Render: {:.2}ms, Samples: {}", fps, render_time.as_secs_f32() * 1000.0, accumulator.samples());
+            } else {
+                println!("FPS: {:.1}, Render: {:.2}ms", fps, render_time.as_secs_f32() * 1000.0);
+            }
             fps_counter = 0;
             fps_timer = Instant::now();
         }
@@ -158,6 +228,88 @@ fn main() {
     }
 }
 
+// Escena a renderizar (archivo de escena, mundo cargado, terreno generado o la escena del Nether) y vista por defecto de la cámara
+fn load_scene(args: &cli::CliArgs) -> (Scene, CameraView) {
+    let nether_view = CameraView::new(Vec3::new(20.0, 8.0, 20.0), Vec3::new(0.0, 3.0, 0.0));
+    let (mut scene, view) = match (&args.scene, &args.world, args.terrain_seed) {
+        (Some(path), _, _) => {
+            let loaded = scene_file::load_scene_file(path).unwrap_or_else(|e| {
+                eprintln!("Error loading scene {}: {}", path.display(), e);
+                std::process::exit(1);
+            });
+            (loaded.scene, loaded.view.unwrap_or(nether_view))
+        }
+        (None, Some(path), _) => create_world_scene(path, &args.world_options, &args.structures).unwrap_or_else(|e| {
+            eprintln!("Error loading world {}: {}", path.display(), e);
+            std::process::exit(1);
+        }),
+        (None, None, Some(seed)) => create_terrain_scene(seed, args.terrain_size, &args.structures).unwrap_or_else(|e| {
+            eprintln!("Error loading scene: {}", e);
+            std::process::exit(1);
+        }),
+        (None, None, None) => {
+            let scene = create_nether_scene(&args.structures).unwrap_or_else(|e| {
+                eprintln!("Error loading scene: {}", e);
+                std::process::exit(1);
+            });
+            (scene, nether_view)
+        }
+    };
+    build_scene_bvh(&mut scene);
+    collect_area_lights(&mut scene);
+    (scene, view)
+}
+
+// Reemplaza la escena si el archivo cambió; la cámara y el tiempo siguen igual.
+// Un archivo con errores deja la escena anterior en pantalla. Devuelve si hubo cambio.
+fn reload_scene(watcher: &mut scene_file::SceneWatcher, scene: &mut Scene) -> bool {
+    match watcher.poll() {
+        Some(Ok(loaded)) => {
+            let start = Instant::now();
+            *scene = loaded.scene;
+            build_scene_bvh(scene);
+            collect_area_lights(scene);
+            println!("Reloaded {} in {:.1}ms", watcher.path().display(), start.elapsed().as_secs_f32() * 1000.0);
+            true
+        }
+        Some(Err(e)) => {
+            eprintln!("Error reloading scene {}: {}", watcher.path().display(), e);
+            false
+        }
+        None => false,
+    }
+}
+
+// Render sin ventana: una sola imagen al tiempo indicado, escrita a disco
+fn run_headless(args: &cli::CliArgs, output: &std::path::Path, scene: &mut Scene, default_view: CameraView) {
+    let camera = args.build_camera(args.width, args.height, default_view);
+    update_nether_scene(scene, args.time, args.day_speed);
+    refit_scene_bvh(scene, args.time);
+    let scene = &*scene;
+
+    let opts = args.render_options();
+    let start = Instant::now();
+    let mut buffer = vec![0u32; args.width * args.height];
+    let frame = camera.build_frame(args.width, args.height);
+    if let Some(samples) = args.path_samples {
+        let mut accumulator = pathtracer::Accumulator::new(args.width, args.height);
+        for _ in 0..samples {
+            accumulator.add_pass(scene, &frame, args.time, 0.0, opts.threads);
+        }
+        accumulator.resolve(&mut buffer, color_to_u32);
+        println!("Rendered {}x{} with {} samples per pixel in {:.2}s", args.width, args.height, samples, start.elapsed().as_secs_f32());
+    } else {
+        render_frame(scene, &frame, &mut buffer, args.time, 0.0, opts);
+        println!("Rendered {}x{} in {:.2}s", args.width, args.height, start.elapsed().as_secs_f32());
+    }
+
+    if let Err(e) = image_io::save_image(output, args.width, args.height, &buffer) {
+        eprintln!("Error writing {}: {}", output.display(), e);
+        std::process::exit(1);
+    }
+    println!("Saved {}", output.display());
+}
+
 #[derive(Default)]
 struct InputState {
     last_mouse_pos: Option<(f32, f32)>,
@@ -230,13 +382,23 @@ fn handle_input(
 
 fn render_parallel(scene: &Scene, camera: &Camera, buffer: &mut [u32], time: f32, rotation_y: f32, opts: raytracer::RenderOptions) {
     let frame = camera.build_frame(WIDTH, HEIGHT);
-    for (y, row) in buffer.chunks_mut(WIDTH).enumerate() {
+    render_frame(scene, &frame, buffer, time, rotation_y, opts);
+}
+
+fn render_frame(scene: &Scene, frame: &CameraFrame, buffer: &mut [u32], time: f32, rotation_y: f32, opts: raytracer::RenderOptions) {
+    parallel::for_each_row(buffer, frame.width, opts.threads, |y, row| {
         for (x, pixel) in row.iter_mut().enumerate() {
-            let ray = frame.get_ray(x as f32, y as f32);
-            let color = trace_ray(&ray, scene, 0, time, rotation_y, &opts);
-            *pixel = color_to_u32(color);
+            *pixel = render_pixel(scene, frame, x, y, time, rotation_y, &opts);
         }
-    }
+    });
+}
+
+// El generador aleatorio se siembra por píxel para que la imagen no dependa
+// del hilo que tomó cada fila
+fn render_pixel(scene: &Scene, frame: &CameraFrame, x: usize, y: usize, time: f32, rotation_y: f32, opts: &raytracer::RenderOptions) -> u32 {
+    seed_pixel_rng(x, y, time.to_bits());
+    let ray = frame.get_ray(x as f32, y as f32);
+    color_to_u32(trace_ray(&ray, scene, 0, time, rotation_y, opts))
 }
 
 fn render_parallel_scaled(scene: &Scene, camera: &Camera, full_buffer: &mut [u32], lowres_buffer: &mut Vec<u32>, time: f32, rotation_y: f32, scale_factor: usize, opts: raytracer::RenderOptions) {
@@ -248,13 +410,7 @@ fn render_parallel_scaled(scene: &Scene, camera: &Camera, full_buffer: &mut [u32
     let lh = HEIGHT / scale_factor;
     if lowres_buffer.len() != lw * lh { lowres_buffer.resize(lw * lh, 0); }
     let frame = camera.build_frame(lw, lh);
-    for (y, row) in lowres_buffer.chunks_mut(lw).enumerate() {
-        for (x, pixel) in row.iter_mut().enumerate() {
-            let ray = frame.get_ray(x as f32, y as f32);
-            let color = trace_ray(&ray, scene, 0, time, rotation_y, &opts);
-            *pixel = color_to_u32(color);
-        }
-    }
+    render_frame(scene, &frame, lowres_buffer, time, rotation_y, opts);
     for y in 0..HEIGHT {
         let mut src_y = y / scale_factor;
         if src_y >= lh { src_y = lh - 1; }
@@ -282,19 +438,18 @@ fn render_checkerboard_scaled(
 ) {
     if scale_factor <= 1 {
         let frame = camera.build_frame(WIDTH, HEIGHT);
-        for (y, row) in full_buffer.chunks_mut(WIDTH).enumerate() {
-            for x in 0..WIDTH {
+        let prev: &[u32] = prev_full_buffer;
+        parallel::for_each_row(full_buffer, WIDTH, opts.threads, |y, row| {
+            for (x, pixel) in row.iter_mut().enumerate() {
                 let pattern = ((x + y) & 1) == 0;
                 if pattern == phase {
-                    let ray = frame.get_ray(x as f32, y as f32);
-                    let color = trace_ray(&ray, scene, 0, time, rotation_y, &opts);
-                    row[x] = color_to_u32(color);
+                    *pixel = render_pixel(scene, &frame, x, y, time, rotation_y, &opts);
                 } else {
-                    row[x] = prev_full_buffer[y * WIDTH + x];
+                    *pixel = prev[y * WIDTH + x];
                 }
             }
-        }
-        prev_full_buffer.copy_from_slice(&full_buffer);
+        });
+        prev_full_buffer.copy_from_slice(full_buffer);
         return;
     }
     let lw = WIDTH / scale_factor;
@@ -302,18 +457,17 @@ fn render_checkerboard_scaled(
     if lowres_buffer.len() != lw * lh { lowres_buffer.resize(lw * lh, 0); }
     if prev_lowres_buffer.len() != lw * lh { prev_lowres_buffer.resize(lw * lh, 0); }
     let frame = camera.build_frame(lw, lh);
-    for (y, row) in lowres_buffer.chunks_mut(lw).enumerate() {
-        for x in 0..lw {
+    let prev: &[u32] = prev_lowres_buffer;
+    parallel::for_each_row(lowres_buffer, lw, opts.threads, |y, row| {
+        for (x, pixel) in row.iter_mut().enumerate() {
             let pattern = ((x + y) & 1) == 0;
             if pattern == phase {
-                let ray = frame.get_ray(x as f32, y as f32);
-                let color = trace_ray(&ray, scene, 0, time, rotation_y, &opts);
-                row[x] = color_to_u32(color);
+                *pixel = render_pixel(scene, &frame, x, y, time, rotation_y, &opts);
             } else {
-                row[x] = prev_lowres_buffer[y * lw + x];
+                *pixel = prev[y * lw + x];
             }
         }
-    }
+    });
     for y in 0..HEIGHT {
         let mut src_y = y / scale_factor;
         if src_y >= lh { src_y = lh - 1; }
@@ -327,165 +481,198 @@ fn render_checkerboard_scaled(
     *prev_lowres_buffer = lowres_buffer.clone();
 }
 
-fn create_nether_scene() -> Scene {
+fn create_nether_scene(structures: &[StructurePlacement]) -> std::io::Result<Scene> {
     let mut scene = Scene::new();
     
-    let materials = create_nether_materials();
+    // Los bloques van a una grilla de vóxeles; con origen en -0.5 el bloque (x, y, z)
+    // queda centrado en (x, y, z) igual que los cubos unitarios de antes
+    let mut world = VoxelWorld::new(Vec3::new(-0.5, -0.5, -0.5));
+    let mut registry = BlockRegistry::new(&mut scene.materials);
+    let blocks = NetherBlocks::register(&mut world, &mut registry);
     
-    create_nether_terrain(&mut scene, &materials);
-    create_bedrock_pillars(&mut scene, &materials);
-    create_single_portal(&mut scene, &materials);
+    create_nether_terrain(&mut world, &blocks);
+    create_bedrock_pillars(&mut world, &blocks);
+    create_single_portal(&mut world, &blocks);
+    place_structures(&mut world, &mut registry, structures)?;
+    scene.objects.push(Box::new(world));
     create_sun(&mut scene);
     
     setup_lighting(&mut scene);
     scene.skybox = Some(create_nether_skybox());
+    scene.day_cycle = true;
     
-    scene
+    Ok(scene)
 }
 
-fn create_nether_materials() -> NetherMaterials {
-    NetherMaterials::new()
+// Escena con los bloques de un mundo guardado en lugar del terreno fijo del Nether
+fn create_world_scene(path: &std::path::Path, options: &anvil::WorldLoadOptions, structures: &[StructurePlacement]) -> std::io::Result<(Scene, CameraView)> {
+    let mut scene = Scene::new();
+    let mut world = VoxelWorld::new(Vec3::zero());
+    let mut registry = BlockRegistry::new(&mut scene.materials);
+    let start = Instant::now();
+    let stats = anvil::load_world(path, &mut world, &mut registry, options)?;
+    println!(
+        "Loaded {} chunks ({} sections) from {} region file(s) in {:.2}s",
+        stats.chunks, stats.sections, stats.regions, start.elapsed().as_secs_f32()
+    );
+    if stats.skipped_chunks > 0 {
+        println!("Skipped {} unreadable chunk(s)", stats.skipped_chunks);
+    }
+    place_structures(&mut world, &mut registry, structures)?;
+    if world.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "el mundo no contiene bloques en el área pedida"));
+    }
+    
+    // Mirar al suelo en el centro del área cargada
+    let (min, max) = world.get_bounds(0.0);
+    let center = (min + max) * 0.5;
+    let ground = world.top_block(center.x.floor() as i32, center.z.floor() as i32)
+        .map(|y| y as f32 + 1.0)
+        .unwrap_or(max.y);
+    let target = Vec3::new(center.x, ground, center.z);
+    let reach = (max.x - min.x).max(max.z - min.z).clamp(16.0, 96.0) * 0.5;
+    let position = target + Vec3::new(reach, reach * 0.6, reach);
+    
+    scene.objects.push(Box::new(world));
+    // Sol direccional: el mundo puede quedar lejos del origen, donde una luz puntual no llega
+    scene.lights.push(Light::directional(Vec3::new(-0.3, -1.0, -0.2), Vec3::new(1.0, 0.95, 0.8), 1.5));
+    scene.skybox = Some(create_nether_skybox());
+    
+    Ok((scene, CameraView::new(position, target)))
 }
 
-struct NetherMaterials {
-    pub netherrack: Material,
-    pub lava: Material,
-    pub obsidian: Material,
-    pub portal: Material,
+// Terreno del Nether generado con una semilla, centrado en el origen; se ve en corte desde
+// una esquina, con luces dentro de la caverna porque el techo tapa el cielo
+fn create_terrain_scene(seed: u32, size: (i32, i32, i32), structures: &[StructurePlacement]) -> std::io::Result<(Scene, CameraView)> {
+    let mut scene = Scene::new();
+    let mut world = VoxelWorld::new(Vec3::new(-0.5, -0.5, -0.5));
+    let mut registry = BlockRegistry::new(&mut scene.materials);
+    let start = Instant::now();
+    let generator = TerrainGenerator::new(seed);
+    let placed = generator.place(&mut world, &mut registry, (-size.0 / 2, 0, -size.2 / 2), size);
+    println!(
+        "Generated terrain (seed {}, {}x{}x{}, {} blocks) in {:.2}s",
+        seed, size.0, size.1, size.2, placed, start.elapsed().as_secs_f32()
+    );
+    place_structures(&mut world, &mut registry, structures)?;
+    
+    let (width, height, depth) = (size.0 as f32, size.1 as f32, size.2 as f32);
+    scene.objects.push(Box::new(world));
+    scene.ambient_light = Vec3::new(0.25, 0.12, 0.1);
+    scene.lights.push(Light::point(Vec3::new(0.0, height * 0.5, 0.0), Vec3::new(1.0, 0.55, 0.3), 4.0));
+    scene.lights.push(Light::point(Vec3::new(width * 0.25, height * 0.45, depth * 0.25), Vec3::new(1.0, 0.4, 0.1), 3.0));
+    scene.skybox = Some(create_nether_skybox());
+    
+    let target = Vec3::new(0.0, height * 0.35, 0.0);
+    let position = Vec3::new(width * 0.8, height * 0.55, depth * 0.8);
+    Ok((scene, CameraView::new(position, target)))
 }
 
-impl NetherMaterials {
-    fn new() -> Self {
+// Coloca los esquemáticos pedidos; comparten el registro con el resto de la escena para
+// que su obsidiana o su portal usen el mismo tipo de bloque
+fn place_structures(world: &mut VoxelWorld, registry: &mut BlockRegistry, structures: &[StructurePlacement]) -> std::io::Result<()> {
+    for structure in structures {
+        let schematic = Schematic::load(&structure.path).map_err(|e| {
+            std::io::Error::new(e.kind(), format!("{}: {}", structure.path.display(), e))
+        })?;
+        let placed = schematic.place(world, registry, structure.origin, structure.rotation);
+        println!(
+            "Placed {} ({}x{}x{}, {} blocks) at {:?}",
+            structure.path.display(), schematic.size.0, schematic.size.1, schematic.size.2, placed, structure.origin
+        );
+    }
+    Ok(())
+}
+
+// IDs de bloque de la escena dentro del VoxelWorld
+struct NetherBlocks {
+    netherrack: BlockId,
+    lava: BlockId,
+    obsidian: BlockId,
+    portal: BlockId,
+}
+
+impl NetherBlocks {
+    fn register(world: &mut VoxelWorld, registry: &mut BlockRegistry) -> Self {
         Self {
-            netherrack: Material::new()
-                .with_properties(Vec3::new(1.0, 0.3, 0.3), 0.0, 0.0, 0.3),
-            
-            lava: Material::emissive(Vec3::new(5.0, 2.5, 0.0), 20.0)
-                .with_texture(Texture::animated_fire())
-                .with_properties(Vec3::new(2.0, 1.2, 0.3), 0.8, 0.0, 0.3),
-            
-            obsidian: Material::new()
-                .with_texture(Texture::minecraft_obsidian())
-                .with_properties(Vec3::new(0.05, 0.02, 0.08), 0.3, 0.0, 0.0),
-            
-            portal: Material::new()
-                .with_texture(Texture::nether_portal())
-                .with_properties(Vec3::new(0.5, 0.1, 0.8), 0.1, 0.9, 0.3)
-                .with_emissive(Vec3::new(0.4, 0.15, 0.6)),
+            netherrack: registry.id_for(world, "minecraft:netherrack"),
+            lava: registry.id_for(world, "minecraft:lava"),
+            obsidian: registry.id_for(world, "minecraft:obsidian"),
+            portal: registry.id_for(world, "minecraft:nether_portal"),
         }
     }
 }
 
-fn create_nether_terrain(scene: &mut Scene, materials: &NetherMaterials) {
+fn create_nether_terrain(world: &mut VoxelWorld, blocks: &NetherBlocks) {
     for x in -10..10 {
         for z in -10..10 {
-            scene.objects.push(Box::new(Cube::new(
-                Vec3::new(x as f32, -1.0, z as f32),
-                1.0,
-                materials.netherrack.clone(),
-            )));
+            world.set_block(x, -1, z, blocks.netherrack);
             
             for y in -3..-1 {
                 if (x + z + y) % 2 == 0 {
-                    scene.objects.push(Box::new(Cube::new(
-                        Vec3::new(x as f32, y as f32, z as f32),
-                        1.0,
-                        materials.netherrack.clone(),
-                    )));
+                    world.set_block(x, y, z, blocks.netherrack);
                 }
             }
             
             if (x + z) % 3 == 0 {
-                scene.objects.push(Box::new(Cube::new(
-                    Vec3::new(x as f32, -4.0, z as f32),
-                    1.0,
-                    materials.netherrack.clone(),
-                )));
+                world.set_block(x, -4, z, blocks.netherrack);
             }
         }
     }
     
     // Pozos de lava en el suelo
     let lava_positions = [
-        (-6.0, 0.0, -6.0),
-        (5.0, 0.0, 5.0),
+        (-6, 0, -6),
+        (5, 0, 5),
     ];
     
     for (lx, ly, lz) in lava_positions.iter() {
         for dx in 0..2 {
             for dz in 0..2 {
-                scene.objects.push(Box::new(Cube::new(
-                    Vec3::new(lx + dx as f32, *ly, lz + dz as f32),
-                    1.0,
-                    materials.lava.clone(),
-                )));
+                world.set_block(lx + dx, *ly, lz + dz, blocks.lava);
             }
         }
     }
 }
 
-fn create_bedrock_pillars(scene: &mut Scene, materials: &NetherMaterials) {
-    let pillar_positions = [
-        (8.0_f32, 8.0_f32),
-        (-7.0_f32, -7.0_f32),
-        (6.0_f32, -6.0_f32),
+fn create_bedrock_pillars(world: &mut VoxelWorld, blocks: &NetherBlocks) {
+    let pillar_positions: [(i32, i32); 3] = [
+        (8, 8),
+        (-7, -7),
+        (6, -6),
     ];
     
     for (px, pz) in pillar_positions.iter() {
-        let height = 5 + ((px.abs() + pz.abs()) as i32 % 3);
+        let height = 5 + ((px.abs() + pz.abs()) % 3);
         for y in 0..height {
-            scene.objects.push(Box::new(Cube::new(
-                Vec3::new(*px, y as f32, *pz),
-                1.0,
-                materials.obsidian.clone(),
-            )));
+            world.set_block(*px, y, *pz, blocks.obsidian);
         }
         
     }
 }
 
-fn create_single_portal(scene: &mut Scene, materials: &NetherMaterials) {
-    let portal_x = 0.0;
-    let portal_z = 0.0;
+fn create_single_portal(world: &mut VoxelWorld, blocks: &NetherBlocks) {
+    let portal_x = 0;
+    let portal_z = 0;
     let width = 3;
     let height = 5;
     
     // Marco de obsidiana - pilares izquierdo y derecho
     for y in 0..height {
-        scene.objects.push(Box::new(Cube::new(
-            Vec3::new(portal_x - 1.0, y as f32, portal_z),
-            1.0,
-            materials.obsidian.clone(),
-        )));
-        scene.objects.push(Box::new(Cube::new(
-            Vec3::new(portal_x + width as f32, y as f32, portal_z),
-            1.0,
-            materials.obsidian.clone(),
-        )));
+        world.set_block(portal_x - 1, y, portal_z, blocks.obsidian);
+        world.set_block(portal_x + width, y, portal_z, blocks.obsidian);
     }
     
     // Arriba y abajo
     for wx in 0..width {
-        scene.objects.push(Box::new(Cube::new(
-            Vec3::new(portal_x + wx as f32, -1.0, portal_z),
-            1.0,
-            materials.obsidian.clone(),
-        )));
-        scene.objects.push(Box::new(Cube::new(
-            Vec3::new(portal_x + wx as f32, height as f32, portal_z),
-            1.0,
-            materials.obsidian.clone(),
-        )));
+        world.set_block(portal_x + wx, -1, portal_z, blocks.obsidian);
+        world.set_block(portal_x + wx, height, portal_z, blocks.obsidian);
     }
     
     // Efecto del portal adentro
     for y in 0..height {
         for wx in 0..width {
-            scene.objects.push(Box::new(Cube::new(
-                Vec3::new(portal_x + wx as f32, y as f32, portal_z),
-                1.0,
-                materials.portal.clone(),
-            )));
+            world.set_block(portal_x + wx, y, portal_z, blocks.portal);
         }
     }
 }
@@ -495,45 +682,20 @@ fn create_sun(scene: &mut Scene) {
     let sun_material = Material::emissive(Vec3::new(1.5, 1.4, 1.0), 15.0)
         .with_properties(Vec3::new(1.0, 1.0, 0.8), 1.0, 0.0, 0.0);
     
-    scene.objects.push(Box::new(Sphere::new(
+    // Solo se ve: la luz del sol es la primera luz de la escena, que se mueve con el ciclo
+    scene.objects.push(Box::new(WithoutAreaLights(Sphere::new(
         Vec3::new(0.0, 30.0, 0.0),
         3.0,
-        sun_material,
-    )));
+        scene.materials.add(sun_material),
+    ))));
 }
 
 fn setup_lighting(scene: &mut Scene) {
-    // Luz del sol (se mueve con el ciclo)
-    scene.lights.push(Light {
-        position: Vec3::new(0.0, 30.0, 0.0),
-        color: Vec3::new(1.0, 0.95, 0.8),
-        intensity: 1.5,
-        light_type: LightType::Point,
-    });
-    
-    // Luz del portal central
-    scene.lights.push(Light {
-        position: Vec3::new(1.5, 2.5, 0.0),
-        color: Vec3::new(0.6, 0.2, 0.9),
-        intensity: 4.0,
-        light_type: LightType::Point,
-    });
-    
-    // Luces de los pozos de lava
-    let lava_lights = [
-        (-6.0, 0.0, -6.0),
-        (5.0, 0.0, 5.0),
-    ];
-    
-    for (lx, ly, lz) in lava_lights.iter() {
-        scene.lights.push(Light {
-            position: Vec3::new(*lx, *ly + 1.0, *lz),
-            color: Vec3::new(1.0, 0.4, 0.1),
-            intensity: 3.5,
-            light_type: LightType::Point,
-        });
-    }
+    // Luz del sol (se mueve con el ciclo); con el radio de la esfera del sol, sus sombras
+    // tienen penumbra
+    scene.lights.push(Light::sphere(Vec3::new(0.0, 30.0, 0.0), 3.0, Vec3::new(1.0, 0.95, 0.8), 1.5));
     
+    // La lava y el portal iluminan como luces de área (ver collect_area_lights)
 }
 
 fn create_nether_skybox() -> Skybox {
@@ -546,6 +708,10 @@ fn create_nether_skybox() -> Skybox {
 }
 
 fn update_nether_scene(scene: &mut Scene, time: f32, speed: f32) {
+    if !scene.day_cycle {
+        return;
+    }
+    
     // Mover el sol en trayectoria circular
     let angle = time * speed;
     let sun_radius = 40.0;
@@ -555,6 +721,9 @@ fn update_nether_scene(scene: &mut Scene, time: f32, speed: f32) {
     // Actualizar posición de la luz del sol
     if let Some(sun_light) = scene.lights.get_mut(0) {
         sun_light.position = Vec3::new(sun_x, sun_y.max(5.0), 0.0);
+        if let LightType::Directional(_) = sun_light.light_type {
+            sun_light.light_type = LightType::Directional(-sun_light.position.normalize());
+        }
         
         // Ajustar intensidad según altura del sol
         let height_factor = (sun_y / sun_radius).max(0.0);
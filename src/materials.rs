@@ -1,29 +1,41 @@
+use std::ops::{Index, IndexMut};
+
 use crate::geometry::Vec3;
 use crate::texture::Texture;
 
+// Índice de un material en la tabla de la escena
+pub type MaterialId = u32;
+
+// Modelo metallic-roughness (ver bsdf.rs): `albedo` es el color base, `metallic` mezcla entre
+// dieléctrico y metal, `roughness` ensancha brillos y reflejos, y `specular` fija cuánto
+// reflejan los dieléctricos de frente (0.5 es el 4%). Con `transparency` la superficie es en
+// esa parte un dieléctrico liso que refleja o refracta según Fresnel, y `absorption` tiñe por
+// espesor la luz que viaja por adentro
 #[derive(Clone)]
 pub struct Material {
     pub albedo: Vec3,
     pub specular: f32,
     pub transparency: f32,
-    pub reflectivity: f32,
+    pub metallic: f32,
     pub refraction_index: f32,
     pub texture: Option<Texture>,
     pub emissive: Vec3,
     pub roughness: f32,
+    pub absorption: Vec3, // Coeficientes de absorción por unidad de distancia (Beer-Lambert)
 }
 
 impl Material {
     pub fn new() -> Self {
         Material {
             albedo: Vec3::new(0.8, 0.8, 0.8),
-            specular: 0.1,
+            specular: 0.5,
             transparency: 0.0,
-            reflectivity: 0.1,
+            metallic: 0.0,
             refraction_index: 1.0,
             texture: None,
             emissive: Vec3::zero(),
             roughness: 0.5,
+            absorption: Vec3::zero(),
         }
     }
     
@@ -32,14 +44,51 @@ impl Material {
             albedo: color,
             specular: 0.0,
             transparency: 0.0,
-            reflectivity: 0.0,
+            metallic: 0.0,
             refraction_index: 1.0,
             texture: None,
             emissive: color * intensity,
             roughness: 1.0,
+            absorption: Vec3::zero(),
         }
     }
     
+    // Materiales de bloques del Nether, compartidos por la escena y los cargadores de mundos
+    pub fn netherrack() -> Self {
+        Material::new()
+            .with_properties(Vec3::new(1.0, 0.3, 0.3), 0.0, 0.0, 0.3)
+    }
+    
+    pub fn lava() -> Self {
+        Material::emissive(Vec3::new(5.0, 2.5, 0.0), 20.0)
+            .with_texture(Texture::animated_fire())
+            .with_properties(Vec3::new(2.0, 1.2, 0.3), 0.8, 0.0, 0.3)
+    }
+    
+    pub fn obsidian() -> Self {
+        Material::new()
+            .with_texture(Texture::minecraft_obsidian())
+            .with_properties(Vec3::new(0.05, 0.02, 0.08), 0.3, 0.0, 0.0)
+    }
+    
+    pub fn nether_portal() -> Self {
+        Material::new()
+            .with_texture(Texture::nether_portal())
+            .with_properties(Vec3::new(0.5, 0.1, 0.8), 0.1, 0.9, 0.3)
+            .with_emissive(Vec3::new(0.4, 0.15, 0.6))
+    }
+    
+    pub fn glowstone() -> Self {
+        Material::emissive(Vec3::new(1.0, 0.85, 0.5), 4.0)
+            .with_texture(Texture::minecraft_glowstone())
+    }
+    
+    pub fn stone() -> Self {
+        Material::new()
+            .with_texture(Texture::minecraft_stone())
+            .with_properties(Vec3::new(0.5, 0.5, 0.5), 0.05, 0.0, 0.0)
+    }
+    
     pub fn with_texture(mut self, texture: Texture) -> Self {
         self.texture = Some(texture);
         self
@@ -50,11 +99,33 @@ impl Material {
         self
     }
     
+    pub fn with_metallic_roughness(mut self, albedo: Vec3, metallic: f32, roughness: f32) -> Self {
+        self.albedo = albedo;
+        self.metallic = metallic;
+        self.roughness = roughness;
+        self
+    }
+    
+    pub fn with_refraction_index(mut self, refraction_index: f32) -> Self {
+        self.refraction_index = refraction_index;
+        self
+    }
+    
+    // Tinte por espesor: la luz que recorre `distance` por dentro sale multiplicada por `color`
+    pub fn with_absorption(mut self, color: Vec3, distance: f32) -> Self {
+        let coefficient = |c: f32| -c.clamp(1e-4, 1.0).ln() / distance;
+        self.absorption = Vec3::new(coefficient(color.x), coefficient(color.y), coefficient(color.z));
+        self
+    }
+    
+    // Parámetros del modelo anterior (brillo especular, transparencia y reflejo de espejo): el
+    // brillo pasa a ser el nivel especular del dieléctrico y el espejo sobre un sólido a metal;
+    // en los transparentes el reflejo ya lo da el Fresnel
     pub fn with_properties(mut self, albedo: Vec3, specular: f32, transparency: f32, reflectivity: f32) -> Self {
         self.albedo = albedo;
         self.specular = specular;
         self.transparency = transparency;
-        self.reflectivity = reflectivity;
+        self.metallic = if transparency > 0.0 { 0.0 } else { reflectivity };
         self
     }
     
@@ -74,7 +145,42 @@ impl Material {
         self.transparency > 0.001
     }
     
-    pub fn is_reflective(&self) -> bool {
-        self.reflectivity > 0.001
+    // Fracción de luz que sobrevive a recorrer `distance` dentro del material
+    pub fn transmittance(&self, distance: f32) -> Vec3 {
+        let a = self.absorption * distance;
+        Vec3::new((-a.x).exp(), (-a.y).exp(), (-a.z).exp())
+    }
+}
+
+// Tabla de materiales de la escena: las primitivas, los triángulos y los bloques guardan solo
+// el ID, así un material compartido por miles de bloques existe una sola vez y al editarlo
+// cambian todos los que lo usan
+#[derive(Default)]
+pub struct MaterialRegistry {
+    materials: Vec<Material>,
+}
+
+impl MaterialRegistry {
+    pub fn new() -> Self {
+        Self::default()
     }
-}
\ No newline at end of file
+
+    pub fn add(&mut self, material: Material) -> MaterialId {
+        self.materials.push(material);
+        (self.materials.len() - 1) as MaterialId
+    }
+}
+
+impl Index<MaterialId> for MaterialRegistry {
+    type Output = Material;
+
+    fn index(&self, id: MaterialId) -> &Material {
+        &self.materials[id as usize]
+    }
+}
+
+impl IndexMut<MaterialId> for MaterialRegistry {
+    fn index_mut(&mut self, id: MaterialId) -> &mut Material {
+        &mut self.materials[id as usize]
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use crate::area_lights::AreaLight;
+use crate::geometry::*;
+use crate::bvh::Bvh;
+use crate::materials::MaterialRegistry;
+use crate::raytracer::HitInfo;
+use crate::shapes::{Primitive, Triangle};
+
+// Malla de triángulos con su propio BVH: en el BVH de la escena ocupa una sola hoja,
+// así un modelo de miles de caras no degrada el resto de la escena
+pub struct Mesh {
+    pub triangles: Vec<Triangle>,
+    bvh: Bvh,
+    bounds: (Vec3, Vec3),
+}
+
+impl Mesh {
+    pub fn new(triangles: Vec<Triangle>) -> Self {
+        let triangle_bounds: Vec<(Vec3, Vec3)> = triangles.iter().map(|t| t.get_bounds(0.0)).collect();
+        let bounds = triangle_bounds.iter().fold(
+            (Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY), Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY)),
+            |(min, max), &(bmin, bmax)| (min.min(bmin), max.max(bmax)),
+        );
+        let bvh = Bvh::build(&triangle_bounds);
+        Mesh { triangles, bvh, bounds }
+    }
+}
+
+impl Primitive for Mesh {
+    fn intersect(&self, ray: &Ray, time: f32) -> Option<HitInfo> {
+        self.bvh.intersect(ray, &|i| self.triangles[i].intersect(ray, time))
+    }
+
+    fn get_bounds(&self, _time: f32) -> (Vec3, Vec3) {
+        self.bounds
+    }
+
+    fn occluded(&self, ray: &Ray, max_t: f32, time: f32) -> bool {
+        self.bvh.occluded(ray, max_t, &|i| self.triangles[i].occluded(ray, max_t, time))
+    }
+
+    fn area_lights(&self, materials: &MaterialRegistry, out: &mut Vec<AreaLight>) {
+        for triangle in &self.triangles {
+            triangle.area_lights(materials, out);
+        }
+    }
+}
+
+// Genera normales por vértice promediando las caras que comparten cada posición, pesadas
+// por área; una cara solo entra si su normal difiere menos de `max_angle` grados de la
+// del triángulo, así las aristas marcadas (como las de un cubo) siguen siendo vivas
+pub fn smooth_normals(triangles: &mut [Triangle], max_angle: f32) {
+    let key = |p: Vec3| ((p.x + 0.0).to_bits(), (p.y + 0.0).to_bits(), (p.z + 0.0).to_bits());
+    // Normal sin normalizar: su largo es el doble del área
+    let face_normals: Vec<Vec3> = triangles.iter().map(|t| (t.v1 - t.v0).cross(t.v2 - t.v0)).collect();
+    let unit_normals: Vec<Vec3> = face_normals.iter().map(|n| n.normalize()).collect();
+
+    let mut sharing: HashMap<(u32, u32, u32), Vec<usize>> = HashMap::new();
+    for (i, t) in triangles.iter().enumerate() {
+        for p in [t.v0, t.v1, t.v2] {
+            sharing.entry(key(p)).or_default().push(i);
+        }
+    }
+
+    let cos_threshold = max_angle.to_radians().cos();
+    for (i, triangle) in triangles.iter_mut().enumerate() {
+        let own = unit_normals[i];
+        let corner = |p: Vec3| {
+            sharing[&key(p)].iter()
+                .filter(|&&j| unit_normals[j].dot(own) >= cos_threshold)
+                .fold(Vec3::zero(), |sum, &j| sum + face_normals[j])
+        };
+        let (n0, n1, n2) = (corner(triangle.v0), corner(triangle.v1), corner(triangle.v2));
+        triangle.vertex_normals = Some([n0.normalize(), n1.normalize(), n2.normalize()]);
+    }
+}
@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+use std::io::{self, Read};
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+
+// Lector de NBT (Named Binary Tag), el formato binario de Minecraft para chunks,
+// estructuras y esquemáticos
+
+// Se guardan todos los tipos aunque el renderer solo lea algunos (p. ej. Float y Double)
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(HashMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(map) => map.get(name),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // Cualquier entero como i64 (los archivos varían el tipo entre versiones)
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Tag::Byte(v) => Some(*v as i64),
+            Tag::Short(v) => Some(*v as i64),
+            Tag::Int(v) => Some(*v as i64),
+            Tag::Long(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_i32(&self) -> Option<i32> {
+        self.as_i64().map(|v| v as i32)
+    }
+
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match self {
+            Tag::LongArray(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&HashMap<String, Tag>> {
+        match self {
+            Tag::Compound(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_byte_array(&self) -> Option<&[i8]> {
+        match self {
+            Tag::ByteArray(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    // Enteros de un IntArray o de una lista de enteros (las estructuras guardan "pos" como lista)
+    pub fn as_int_vec(&self) -> Option<Vec<i32>> {
+        match self {
+            Tag::IntArray(values) => Some(values.clone()),
+            Tag::List(items) => items.iter().map(Tag::as_i32).collect(),
+            _ => None,
+        }
+    }
+}
+
+pub fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+// Descomprime según la cabecera: gzip (archivos .nbt/.schem), zlib (chunks) o sin comprimir
+pub fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    match data {
+        [0x1f, 0x8b, ..] => { GzDecoder::new(data).read_to_end(&mut out)?; }
+        [0x78, ..] => { ZlibDecoder::new(data).read_to_end(&mut out)?; }
+        _ => out.extend_from_slice(data),
+    }
+    Ok(out)
+}
+
+// Lee la etiqueta raíz (siempre un Compound con nombre) de datos ya descomprimidos
+pub fn parse(data: &[u8]) -> io::Result<Tag> {
+    let mut reader = Reader { data, pos: 0 };
+    let kind = reader.u8()?;
+    if kind != 10 {
+        return Err(invalid_data(format!("NBT: la raíz debe ser Compound, se encontró tipo {}", kind)));
+    }
+    reader.string()?; // Nombre de la raíz, normalmente vacío
+    reader.payload(kind, 0)
+}
+
+pub fn parse_compressed(data: &[u8]) -> io::Result<Tag> {
+    parse(&decompress(data)?)
+}
+
+// Límite de anidamiento para no desbordar la pila con archivos corruptos
+const MAX_DEPTH: usize = 512;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() - self.pos < n {
+            return Err(invalid_data("NBT: fin de datos inesperado"));
+        }
+        let bytes = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    fn length(&mut self) -> io::Result<usize> {
+        let len = self.i32()?;
+        if len < 0 {
+            return Err(invalid_data("NBT: longitud negativa"));
+        }
+        Ok(len as usize)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = u16::from_be_bytes(self.array()?) as usize;
+        // Java usa "UTF-8 modificado"; la diferencia solo afecta a caracteres raros
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn payload(&mut self, kind: u8, depth: usize) -> io::Result<Tag> {
+        if depth > MAX_DEPTH {
+            return Err(invalid_data("NBT: anidamiento demasiado profundo"));
+        }
+        Ok(match kind {
+            1 => Tag::Byte(self.u8()? as i8),
+            2 => Tag::Short(i16::from_be_bytes(self.array()?)),
+            3 => Tag::Int(self.i32()?),
+            4 => Tag::Long(i64::from_be_bytes(self.array()?)),
+            5 => Tag::Float(f32::from_be_bytes(self.array()?)),
+            6 => Tag::Double(f64::from_be_bytes(self.array()?)),
+            7 => {
+                let len = self.length()?;
+                Tag::ByteArray(self.take(len)?.iter().map(|&b| b as i8).collect())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let item_kind = self.u8()?;
+                let len = self.length()?;
+                let mut items = Vec::with_capacity(len.min(4096));
+                for _ in 0..len {
+                    items.push(self.payload(item_kind, depth + 1)?);
+                }
+                Tag::List(items)
+            }
+            10 => {
+                let mut map = HashMap::new();
+                loop {
+                    let child_kind = self.u8()?;
+                    if child_kind == 0 { break; }
+                    let name = self.string()?;
+                    map.insert(name, self.payload(child_kind, depth + 1)?);
+                }
+                Tag::Compound(map)
+            }
+            11 => {
+                let len = self.length()?;
+                let bytes = self.take(len.checked_mul(4).ok_or_else(|| invalid_data("NBT: arreglo demasiado grande"))?)?;
+                Tag::IntArray(bytes.chunks_exact(4).map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]])).collect())
+            }
+            12 => {
+                let len = self.length()?;
+                let bytes = self.take(len.checked_mul(8).ok_or_else(|| invalid_data("NBT: arreglo demasiado grande"))?)?;
+                Tag::LongArray(bytes.chunks_exact(8).map(|b| {
+                    i64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])
+                }).collect())
+            }
+            other => return Err(invalid_data(format!("NBT: tipo de etiqueta desconocido {}", other))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    use flate2::Compression;
+    use flate2::write::{GzEncoder, ZlibEncoder};
+
+    fn kind(tag: &Tag) -> u8 {
+        match tag {
+            Tag::Byte(_) => 1,
+            Tag::Short(_) => 2,
+            Tag::Int(_) => 3,
+            Tag::Long(_) => 4,
+            Tag::Float(_) => 5,
+            Tag::Double(_) => 6,
+            Tag::ByteArray(_) => 7,
+            Tag::String(_) => 8,
+            Tag::List(_) => 9,
+            Tag::Compound(_) => 10,
+            Tag::IntArray(_) => 11,
+            Tag::LongArray(_) => 12,
+        }
+    }
+
+    fn write_string(out: &mut Vec<u8>, s: &str) {
+        out.extend_from_slice(&(s.len() as u16).to_be_bytes());
+        out.extend_from_slice(s.as_bytes());
+    }
+
+    // Escritor mínimo, solo para armar datos de prueba
+    fn write_payload(out: &mut Vec<u8>, tag: &Tag) {
+        match tag {
+            Tag::Byte(v) => out.push(*v as u8),
+            Tag::Short(v) => out.extend_from_slice(&v.to_be_bytes()),
+            Tag::Int(v) => out.extend_from_slice(&v.to_be_bytes()),
+            Tag::Long(v) => out.extend_from_slice(&v.to_be_bytes()),
+            Tag::Float(v) => out.extend_from_slice(&v.to_be_bytes()),
+            Tag::Double(v) => out.extend_from_slice(&v.to_be_bytes()),
+            Tag::ByteArray(values) => {
+                out.extend_from_slice(&(values.len() as i32).to_be_bytes());
+                out.extend(values.iter().map(|&b| b as u8));
+            }
+            Tag::String(s) => write_string(out, s),
+            Tag::List(items) => {
+                out.push(items.first().map_or(0, kind));
+                out.extend_from_slice(&(items.len() as i32).to_be_bytes());
+                for item in items {
+                    write_payload(out, item);
+                }
+            }
+            Tag::Compound(map) => {
+                for (name, child) in map {
+                    out.push(kind(child));
+                    write_string(out, name);
+                    write_payload(out, child);
+                }
+                out.push(0);
+            }
+            Tag::IntArray(values) => {
+                out.extend_from_slice(&(values.len() as i32).to_be_bytes());
+                for v in values {
+                    out.extend_from_slice(&v.to_be_bytes());
+                }
+            }
+            Tag::LongArray(values) => {
+                out.extend_from_slice(&(values.len() as i32).to_be_bytes());
+                for v in values {
+                    out.extend_from_slice(&v.to_be_bytes());
+                }
+            }
+        }
+    }
+
+    fn encode_root(root: &Tag) -> Vec<u8> {
+        let mut out = vec![10];
+        write_string(&mut out, "");
+        write_payload(&mut out, root);
+        out
+    }
+
+    fn compound(entries: Vec<(&str, Tag)>) -> Tag {
+        Tag::Compound(entries.into_iter().map(|(name, tag)| (name.to_string(), tag)).collect())
+    }
+
+    fn every_tag() -> Tag {
+        compound(vec![
+            ("byte", Tag::Byte(-5)),
+            ("short", Tag::Short(-1234)),
+            ("int", Tag::Int(123_456_789)),
+            ("long", Tag::Long(-9_876_543_210)),
+            ("float", Tag::Float(1.5)),
+            ("double", Tag::Double(-0.125)),
+            ("bytes", Tag::ByteArray(vec![-1, 0, 127])),
+            ("string", Tag::String("minecraft:netherrack".to_string())),
+            ("list", Tag::List(vec![Tag::Int(1), Tag::Int(2), Tag::Int(3)])),
+            ("nested", compound(vec![("name", Tag::String("x".to_string()))])),
+            ("ints", Tag::IntArray(vec![i32::MIN, 0, i32::MAX])),
+            ("longs", Tag::LongArray(vec![i64::MIN, 0, i64::MAX])),
+        ])
+    }
+
+    #[test]
+    fn each_tag_type_round_trips() {
+        let root = every_tag();
+        for (name, tag) in root.as_compound().unwrap() {
+            let single = compound(vec![(name.as_str(), tag.clone())]);
+            assert_eq!(parse(&encode_root(&single)).unwrap(), single, "tag {}", name);
+        }
+        assert_eq!(parse(&encode_root(&root)).unwrap(), root);
+    }
+
+    #[test]
+    fn empty_list_round_trips() {
+        let root = compound(vec![("empty", Tag::List(Vec::new()))]);
+        assert_eq!(parse(&encode_root(&root)).unwrap(), root);
+    }
+
+    #[test]
+    fn compressed_data_round_trips() {
+        let root = every_tag();
+        let raw = encode_root(&root);
+
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(&raw).unwrap();
+        assert_eq!(parse_compressed(&gzip.finish().unwrap()).unwrap(), root);
+
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(&raw).unwrap();
+        assert_eq!(parse_compressed(&zlib.finish().unwrap()).unwrap(), root);
+
+        assert_eq!(parse_compressed(&raw).unwrap(), root);
+    }
+
+    #[test]
+    fn every_truncation_is_an_error() {
+        let data = encode_root(&every_tag());
+        for len in 0..data.len() {
+            assert!(parse(&data[..len]).is_err(), "se aceptaron {} de {} bytes", len, data.len());
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        // Raíz que no es Compound
+        assert!(parse(&[3, 0, 0, 0, 0, 0, 1]).is_err());
+        // Tipo de etiqueta desconocido
+        assert!(parse(&[10, 0, 0, 99, 0, 1, b'x']).is_err());
+        // Arreglo con longitud negativa
+        assert!(parse(&[10, 0, 0, 7, 0, 1, b'a', 0xff, 0xff, 0xff, 0xff, 0]).is_err());
+        // Arreglo más largo que los datos
+        assert!(parse(&[10, 0, 0, 12, 0, 1, b'a', 0x7f, 0xff, 0xff, 0xff, 0]).is_err());
+    }
+
+    // Compounds uno dentro de otro, cada uno con un único hijo "c", todos bien cerrados
+    fn nested(levels: usize) -> Vec<u8> {
+        let mut data = vec![10, 0, 0];
+        for _ in 0..levels {
+            data.extend_from_slice(&[10, 0, 1, b'c']);
+        }
+        data.extend(std::iter::repeat_n(0, levels + 1));
+        data
+    }
+
+    #[test]
+    fn rejects_excessive_nesting() {
+        assert!(parse(&nested(16)).is_ok());
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::geometry::Vec3;
+use crate::materials::{Material, MaterialId, MaterialRegistry};
+use crate::shapes::Triangle;
+
+// Cargador de modelos Wavefront OBJ con sus materiales MTL. Las caras de más de tres
+// vértices se triangulan en abanico; las texturas de imagen (map_Kd...) no se soportan.
+
+fn invalid_line(line: usize, message: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("línea {}: {}", line, message))
+}
+
+fn parse_floats<const N: usize>(parts: &[&str], line: usize) -> io::Result<[f32; N]> {
+    let mut values = [0.0; N];
+    for (i, value) in values.iter_mut().enumerate() {
+        let text = parts.get(i).ok_or_else(|| invalid_line(line, format!("se esperaban {} números", N)))?;
+        *value = text.parse().map_err(|_| invalid_line(line, format!("número inválido '{}'", text)))?;
+    }
+    Ok(values)
+}
+
+// Índices de OBJ: empiezan en 1 y los negativos cuentan desde el final
+fn resolve_index(text: &str, count: usize, line: usize) -> io::Result<usize> {
+    let index: i64 = text.parse().map_err(|_| invalid_line(line, format!("índice inválido '{}'", text)))?;
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(invalid_line(line, format!("índice {} fuera de rango", index)));
+    }
+    Ok(resolved as usize)
+}
+
+// Triángulos del modelo; los materiales del MTL se agregan a `registry` y
+// `material_override` los reemplaza a todos
+pub fn load_obj(path: &Path, material_override: Option<MaterialId>, registry: &mut MaterialRegistry) -> io::Result<Vec<Triangle>> {
+    let text = fs::read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut uvs: Vec<(f32, f32)> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut materials: HashMap<String, MaterialId> = HashMap::new();
+    let default_material = material_override.unwrap_or_else(|| registry.add(Material::new()));
+    let mut current = default_material;
+    let mut triangles = Vec::new();
+    let mut degenerate = 0;
+
+    for (number, raw) in text.lines().enumerate() {
+        let line = number + 1;
+        let content = raw.split('#').next().unwrap_or("").trim();
+        let mut parts = content.split_whitespace();
+        let Some(keyword) = parts.next() else { continue };
+        let args: Vec<&str> = parts.collect();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = parse_floats::<3>(&args, line)?;
+                positions.push(Vec3::new(x, y, z));
+            }
+            "vt" => {
+                let [u] = parse_floats::<1>(&args, line)?;
+                let v = match args.get(1) {
+                    Some(_) => parse_floats::<2>(&args, line)?[1],
+                    None => 0.0,
+                };
+                uvs.push((u, v));
+            }
+            "vn" => {
+                let [x, y, z] = parse_floats::<3>(&args, line)?;
+                normals.push(Vec3::new(x, y, z));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(invalid_line(line, "una cara necesita al menos 3 vértices"));
+                }
+                // Cada vértice es v, v/vt, v//vn o v/vt/vn
+                let mut corners = Vec::with_capacity(args.len());
+                for corner in &args {
+                    let mut fields = corner.split('/');
+                    let position = resolve_index(fields.next().unwrap_or(""), positions.len(), line)?;
+                    let uv = match fields.next() {
+                        Some(text) if !text.is_empty() => Some(resolve_index(text, uvs.len(), line)?),
+                        _ => None,
+                    };
+                    let normal = match fields.next() {
+                        Some(text) if !text.is_empty() => Some(resolve_index(text, normals.len(), line)?),
+                        _ => None,
+                    };
+                    corners.push((positions[position], uv.map(|i| uvs[i]), normal.map(|i| normals[i])));
+                }
+                for i in 1..corners.len() - 1 {
+                    let (v0, uv0, n0) = corners[0];
+                    let (v1, uv1, n1) = corners[i];
+                    let (v2, uv2, n2) = corners[i + 1];
+                    if (v1 - v0).cross(v2 - v0).length_squared() < 1e-20 {
+                        degenerate += 1;
+                        continue;
+                    }
+                    let triangle = match (uv0, uv1, uv2) {
+                        (Some(uv0), Some(uv1), Some(uv2)) => Triangle::new_with_uvs(v0, v1, v2, uv0, uv1, uv2, current),
+                        _ => Triangle::new(v0, v1, v2, current),
+                    };
+                    triangles.push(match (n0, n1, n2) {
+                        (Some(n0), Some(n1), Some(n2)) => triangle.with_vertex_normals(n0, n1, n2),
+                        _ => triangle,
+                    });
+                }
+            }
+            "mtllib" if material_override.is_none() => {
+                // El nombre del archivo puede tener espacios
+                let file = base_dir.join(content["mtllib".len()..].trim());
+                match load_mtl(&file) {
+                    Ok(loaded) => materials.extend(loaded.into_iter().map(|(name, material)| (name, registry.add(material)))),
+                    Err(e) => eprintln!("Warning: could not read {}: {}", file.display(), e),
+                }
+            }
+            "usemtl" if material_override.is_none() => {
+                let name = content["usemtl".len()..].trim();
+                current = match materials.get(name) {
+                    Some(&material) => material,
+                    None => {
+                        eprintln!("Warning: {}, line {}: unknown material '{}'", path.display(), line, name);
+                        default_material
+                    }
+                };
+            }
+            // Grupos, objetos, grupos de suavizado, líneas, etc.
+            _ => {}
+        }
+    }
+
+    if degenerate > 0 {
+        println!("Skipped {} degenerate triangle(s) in {}", degenerate, path.display());
+    }
+    Ok(triangles)
+}
+
+pub fn load_mtl(path: &Path) -> io::Result<HashMap<String, Material>> {
+    let text = fs::read_to_string(path)?;
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlEntry)> = None;
+
+    for (number, raw) in text.lines().enumerate() {
+        let line = number + 1;
+        let content = raw.split('#').next().unwrap_or("").trim();
+        let mut parts = content.split_whitespace();
+        let Some(keyword) = parts.next() else { continue };
+        let args: Vec<&str> = parts.collect();
+
+        if keyword == "newmtl" {
+            if let Some((name, entry)) = current.take() {
+                materials.insert(name, entry.to_material());
+            }
+            current = Some((content["newmtl".len()..].trim().to_string(), MtlEntry::default()));
+            continue;
+        }
+        let Some((_, entry)) = current.as_mut() else { continue };
+        match keyword {
+            "Kd" => entry.diffuse = Some(parse_floats::<3>(&args, line)?),
+            "Ks" => entry.specular = Some(parse_floats::<3>(&args, line)?),
+            "Ke" => entry.emissive = Some(parse_floats::<3>(&args, line)?),
+            "Ns" => entry.shininess = Some(parse_floats::<1>(&args, line)?[0]),
+            "Ni" => entry.optical_density = Some(parse_floats::<1>(&args, line)?[0]),
+            "d" => entry.dissolve = Some(parse_floats::<1>(&args, line)?[0]),
+            "Tr" => entry.dissolve = Some(1.0 - parse_floats::<1>(&args, line)?[0]),
+            "illum" => entry.illum = Some(parse_floats::<1>(&args, line)?[0] as i32),
+            _ => {}
+        }
+    }
+    if let Some((name, entry)) = current {
+        materials.insert(name, entry.to_material());
+    }
+    Ok(materials)
+}
+
+#[derive(Default)]
+struct MtlEntry {
+    diffuse: Option<[f32; 3]>,
+    specular: Option<[f32; 3]>,
+    emissive: Option<[f32; 3]>,
+    shininess: Option<f32>,
+    optical_density: Option<f32>,
+    dissolve: Option<f32>,
+    illum: Option<i32>,
+}
+
+impl MtlEntry {
+    fn to_material(&self) -> Material {
+        let mut material = Material::new();
+        if let Some([r, g, b]) = self.diffuse {
+            material.albedo = Vec3::new(r, g, b);
+        }
+        let specular = self.specular.map(|[r, g, b]| (r + g + b) / 3.0);
+        if let Some(specular) = specular {
+            material.specular = specular;
+        }
+        // Los modos de iluminación 3-7 son los que piden reflejos trazados: pasan a metal
+        material.metallic = match self.illum {
+            Some(3..=7) => specular.unwrap_or(0.5),
+            _ => 0.0,
+        };
+        if let Some(shininess) = self.shininess {
+            // Conversión habitual de exponente de Phong a rugosidad
+            material.roughness = (2.0 / (shininess.max(0.0) + 2.0)).sqrt();
+        }
+        if let Some(ior) = self.optical_density {
+            material.refraction_index = ior.max(1.0);
+        }
+        if let Some(dissolve) = self.dissolve {
+            material.transparency = (1.0 - dissolve).clamp(0.0, 1.0);
+        }
+        if let Some([r, g, b]) = self.emissive {
+            material.emissive = Vec3::new(r, g, b);
+        }
+        material
+    }
+}
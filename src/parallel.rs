@@ -0,0 +1,39 @@
+use std::sync::Mutex;
+use std::thread;
+
+// Filas que toma un hilo cada vez que pide trabajo
+const ROWS_PER_TASK: usize = 4;
+
+pub fn default_thread_count() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+// Recorre el buffer por filas repartiéndolas entre `threads` hilos. Las franjas salen de
+// una cola compartida: cada hilo toma la siguiente libre al terminar la suya, así las
+// zonas caras (reflejos, portal) no dejan al resto de núcleos esperando.
+pub fn for_each_row<T, F>(buffer: &mut [T], width: usize, threads: usize, shade_row: F)
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync,
+{
+    let threads = threads.max(1);
+    if threads == 1 {
+        for (y, row) in buffer.chunks_mut(width).enumerate() {
+            shade_row(y, row);
+        }
+        return;
+    }
+
+    let tasks = Mutex::new(buffer.chunks_mut(width * ROWS_PER_TASK).enumerate());
+    thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| loop {
+                let next = tasks.lock().unwrap().next();
+                let Some((task, band)) = next else { break };
+                for (i, row) in band.chunks_mut(width).enumerate() {
+                    shade_row(task * ROWS_PER_TASK + i, row);
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,52 @@
+use crate::geometry::Vec3;
+use crate::parallel;
+use crate::raytracer::{seed_pixel_rng, trace_path, CameraFrame, Scene};
+
+// Acumulación del trazado de caminos: cada pasada suma una muestra por píxel a un buffer en
+// coma flotante y la imagen es el promedio. Mientras la cámara y la escena no cambian la
+// imagen converge; cualquier cambio obliga a reiniciarla
+pub struct Accumulator {
+    width: usize,
+    sum: Vec<Vec3>,
+    samples: u32,
+}
+
+impl Accumulator {
+    pub fn new(width: usize, height: usize) -> Self {
+        Accumulator { width, sum: vec![Vec3::zero(); width * height], samples: 0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.sum.fill(Vec3::zero());
+        self.samples = 0;
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    // Suma una muestra más en cada píxel; la semilla cambia con cada pasada para que las
+    // muestras sean independientes, y sigue siendo fija por píxel para no depender del hilo
+    pub fn add_pass(&mut self, scene: &Scene, frame: &CameraFrame, time: f32, rotation_y: f32, threads: usize) {
+        let pass_seed = time.to_bits() ^ self.samples.wrapping_mul(0x9e37_79b9);
+        parallel::for_each_row(&mut self.sum, self.width, threads, |y, row| {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                seed_pixel_rng(x, y, pass_seed);
+                let sample = trace_path(&frame.get_jittered_ray(x, y), scene, time, rotation_y);
+                // Una muestra inválida arruinaría el píxel para siempre; se descarta
+                if sample.x.is_finite() && sample.y.is_finite() && sample.z.is_finite() {
+                    *pixel = *pixel + sample;
+                }
+            }
+        });
+        self.samples += 1;
+    }
+
+    // Promedio de las muestras convertido con `to_pixel`
+    pub fn resolve(&self, buffer: &mut [u32], to_pixel: fn(Vec3) -> u32) {
+        let scale = 1.0 / self.samples.max(1) as f32;
+        for (pixel, sum) in buffer.iter_mut().zip(&self.sum) {
+            *pixel = to_pixel(*sum * scale);
+        }
+    }
+}
@@ -2,11 +2,34 @@ use crate::geometry::*;
 use crate::materials::*;
 use crate::texture::TextureQuality;
 use crate::shapes::*;
+use crate::bvh::Bvh;
+use crate::area_lights::{AreaLights, LightSample};
+use crate::bsdf::{Bsdf, fresnel_dielectric};
 use std::cell::Cell;
 
 // Generador simple de números aleatorios (LCG)
 thread_local! {
     static RNG_STATE: Cell<u32> = Cell::new(12345);
+    // Semilla del píxel sin el cuadro, para muestras que se repiten igual en cada cuadro
+    static PIXEL_SEED: Cell<u32> = const { Cell::new(12345) };
+}
+
+// Reinicia el generador del hilo actual con una semilla derivada del píxel y del cuadro,
+// así el resultado es el mismo sin importar qué hilo renderice cada píxel
+pub fn seed_pixel_rng(x: usize, y: usize, frame_seed: u32) {
+    let pixel = (x as u32).wrapping_mul(0x8da6_b343) ^ (y as u32).wrapping_mul(0xd816_3841);
+    RNG_STATE.with(|state| state.set(mix_bits(pixel ^ frame_seed.wrapping_mul(0xcb1a_b31f))));
+    PIXEL_SEED.with(|seed| seed.set(mix_bits(pixel)));
+}
+
+// Mezcla final para separar semillas de píxeles vecinos
+fn mix_bits(mut h: u32) -> u32 {
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    h
 }
 
 fn random_f32() -> f32 {
@@ -21,6 +44,20 @@ fn random_f32() -> f32 {
 // Opciones de renderizado
 const ENABLE_AA: bool = false; // Desactivar jitter para mejor FPS
 
+// Vista inicial de una escena: dónde está la cámara, a qué mira y su campo de visión
+#[derive(Clone, Copy)]
+pub struct CameraView {
+    pub position: Vec3,
+    pub target: Vec3,
+    pub fov: f32,
+}
+
+impl CameraView {
+    pub fn new(position: Vec3, target: Vec3) -> Self {
+        CameraView { position, target, fov: 45.0 }
+    }
+}
+
 pub struct Camera {
     pub position: Vec3,
     pub target: Vec3,
@@ -182,6 +219,14 @@ impl CameraFrame {
     }
 }
 
+impl CameraFrame {
+    // Rayo por un punto al azar dentro del píxel, para que las muestras acumuladas
+    // suavicen los bordes
+    pub fn get_jittered_ray(&self, x: usize, y: usize) -> Ray {
+        self.get_ray(x as f32 + random_f32(), y as f32 + random_f32())
+    }
+}
+
 fn random_in_unit_disk() -> Vec3 {
     loop {
         let p = Vec3::new(random_f32(), random_f32(), 0.0) * 2.0 - Vec3::new(1.0, 1.0, 0.0);
@@ -203,6 +248,10 @@ pub enum LightType {
     Point,
     Directional(Vec3), // Direction vector
     Spot { direction: Vec3, inner_cone: f32, outer_cone: f32 },
+    // Luces con tamaño: dan penumbra porque cada rayo de sombra apunta a otro punto de la luz
+    Sphere { radius: f32 },
+    Rect { u: Vec3, v: Vec3 }, // Lados del rectángulo centrado en `position`; ilumina hacia u × v
+    Disk { normal: Vec3, radius: f32 },
 }
 
 impl Light {
@@ -237,21 +286,68 @@ impl Light {
         }
     }
     
+    pub fn sphere(position: Vec3, radius: f32, color: Vec3, intensity: f32) -> Self {
+        Self { position, color, intensity, light_type: LightType::Sphere { radius } }
+    }
+    
+    pub fn rect(position: Vec3, u: Vec3, v: Vec3, color: Vec3, intensity: f32) -> Self {
+        Self { position, color, intensity, light_type: LightType::Rect { u, v } }
+    }
+    
+    pub fn disk(position: Vec3, normal: Vec3, radius: f32, color: Vec3, intensity: f32) -> Self {
+        Self { position, color, intensity, light_type: LightType::Disk { normal: normal.normalize(), radius } }
+    }
+    
     pub fn get_light_direction(&self, point: Vec3) -> Vec3 {
         match &self.light_type {
-            LightType::Point => (self.position - point).normalize(),
             LightType::Directional(dir) => -*dir,
-            LightType::Spot { .. } => (self.position - point).normalize(),
+            _ => (self.position - point).normalize(),
+        }
+    }
+    
+    // Si la luz tiene tamaño; las demás se resuelven con un solo rayo de sombra
+    pub fn is_area(&self) -> bool {
+        matches!(self.light_type, LightType::Sphere { .. } | LightType::Rect { .. } | LightType::Disk { .. })
+    }
+    
+    // Punto de la luz para un rayo de sombra desde `point`, con (u1, u2) en [0, 1). La esfera
+    // se toma como el disco que le muestra a `point`
+    pub fn sample_position(&self, point: Vec3, u1: f32, u2: f32) -> Vec3 {
+        let disk = |normal: Vec3, radius: f32| {
+            let helper = if normal.x.abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+            let tangent = normal.cross(helper).normalize();
+            let bitangent = normal.cross(tangent);
+            let r = radius * u1.sqrt();
+            let phi = 2.0 * std::f32::consts::PI * u2;
+            self.position + tangent * (r * phi.cos()) + bitangent * (r * phi.sin())
+        };
+        match &self.light_type {
+            LightType::Sphere { radius } => disk((point - self.position).normalize(), *radius),
+            LightType::Rect { u, v } => self.position + *u * (u1 - 0.5) + *v * (u2 - 0.5),
+            LightType::Disk { normal, radius } => disk(*normal, *radius),
+            _ => self.position,
+        }
+    }
+    
+    // Los rectángulos y discos emiten por una sola cara, con caída de coseno (Lambert)
+    pub fn emission_factor(&self, light_dir: Vec3) -> f32 {
+        match &self.light_type {
+            LightType::Rect { u, v } => u.cross(*v).normalize().dot(-light_dir).max(0.0),
+            LightType::Disk { normal, .. } => normal.dot(-light_dir).max(0.0),
+            _ => 1.0,
         }
     }
     
-    pub fn get_attenuation(&self, point: Vec3) -> f32 {
+    // Dirección hacia `target` (un punto de la luz, ver `sample_position`), distancia y
+    // atenuación; las direccionales no tienen punto
+    pub fn incidence(&self, point: Vec3, target: Vec3) -> (Vec3, f32, f32) {
         match &self.light_type {
-            LightType::Point | LightType::Spot { .. } => {
-                let distance = (self.position - point).length();
-                1.0 / (1.0 + 0.09 * distance + 0.032 * distance * distance)
+            LightType::Directional(dir) => (-*dir, f32::INFINITY, 1.0),
+            _ => {
+                let to_light = target - point;
+                let distance = to_light.length();
+                (to_light.normalize(), distance, 1.0 / (1.0 + 0.09 * distance + 0.032 * distance * distance))
             },
-            LightType::Directional(_) => 1.0,
         }
     }
     
@@ -282,7 +378,10 @@ pub struct Scene {
     pub skybox: Option<Skybox>,
     pub ambient_light: Vec3,
     pub fog: Option<Fog>,
-    pub bvh: Option<BVHNode>,
+    pub bvh: Option<Bvh>,
+    pub materials: MaterialRegistry,
+    pub area_lights: AreaLights, // Superficies emisivas muestreadas como luces
+    pub day_cycle: bool, // El sol (primera luz), el cielo y el ambiente siguen el ciclo día/noche
 }
 
 impl Scene {
@@ -294,6 +393,9 @@ impl Scene {
             ambient_light: Vec3::new(0.1, 0.1, 0.15),
             fog: None,
             bvh: None,
+            materials: MaterialRegistry::new(),
+            area_lights: AreaLights::new(),
+            day_cycle: false,
         }
     }
     
@@ -309,130 +411,40 @@ impl Scene {
 }
 
 // ========================= Aceleración BVH =========================
-#[derive(Clone)]
-pub struct BVHNode {
-    pub bounds_min: Vec3,
-    pub bounds_max: Vec3,
-    pub left: Option<Box<BVHNode>>,
-    pub right: Option<Box<BVHNode>>,
-    pub indices: Vec<usize>, // Índices de hoja en Scene.objects
-}
-
-fn union_bounds(a_min: Vec3, a_max: Vec3, b_min: Vec3, b_max: Vec3) -> (Vec3, Vec3) {
-    (a_min.min(b_min), a_max.max(b_max))
-}
-
-fn ray_aabb_intersect(ray: &Ray, bmin: Vec3, bmax: Vec3) -> bool {
-    let inv_dir = Vec3::new(
-        if ray.direction.x.abs() < 1e-8 { 1e32 } else { 1.0 / ray.direction.x },
-        if ray.direction.y.abs() < 1e-8 { 1e32 } else { 1.0 / ray.direction.y },
-        if ray.direction.z.abs() < 1e-8 { 1e32 } else { 1.0 / ray.direction.z },
-    );
-    let mut tmin = (bmin.x - ray.origin.x) * inv_dir.x;
-    let mut tmax = (bmax.x - ray.origin.x) * inv_dir.x;
-    if tmin > tmax { std::mem::swap(&mut tmin, &mut tmax); }
-    let mut tymin = (bmin.y - ray.origin.y) * inv_dir.y;
-    let mut tymax = (bmax.y - ray.origin.y) * inv_dir.y;
-    if tymin > tymax { std::mem::swap(&mut tymin, &mut tymax); }
-    if (tmin > tymax) || (tymin > tmax) { return false; }
-    if tymin > tmin { tmin = tymin; }
-    if tymax < tmax { tmax = tymax; }
-    let mut tzmin = (bmin.z - ray.origin.z) * inv_dir.z;
-    let mut tzmax = (bmax.z - ray.origin.z) * inv_dir.z;
-    if tzmin > tzmax { std::mem::swap(&mut tzmin, &mut tzmax); }
-    if (tmin > tzmax) || (tzmin > tmax) { return false; }
-    true
-}
-
-struct ObjectInfo {
-    index: usize,
-    bmin: Vec3,
-    bmax: Vec3,
-    centroid: Vec3,
-}
-
 pub fn build_scene_bvh(scene: &mut Scene) {
     if scene.objects.is_empty() { return; }
-    // Recopilar límites
-    let mut infos: Vec<ObjectInfo> = Vec::with_capacity(scene.objects.len());
-    for (i, obj) in scene.objects.iter().enumerate() {
-        let (bmin, bmax) = obj.get_bounds();
-        let centroid = (bmin + bmax) * 0.5;
-        infos.push(ObjectInfo { index: i, bmin, bmax, centroid });
-    }
-    let node = build_bvh_recursive(&mut infos[..]);
-    scene.bvh = Some(node);
-}
-
-fn build_bvh_recursive(objects: &mut [ObjectInfo]) -> BVHNode {
-    // Calcular límites de todos
-    let mut bounds_min = Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
-    let mut bounds_max = Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
-    for info in objects.iter() {
-        let (bmin, bmax) = union_bounds(bounds_min, bounds_max, info.bmin, info.bmax);
-        bounds_min = bmin; bounds_max = bmax;
-    }
-    if objects.len() <= 8 {
-        return BVHNode {
-            bounds_min,
-            bounds_max,
-            left: None,
-            right: None,
-            indices: objects.iter().map(|o| o.index).collect(),
-        };
-    }
-    // Elegir eje de división por mayor extensión
-    let extent = bounds_max - bounds_min;
-    let axis = if extent.x > extent.y && extent.x > extent.z { 0 } else if extent.y > extent.z { 1 } else { 2 };
-    objects.sort_by(|a, b| {
-        let ca = match axis { 0 => a.centroid.x, 1 => a.centroid.y, _ => a.centroid.z };
-        let cb = match axis { 0 => b.centroid.x, 1 => b.centroid.y, _ => b.centroid.z };
-        ca.partial_cmp(&cb).unwrap_or(std::cmp::Ordering::Equal)
-    });
-    let mid = objects.len() / 2;
-    let (left_slice, right_slice) = objects.split_at_mut(mid);
-    let left = build_bvh_recursive(left_slice);
-    let right = build_bvh_recursive(right_slice);
-    BVHNode {
-        bounds_min,
-        bounds_max,
-        left: Some(Box::new(left)),
-        right: Some(Box::new(right)),
-        indices: Vec::new(),
-    }
-}
-
-fn intersect_bvh(ray: &Ray, node: &BVHNode, scene: &Scene, time: f32) -> Option<HitInfo> {
-    if !ray_aabb_intersect(ray, node.bounds_min, node.bounds_max) {
-        return None;
-    }
-    let mut best_hit: Option<HitInfo> = None;
-    let mut best_t = f32::INFINITY;
-    if node.left.is_none() && node.right.is_none() {
-        for &idx in &node.indices {
-            if let Some(hit) = scene.objects[idx].intersect(ray, time) {
-                if hit.t > 0.001 && hit.t < best_t {
-                    best_t = hit.t;
-                    best_hit = Some(hit);
-                }
-            }
-        }
-        return best_hit;
+    // Recopilar límites; los objetos animados se marcan para reajustarlos en cada cuadro
+    let bounds: Vec<(Vec3, Vec3)> = scene.objects.iter().map(|obj| obj.get_bounds(0.0)).collect();
+    let animated: Vec<usize> = (0..scene.objects.len()).filter(|&i| scene.objects[i].is_animated()).collect();
+    let mut bvh = Bvh::build(&bounds);
+    bvh.mark_dynamic(&animated);
+    let stats = bvh.stats();
+    println!(
+        "BVH: {} objects ({} unbounded), {} nodes, depth {}, {} leaves of {}-{} (avg {:.1}), SAH cost {:.2}",
+        scene.objects.len(), stats.unbounded, stats.nodes, stats.max_depth, stats.leaves,
+        stats.min_leaf, stats.max_leaf, stats.average_leaf, stats.sah_cost
+    );
+    if !animated.is_empty() {
+        println!("BVH: {} animated objects, {} nodes refit per frame", animated.len(), stats.dynamic);
     }
-    if let Some(left) = &node.left {
-        if let Some(hit) = intersect_bvh(ray, left, scene, time) {
-            best_t = hit.t;
-            best_hit = Some(hit);
-        }
+    scene.bvh = Some(bvh);
+}
+
+// Junta las superficies emisivas (lava, portal, sol...) como luces de área, así iluminan
+// desde donde están sin poner luces puntuales a mano
+pub fn collect_area_lights(scene: &mut Scene) {
+    scene.area_lights = AreaLights::collect(&scene.objects, &scene.materials);
+    if !scene.area_lights.is_empty() {
+        println!("Area lights: {} emissive surfaces", scene.area_lights.len());
     }
-    if let Some(right) = &node.right {
-        if let Some(hit) = intersect_bvh(ray, right, scene, time) {
-            if hit.t < best_t {
-                best_hit = Some(hit);
-            }
-        }
+}
+
+// Lleva las cajas de los objetos animados al instante `time`; solo se tocan sus nodos
+pub fn refit_scene_bvh(scene: &mut Scene, time: f32) {
+    if let Some(bvh) = &mut scene.bvh {
+        let objects = &scene.objects;
+        bvh.refit(&|i| objects[i].get_bounds(time));
     }
-    best_hit
 }
 
 pub struct Fog {
@@ -462,14 +474,17 @@ impl Fog {
     }
     
     pub fn apply(&self, original_color: Vec3, distance: f32) -> Vec3 {
+        original_color.interpolate(self.color, self.factor(distance))
+    }
+
+    // Cuánto del color de la niebla se ve tras recorrer `distance`, de 0 a 1
+    pub fn factor(&self, distance: f32) -> f32 {
         if self.density > 0.0 {
             // Niebla exponencial
-            let fog_factor = (-self.density * distance).exp();
-            original_color.interpolate(self.color, 1.0 - fog_factor)
+            1.0 - (-self.density * distance).exp()
         } else {
             // Niebla lineal
-            let fog_factor = ((distance - self.start) / (self.end - self.start)).clamp(0.0, 1.0);
-            original_color.interpolate(self.color, fog_factor)
+            ((distance - self.start) / (self.end - self.start)).clamp(0.0, 1.0)
         }
     }
 }
@@ -575,15 +590,36 @@ pub struct HitInfo {
     pub t: f32,
     pub point: Vec3,
     pub normal: Vec3,
-    pub material: Material,
+    pub material: MaterialId,
     pub uv: (f32, f32),
+    pub emitter: Emitter,
+}
+
+// Si la superficie golpeada está entre las luces de área de la escena, para que un rayo que
+// encuentra un emisor sepa con qué densidad lo habría elegido el muestreo de luces. Solo se
+// mira cuando el material es emisivo; cada primitiva marca lo mismo que entrega `area_lights`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Emitter {
+    // No se muestrea como luz: solo ilumina cuando un rebote la encuentra
+    Unsampled,
+    // Cara o triángulo, con densidad por área según su material
+    Surface,
+    // Esfera, muestreada por el cono que ocupa
+    Sphere,
+    // Marca dónde está una luz que ya ilumina por su cuenta (ver WithoutAreaLights): se ve,
+    // pero los rebotes no suman su brillo
+    Proxy,
 }
 
 // Parámetros mejorados de raytracing (límite superior)
 const MAX_DEPTH: i32 = 6;
-const SAMPLES_PER_PIXEL: i32 = 1;
 const RUSSIAN_ROULETTE_DEPTH: i32 = 3;
-const MIN_CONTRIBUTION: f32 = 0.001;
+// Rebotes máximos de un camino; la ruleta rusa suele cortarlo mucho antes
+const MAX_PATH_LENGTH: i32 = 32;
+// Reflectancia desde la que el modo Whitted traza el reflejo de una superficie
+const MIN_REFLECTANCE: f32 = 0.02;
+// Parte del tramo hasta un punto de una luz de área que no revisa su rayo de sombra
+const SHADOW_EPSILON: f32 = 1e-3;
 
 // Opciones de renderizado para balancear calidad y rendimiento
 #[derive(Clone, Copy)]
@@ -594,9 +630,99 @@ pub struct RenderOptions {
     pub shadow_mode: ShadowMode,
     pub max_depth: i32,
     pub far_simplify_distance: f32,
+    pub threads: usize, // Hilos de render (1 = sin paralelismo)
+    pub shadow_samples: u32, // Rayos de sombra por luz con tamaño, en una grilla k×k
+}
+
+// Hasta cuántos volúmenes transparentes uno dentro de otro se siguen
+const MAX_NESTED_MEDIA: usize = 4;
+
+// Medios transparentes en los que está metido un rayo, del más externo al más interno; sin
+// ninguno está en el aire. Entrar a un dieléctrico agrega su material y salir lo quita, así el
+// índice de refracción del otro lado y la absorción de cada tramo salen del medio correcto
+// aunque un volumen esté dentro de otro (hielo en agua)
+#[derive(Clone, Copy)]
+struct Media {
+    materials: [MaterialId; MAX_NESTED_MEDIA],
+    len: usize,
+}
+
+impl Media {
+    fn new() -> Self {
+        Media { materials: [0; MAX_NESTED_MEDIA], len: 0 }
+    }
+
+    fn current(&self) -> Option<MaterialId> {
+        self.len.checked_sub(1).map(|i| self.materials[i])
+    }
+
+    // Al entrar a `material`; si ya no hay lugar, reemplaza al más interno
+    fn entered(mut self, material: MaterialId) -> Self {
+        self.len = self.len.min(MAX_NESTED_MEDIA - 1);
+        self.materials[self.len] = material;
+        self.len += 1;
+        self
+    }
+
+    // Al salir de `material`: se quita su aparición más interna, si la hay
+    fn exited(mut self, material: MaterialId) -> Self {
+        if let Some(i) = self.materials[..self.len].iter().rposition(|&m| m == material) {
+            self.materials.copy_within(i + 1..self.len, i);
+            self.len -= 1;
+        }
+        self
+    }
+
+    fn refraction_index(&self, materials: &MaterialRegistry) -> f32 {
+        self.current().map_or(1.0, |m| materials[m].refraction_index)
+    }
+
+    // Lo que sobrevive a un tramo de `distance` dentro del medio actual
+    fn transmittance(&self, materials: &MaterialRegistry, distance: f32) -> Vec3 {
+        self.current().map_or(Vec3::new(1.0, 1.0, 1.0), |m| materials[m].transmittance(distance))
+    }
+}
+
+// Un rayo cruzando la superficie de un dieléctrico: normal del lado del rayo, fracción que se
+// refleja según Fresnel, las dos direcciones posibles y los medios del lado de la refracción
+struct Crossing {
+    normal: Vec3,
+    reflectance: f32,
+    reflected: Vec3,
+    refracted: Option<Vec3>,
+    media: Media,
+}
+
+impl Crossing {
+    fn new(direction: Vec3, hit: &HitInfo, media: &Media, materials: &MaterialRegistry) -> Self {
+        let material = &materials[hit.material];
+        let entering = direction.dot(hit.normal) < 0.0;
+        let normal = if entering { hit.normal } else { -hit.normal };
+        let (beyond, eta) = if entering {
+            (media.entered(hit.material), media.refraction_index(materials) / material.refraction_index)
+        } else {
+            let outside = media.exited(hit.material);
+            (outside, material.refraction_index / outside.refraction_index(materials))
+        };
+        let refracted = direction.refract(normal, eta);
+        let reflectance = if refracted.is_some() { fresnel_dielectric(-direction.dot(normal), eta) } else { 1.0 };
+        Crossing { normal, reflectance, reflected: direction.reflect(normal), refracted, media: beyond }
+    }
+}
+
+// Rama del árbol de rayos del modo Whitted: rebotes hechos y medios en los que está
+#[derive(Clone, Copy)]
+struct Branch {
+    depth: i32,
+    media: Media,
 }
 
 pub fn trace_ray(ray: &Ray, scene: &Scene, depth: i32, time: f32, rotation_y: f32, opts: &RenderOptions) -> Vec3 {
+    trace_branch(ray, scene, Branch { depth, media: Media::new() }, time, rotation_y, opts)
+}
+
+fn trace_branch(ray: &Ray, scene: &Scene, branch: Branch, time: f32, rotation_y: f32, opts: &RenderOptions) -> Vec3 {
+    let depth = branch.depth;
     // Terminación ruleta rusa
     // Parada forzada por opciones del usuario
     if depth >= opts.max_depth { return Vec3::zero(); }
@@ -614,29 +740,197 @@ pub fn trace_ray(ray: &Ray, scene: &Scene, depth: i32, time: f32, rotation_y: f3
     
     // Encontrar intersección más cercana
     if let Some(hit) = intersect_scene(&rotated_ray, scene, time) {
-        let color = shade_hit(ray, &hit, scene, depth, time, rotation_y, opts);
+        let color = shade_hit(ray, &hit, scene, branch, time, rotation_y, opts);
         
-        // Aplicar niebla si está presente
-        if let Some(fog) = &scene.fog {
+        // Dentro de un vidrio o del agua el tramo se tiñe por absorción; la niebla es del aire
+        if branch.media.current().is_some() {
+            color * branch.media.transmittance(&scene.materials, hit.t)
+        } else if let Some(fog) = &scene.fog {
             fog.apply(color, hit.t)
         } else {
             color
         }
     } else {
-        // Fondo/skybox
-        if let Some(skybox) = &scene.skybox {
-            skybox.color_at(ray.direction)
+        background(scene, ray.direction)
+    }
+}
+
+// Fondo/skybox
+fn background(scene: &Scene, direction: Vec3) -> Vec3 {
+    if let Some(skybox) = &scene.skybox {
+        skybox.color_at(direction)
+    } else {
+        // Cielo gradiente por defecto
+        let t = 0.5 * (direction.normalize().y + 1.0);
+        Vec3::new(0.5, 0.7, 1.0).interpolate(Vec3::new(1.0, 1.0, 1.0), t)
+    }
+}
+
+// Un camino para el modo de trazado progresivo. En cada rebote se elige con la transparencia
+// como probabilidad el dieléctrico (reflejo o refracción según Fresnel) o si no se muestrea la
+// BSDF (difuso o lóbulo GGX), y en los opacos se suma la luz directa de las luces puntuales y de un punto sobre las luces de área;
+// el cielo y las superficies emisivas aportan al encontrarlos, estas últimas repartiéndose
+// con el muestreo de luces por MIS. Sin luz ambiental ni atajos: el promedio
+// de muchas muestras converge a la iluminación global de la escena
+pub fn trace_path(ray: &Ray, scene: &Scene, time: f32, rotation_y: f32) -> Vec3 {
+    let mut ray = Ray::new(ray.origin.rotate_y(-rotation_y), ray.direction.rotate_y(-rotation_y));
+    let mut radiance = Vec3::zero();
+    let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+    // Densidad del último rebote en la BSDF; None si el rayo viene de la cámara o de una
+    // refracción, donde el muestreo de luces no pudo haber encontrado al emisor
+    let mut bsdf_pdf: Option<f32> = None;
+    let mut media = Media::new();
+    for bounce in 0..MAX_PATH_LENGTH {
+        let Some(hit) = intersect_scene(&ray, scene, time) else {
+            radiance = radiance + throughput * background(scene, ray.direction.rotate_y(rotation_y));
+            break;
+        };
+        // La niebla del tramo aporta su color y tapa en la misma medida lo que hay detrás; dentro
+        // de un medio transparente, en cambio, la luz se absorbe según la distancia
+        if media.current().is_some() {
+            throughput = throughput * media.transmittance(&scene.materials, hit.t);
+        } else if let Some(fog) = &scene.fog {
+            let amount = fog.factor(hit.t);
+            radiance = radiance + throughput * fog.color * amount;
+            throughput = throughput * (1.0 - amount);
+        }
+
+        let material = &scene.materials[hit.material];
+        let albedo = material.sample_texture_quality(hit.uv, time, TextureQuality::High);
+        if material.is_emissive() {
+            // Si el emisor también se muestrea como luz, esta vía y la de la luz se reparten
+            // su aporte con la heurística de potencia (MIS); el de una marca ya lo da su luz
+            let mut weight = 1.0;
+            if hit.emitter == Emitter::Proxy && bsdf_pdf.is_some() {
+                break;
+            }
+            if let Some(bsdf_pdf) = bsdf_pdf {
+                let light_pdf = scene.area_lights.pdf(ray.origin, &hit);
+                weight = power_heuristic(bsdf_pdf, light_pdf);
+            }
+            radiance = radiance + throughput * material.emissive * albedo * weight;
+            break;
+        }
+
+        // Ruleta rusa: desde cierta profundidad el camino sigue con probabilidad según lo
+        // que todavía puede aportar, y al sobrevivir se compensa para no perder energía
+        if bounce >= RUSSIAN_ROULETTE_DEPTH {
+            let survival = throughput.x.max(throughput.y).max(throughput.z).clamp(0.05, 0.95);
+            if random_f32() > survival {
+                break;
+            }
+            throughput = throughput / survival;
+        }
+
+        let facing = if ray.direction.dot(hit.normal) < 0.0 { hit.normal } else { -hit.normal };
+        if random_f32() < material.transparency {
+            // Reflejo o refracción con la reflectancia de Fresnel como probabilidad: el peso de
+            // cada uno se cancela con la chance de elegirlo
+            let crossing = Crossing::new(ray.direction, &hit, &media, &scene.materials);
+            match crossing.refracted.filter(|_| random_f32() >= crossing.reflectance) {
+                Some(direction) => {
+                    ray = Ray::new(hit.point - crossing.normal * 0.001, direction);
+                    media = crossing.media;
+                }
+                None => ray = Ray::new(hit.point + crossing.normal * 0.001, crossing.reflected),
+            }
+            bsdf_pdf = None;
         } else {
-            // Cielo gradiente por defecto
-            let t = 0.5 * (ray.direction.normalize().y + 1.0);
-            Vec3::new(0.5, 0.7, 1.0).interpolate(Vec3::new(1.0, 1.0, 1.0), t)
+            let surface = SurfacePoint { point: hit.point, normal: facing, view: -ray.direction, bsdf: Bsdf::new(material, albedo) };
+            radiance = radiance + throughput * direct_lights(&surface, scene, time);
+            let sample = scene.area_lights.sample(surface.origin(), random_f32(), random_f32(), random_f32());
+            radiance = radiance + throughput * area_light_direct(sample, &surface, scene, time, true);
+            let Some((direction, weight, pdf)) = surface.bsdf.sample(facing, surface.view, random_f32(), random_f32(), random_f32()) else {
+                break;
+            };
+            ray = Ray::new(surface.origin(), direction);
+            throughput = throughput * weight;
+            bsdf_pdf = Some(pdf);
+        }
+    }
+    radiance
+}
+
+// Punto a sombrear: dónde está, su normal del lado del observador, la dirección hacia el
+// observador y la BSDF de su material
+struct SurfacePoint {
+    point: Vec3,
+    normal: Vec3,
+    view: Vec3,
+    bsdf: Bsdf,
+}
+
+impl SurfacePoint {
+    // Origen de los rayos que salen de la superficie, apenas despegado de ella
+    fn origin(&self) -> Vec3 {
+        self.point + self.normal * 0.001
+    }
+}
+
+// Luz directa de las luces puntuales, direccionales, focos y luces con tamaño sobre un punto
+fn direct_lights(surface: &SurfacePoint, scene: &Scene, time: f32) -> Vec3 {
+    let mut total = Vec3::zero();
+    for light in &scene.lights {
+        // Las luces con tamaño aportan un punto al azar por camino; el promedio da la penumbra
+        let target = if light.is_area() { light.sample_position(surface.point, random_f32(), random_f32()) } else { light.position };
+        let (light_dir, light_distance, attenuation) = light.incidence(surface.point, target);
+        if surface.normal.dot(light_dir) <= 0.0 {
+            continue;
+        }
+        let shadow_ray = Ray::new(surface.origin(), light_dir);
+        if occluded_scene(&shadow_ray, scene, light_distance, time) {
+            continue;
         }
+        let intensity = light.intensity * attenuation * light.get_spot_factor(surface.point) * light.emission_factor(light_dir);
+        total = total + surface.bsdf.eval(surface.normal, surface.view, light_dir) * light.color * intensity;
     }
+    total
+}
+
+// Luz de un punto elegido sobre las luces de área para una superficie, si nada se interpone
+// (el rayo de sombra se corta apenas antes del punto para no chocar con el propio emisor).
+// Con `mis` el aporte pasa por toda la BSDF y se pesa contra el rebote que podría encontrar
+// la misma luz; sin él es el estimador de muestreo de luces solo y queda el difuso, porque
+// el modo Whitted ya ve a los emisores en el reflejo trazado
+fn area_light_direct(sample: Option<LightSample>, surface: &SurfacePoint, scene: &Scene, time: f32, mis: bool) -> Vec3 {
+    let Some(sample) = sample else {
+        return Vec3::zero();
+    };
+    let (origin, normal, view, bsdf) = (surface.origin(), surface.normal, surface.view, &surface.bsdf);
+    let light_pdf = sample.pdf;
+    let to_light = sample.point - origin;
+    let distance = to_light.length();
+    if distance < 1e-4 || light_pdf <= 0.0 {
+        return Vec3::zero();
+    }
+    let direction = to_light / distance;
+    if normal.dot(direction) <= 0.0 {
+        return Vec3::zero();
+    }
+    if occluded_scene(&Ray::new(origin, direction), scene, distance * (1.0 - SHADOW_EPSILON), time) {
+        return Vec3::zero();
+    }
+
+    let material = &scene.materials[sample.material];
+    let emitted = material.emissive * material.sample_texture_quality(sample.uv, time, TextureQuality::High);
+    let (shading, weight) = if mis {
+        (bsdf.eval(normal, view, direction), power_heuristic(light_pdf, bsdf.pdf(normal, view, direction)))
+    } else {
+        (bsdf.eval_diffuse(normal, view, direction), 1.0)
+    };
+    let transmittance = scene.fog.as_ref().map_or(1.0, |fog| 1.0 - fog.factor(distance));
+    shading * emitted * (weight * transmittance / light_pdf)
+}
+
+// Peso de la estrategia con densidad `pdf` frente a la otra (heurística de potencia de Veach)
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > 0.0 { a / (a + b) } else { 0.0 }
 }
 
 fn intersect_scene(ray: &Ray, scene: &Scene, time: f32) -> Option<HitInfo> {
     if let Some(bvh) = &scene.bvh {
-        intersect_bvh(ray, bvh, scene, time)
+        bvh.intersect(ray, &|idx| scene.objects[idx].intersect(ray, time))
     } else {
         let mut closest_hit: Option<HitInfo> = None;
         let mut closest_t = f32::INFINITY;
@@ -652,22 +946,39 @@ fn intersect_scene(ray: &Ray, scene: &Scene, time: f32) -> Option<HitInfo> {
     }
 }
 
-fn shade_hit(ray: &Ray, hit: &HitInfo, scene: &Scene, depth: i32, time: f32, rotation_y: f32, opts: &RenderOptions) -> Vec3 {
+// Rayo de sombra: basta con que algo lo corte antes de `max_t`
+fn occluded_scene(ray: &Ray, scene: &Scene, max_t: f32, time: f32) -> bool {
+    if let Some(bvh) = &scene.bvh {
+        bvh.occluded(ray, max_t, &|idx| scene.objects[idx].occluded(ray, max_t, time))
+    } else {
+        scene.objects.iter().any(|object| object.occluded(ray, max_t, time))
+    }
+}
+
+fn shade_hit(ray: &Ray, hit: &HitInfo, scene: &Scene, branch: Branch, time: f32, rotation_y: f32, opts: &RenderOptions) -> Vec3 {
+    let depth = branch.depth;
     let mut color = Vec3::zero();
     
     // Muestrear textura del material
     // Reducir calidad de textura con recursión pesada o distancia
     let far = hit.t > opts.far_simplify_distance;
     let tex_quality = if far { TextureQuality::Low } else if depth >= 3 { TextureQuality::Low } else if depth >= 1 { TextureQuality::Medium } else { TextureQuality::High };
-    let albedo = hit.material.sample_texture_quality(hit.uv, time, tex_quality);
+    let material = &scene.materials[hit.material];
+    let albedo = material.sample_texture_quality(hit.uv, time, tex_quality);
     
     // Materiales emisivos
-    if hit.material.is_emissive() {
-        return hit.material.emissive * albedo;
+    if material.is_emissive() {
+        return material.emissive * albedo;
     }
     
+    // La BSDF trabaja en el espacio de la escena, donde está el impacto, y con la normal del
+    // lado del observador
+    let view = -ray.direction.rotate_y(-rotation_y);
+    let normal = if view.dot(hit.normal) < 0.0 { -hit.normal } else { hit.normal };
+    let surface = SurfacePoint { point: hit.point, normal, view, bsdf: Bsdf::new(material, albedo) };
+    
     // Iluminación ambiental
-    color = color + scene.ambient_light * albedo;
+    color = color + scene.ambient_light * surface.bsdf.diffuse;
     
     // Iluminación directa (respetando modo de sombras)
     if far {
@@ -675,117 +986,127 @@ fn shade_hit(ray: &Ray, hit: &HitInfo, scene: &Scene, depth: i32, time: f32, rot
         for light in &scene.lights {
             if let LightType::Directional(_) = light.light_type {
                 let light_dir = light.get_light_direction(hit.point);
-                let n_dot_l = hit.normal.dot(light_dir).max(0.0);
-                let intensity = light.intensity;
-                color = color + albedo * light.color * intensity * n_dot_l / std::f32::consts::PI;
+                color = color + surface.bsdf.eval(normal, view, light_dir) * light.color * light.intensity;
             }
         }
     } else {
         for light in &scene.lights {
             match (&opts.shadow_mode, &light.light_type) {
                 (ShadowMode::None, _) => {},
-                (ShadowMode::SunOnly, LightType::Point) => {},
-                _ => { color = color + calculate_direct_lighting(ray, hit, light, albedo, scene, time, opts); }
+                (ShadowMode::SunOnly, LightType::Point | LightType::Sphere { .. } | LightType::Rect { .. } | LightType::Disk { .. }) => {},
+                _ => { color = color + calculate_direct_lighting(&surface, light, scene, time, opts); }
+            }
+        }
+        // Las superficies emisivas, con tantas muestras como las luces con tamaño
+        if let ShadowMode::Full = opts.shadow_mode && !scene.area_lights.is_empty() {
+            let cells = shadow_grid(opts);
+            for (u, u1, u2) in fixed_light_samples(cells) {
+                let sample = scene.area_lights.sample(surface.origin(), u, u1, u2);
+                color = color + area_light_direct(sample, &surface, scene, time, false) / (cells * cells) as f32;
             }
         }
     }
     
-    // Reflexión
-    if !far && hit.material.is_reflective() && depth < opts.max_depth {
-        let reflect_contribution = calculate_reflection(ray, hit, scene, depth, time, rotation_y, opts);
-        color = color + reflect_contribution * hit.material.reflectivity;
+    // Reflexión: una dirección del lóbulo especular por impacto, así la rugosidad la desenfoca
+    let opaque = 1.0 - material.transparency;
+    if !far && depth < opts.max_depth && luminance(surface.bsdf.reflectance(normal.dot(view))) * opaque > MIN_REFLECTANCE
+        && let Some((direction, weight)) = surface.bsdf.sample_specular(normal, view, random_f32(), random_f32()) {
+        let reflect_ray = world_ray(surface.origin(), direction, rotation_y);
+        let next = Branch { depth: depth + 1, ..branch };
+        color = color + trace_branch(&reflect_ray, scene, next, time, rotation_y, opts) * weight;
     }
     
     // Refracción/Transmisión
-    if !far && hit.material.is_transparent() && depth < opts.max_depth {
-        let refract_contribution = calculate_refraction(ray, hit, scene, depth, time, rotation_y, opts);
-        color = color.interpolate(refract_contribution, hit.material.transparency);
+    if !far && material.is_transparent() && depth < opts.max_depth {
+        let dielectric = dielectric_shading(hit, -view, scene, branch, time, rotation_y, opts);
+        color = color.interpolate(dielectric, material.transparency);
     }
     
     color
 }
 
-fn calculate_direct_lighting(ray: &Ray, hit: &HitInfo, light: &Light, albedo: Vec3, scene: &Scene, time: f32, _opts: &RenderOptions) -> Vec3 {
-    let light_dir = light.get_light_direction(hit.point);
-    let light_distance = match light.light_type {
-        LightType::Directional(_) => f32::INFINITY,
-        _ => (light.position - hit.point).length(),
-    };
+fn calculate_direct_lighting(surface: &SurfacePoint, light: &Light, scene: &Scene, time: f32, opts: &RenderOptions) -> Vec3 {
+    if !light.is_area() {
+        return light_sample_shading(surface, light, light.position, scene, time);
+    }
+    // Luces con tamaño: un rayo de sombra por celda de la grilla, a un punto al azar dentro de
+    // la celda, así la penumbra sale pareja con pocas muestras
+    let mut total = Vec3::zero();
+    let cells = shadow_grid(opts);
+    for (u1, u2) in stratified(cells) {
+        let target = light.sample_position(surface.point, u1, u2);
+        total = total + light_sample_shading(surface, light, target, scene, time);
+    }
+    total / (cells * cells) as f32
+}
+
+// Lado de la grilla de muestras de sombra: la raíz de `shadow_samples`, redondeada
+fn shadow_grid(opts: &RenderOptions) -> usize {
+    ((opts.shadow_samples as f32).sqrt().round() as usize).max(1)
+}
+
+// Un punto al azar en cada celda de una grilla de `cells` × `cells` sobre [0, 1)²
+fn stratified(cells: usize) -> impl Iterator<Item = (f32, f32)> {
+    (0..cells * cells).map(move |i| {
+        let (cx, cy) = (i % cells, i / cells);
+        ((cx as f32 + random_f32()) / cells as f32, (cy as f32 + random_f32()) / cells as f32)
+    })
+}
+
+// Como `stratified`, más el número que elige la luz, pero iguales en cada cuadro para el mismo
+// píxel: con pocas muestras por las superficies emisivas el ruido queda quieto en lugar de
+// parpadear mientras la cámara no se mueve
+fn fixed_light_samples(cells: usize) -> impl Iterator<Item = (f32, f32, f32)> {
+    let seed = PIXEL_SEED.with(Cell::get);
+    (0..cells * cells).map(move |i| {
+        let value = |k: usize| mix_bits(seed ^ ((3 * i + k) as u32).wrapping_mul(0x9e37_79b9)) as f32 / u32::MAX as f32;
+        let (cx, cy) = (i % cells, i / cells);
+        (value(0), (cx as f32 + value(1)) / cells as f32, (cy as f32 + value(2)) / cells as f32)
+    })
+}
+
+// Difuso y especular de la luz llegando desde `target`, si nada lo tapa
+fn light_sample_shading(surface: &SurfacePoint, light: &Light, target: Vec3, scene: &Scene, time: f32) -> Vec3 {
+    let (light_dir, light_distance, attenuation) = light.incidence(surface.point, target);
     
     // Salida temprana si no mira hacia la luz
-    let n_dot_l = hit.normal.dot(light_dir).max(0.0);
-    if n_dot_l <= 0.0 { return Vec3::zero(); }
+    if surface.normal.dot(light_dir) <= 0.0 { return Vec3::zero(); }
     
     // Prueba de sombra
-    let shadow_ray = Ray::new(hit.point + hit.normal * 0.001, light_dir);
-    let in_shadow = intersect_scene(&shadow_ray, scene, time)
-        .map(|shadow_hit| shadow_hit.t < light_distance)
-        .unwrap_or(false);
-    
-    if in_shadow {
+    let shadow_ray = Ray::new(surface.origin(), light_dir);
+    if occluded_scene(&shadow_ray, scene, light_distance, time) {
         return Vec3::zero();
     }
     
-    // Atenuación de luz y factor de cono
-    let attenuation = light.get_attenuation(hit.point);
-    let spot_factor = light.get_spot_factor(hit.point);
-    let light_intensity = light.intensity * attenuation * spot_factor;
+    // Atenuación de luz, factor de cono y cara que emite
+    let spot_factor = light.get_spot_factor(surface.point);
+    let light_intensity = light.intensity * attenuation * spot_factor * light.emission_factor(light_dir);
     // Saltar luces que contribuyen muy poco
     if light_intensity <= 0.01 { return Vec3::zero(); }
     
-    // Difuso Lambertiano
-    let diffuse = albedo * light.color * light_intensity * n_dot_l / std::f32::consts::PI;
-    
-    // Especular Blinn-Phong
-    let view_dir = (-ray.direction).normalize();
-    let half_dir = (view_dir + light_dir).normalize();
-    let n_dot_h = hit.normal.dot(half_dir).max(0.0);
-    
-    let shininess = (1.0 - hit.material.roughness) * 256.0 + 1.0;
-    let specular_strength = hit.material.specular;
-    let specular = light.color * light_intensity * n_dot_h.powf(shininess) * specular_strength;
-    
-    diffuse + specular
-}
-
-fn calculate_reflection(ray: &Ray, hit: &HitInfo, scene: &Scene, depth: i32, time: f32, rotation_y: f32, opts: &RenderOptions) -> Vec3 {
-    let reflect_dir = ray.direction.reflect(hit.normal);
-    let reflect_ray = Ray::new(hit.point + hit.normal * 0.001, reflect_dir);
-    trace_ray(&reflect_ray, scene, depth + 1, time, rotation_y, opts)
+    surface.bsdf.eval(surface.normal, surface.view, light_dir) * light.color * light_intensity
 }
 
-fn calculate_refraction(ray: &Ray, hit: &HitInfo, scene: &Scene, depth: i32, time: f32, rotation_y: f32, opts: &RenderOptions) -> Vec3 {
-    let entering = ray.direction.dot(hit.normal) < 0.0;
-    let eta = if entering {
-        1.0 / hit.material.refraction_index
-    } else {
-        hit.material.refraction_index
-    };
-    
-    let normal = if entering { hit.normal } else { -hit.normal };
-    
-    if let Some(refract_dir) = (-ray.direction).refract(normal, eta) {
-        let refract_ray = Ray::new(hit.point - normal * 0.001, refract_dir);
-        trace_ray(&refract_ray, scene, depth + 1, time, rotation_y, opts)
-    } else {
-        // Reflexión interna total
-        calculate_reflection(ray, hit, scene, depth, time, rotation_y, opts)
+// Parte transparente de un dieléctrico liso visto por un rayo con dirección `direction` (en el
+// espacio de la escena): el reflejo y la refracción se reparten la luz según Fresnel, y el
+// rayo refractado sigue dentro o fuera del medio del material
+fn dielectric_shading(hit: &HitInfo, direction: Vec3, scene: &Scene, branch: Branch, time: f32, rotation_y: f32, opts: &RenderOptions) -> Vec3 {
+    let crossing = Crossing::new(direction, hit, &branch.media, &scene.materials);
+    let mut color = Vec3::zero();
+    if crossing.reflectance > MIN_REFLECTANCE {
+        let reflect_ray = world_ray(hit.point + crossing.normal * 0.001, crossing.reflected, rotation_y);
+        let next = Branch { depth: branch.depth + 1, ..branch };
+        color = color + trace_branch(&reflect_ray, scene, next, time, rotation_y, opts) * crossing.reflectance;
     }
-}
-
-fn random_in_unit_sphere() -> Vec3 {
-    loop {
-        let p = Vec3::new(
-            random_f32() * 2.0 - 1.0,
-            random_f32() * 2.0 - 1.0,
-            random_f32() * 2.0 - 1.0,
-        );
-        if p.length_squared() < 1.0 {
-            return p;
-        }
+    if let Some(refracted) = crossing.refracted {
+        let refract_ray = world_ray(hit.point - crossing.normal * 0.001, refracted, rotation_y);
+        let next = Branch { depth: branch.depth + 1, media: crossing.media };
+        color = color + trace_branch(&refract_ray, scene, next, time, rotation_y, opts) * (1.0 - crossing.reflectance);
     }
+    color
 }
 
-fn random_unit_vector() -> Vec3 {
-    random_in_unit_sphere().normalize()
+// Rayo del espacio de la escena pasado al del mundo, que es el que espera `trace_branch`
+fn world_ray(origin: Vec3, direction: Vec3, rotation_y: f32) -> Ray {
+    Ray::new(origin.rotate_y(rotation_y), direction.rotate_y(rotation_y))
 }
@@ -0,0 +1,736 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::area_lights::WithoutAreaLights;
+use crate::blocks::BlockRegistry;
+use crate::csg::{Csg, CsgOp};
+use crate::geometry::{Mat4, Transform, Vec3};
+use crate::gltf;
+use crate::instance::{Instance, Transformed};
+use crate::materials::{Material, MaterialId, MaterialRegistry};
+use crate::mesh::{smooth_normals, Mesh};
+use crate::obj;
+use crate::raytracer::{CameraView, Fog, Light, Scene, Skybox};
+use crate::schematic::{Rotation, Schematic};
+use crate::sdf::{Sdf, SdfPrimitive};
+use crate::shapes::*;
+use crate::terrain::TerrainGenerator;
+use crate::texture::Texture;
+use crate::voxel::VoxelWorld;
+
+// Escenas descritas en TOML: cámara, luces, niebla, cielo, materiales con nombre,
+// primitivas y bloques. Ver scenes/nether.toml como ejemplo. Un .gltf/.glb también
+// se puede abrir directamente como escena.
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDef {
+    camera: Option<CameraDef>,
+    ambient: Option<[f32; 3]>,
+    #[serde(default)]
+    day_cycle: bool,
+    skybox: Option<SkyboxDef>,
+    fog: Option<FogDef>,
+    #[serde(default)]
+    materials: HashMap<String, Spanned<MaterialDef>>,
+    #[serde(default)]
+    lights: Vec<LightDef>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectEntry>>,
+    #[serde(default)]
+    blocks: Vec<Spanned<BlockFillDef>>,
+    #[serde(default)]
+    structures: Vec<Spanned<StructureDef>>,
+    terrain: Option<TerrainDef>,
+}
+
+fn default_true() -> bool { true }
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDef {
+    position: [f32; 3],
+    target: [f32; 3],
+    fov: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SkyboxDef {
+    day_top: [f32; 3],
+    day_horizon: [f32; 3],
+    night_top: Option<[f32; 3]>,
+    night_horizon: Option<[f32; 3]>,
+    sun_color: Option<[f32; 3]>,
+    sun_size: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum FogDef {
+    Linear { color: [f32; 3], start: f32, end: f32 },
+    Exponential { color: [f32; 3], density: f32 },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDef {
+    preset: Option<String>,
+    albedo: Option<[f32; 3]>,
+    specular: Option<f32>,
+    transparency: Option<f32>,
+    metallic: Option<f32>,
+    reflectivity: Option<f32>, // Nombre anterior de `metallic`
+    refraction_index: Option<f32>,
+    roughness: Option<f32>,
+    absorption: Option<[f32; 3]>, // Color tras recorrer `absorption_distance` por dentro
+    absorption_distance: Option<f32>,
+    emissive: Option<[f32; 3]>,
+    texture: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum LightDef {
+    Point { position: [f32; 3], color: [f32; 3], intensity: f32 },
+    Directional { direction: [f32; 3], color: [f32; 3], intensity: f32 },
+    // Conos en grados
+    Spot { position: [f32; 3], direction: [f32; 3], color: [f32; 3], intensity: f32, inner_cone: f32, outer_cone: f32 },
+    // Luces con tamaño (sombras con penumbra); el rectángulo ilumina hacia u × v
+    Sphere { position: [f32; 3], radius: f32, color: [f32; 3], intensity: f32 },
+    Rect { position: [f32; 3], u: [f32; 3], v: [f32; 3], color: [f32; 3], intensity: f32 },
+    Disk { position: [f32; 3], direction: [f32; 3], radius: f32, color: [f32; 3], intensity: f32 },
+}
+
+// Cualquier objeto acepta además una `transform` general y, si es emisivo, `area_light = false`
+// para que se vea sin iluminar (cuando su luz ya es una de [[lights]])
+#[derive(Deserialize)]
+struct ObjectEntry {
+    #[serde(flatten)]
+    object: ObjectDef,
+    transform: Option<TransformDef>,
+    #[serde(default = "default_true")]
+    area_light: bool,
+}
+
+// Escala (uniforme o por eje, negativa para reflejar), rotación en grados y traslación
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TransformDef {
+    #[serde(default)]
+    translate: [f32; 3],
+    rotate: Option<[f32; 3]>,
+    scale: Option<ScaleDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "un número o [x, y, z]")]
+enum ScaleDef {
+    Uniform(f32),
+    Axes([f32; 3]),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectDef {
+    Sphere { center: [f32; 3], radius: f32, material: String },
+    // Rotación en grados (Euler)
+    Cube { center: [f32; 3], size: f32, rotation: Option<[f32; 3]>, material: String },
+    SpinningCube { center: [f32; 3], size: f32, speed: f32, material: String },
+    Plane { point: [f32; 3], normal: [f32; 3], size: Option<[f32; 2]>, material: String },
+    Cylinder { center: [f32; 3], radius: f32, height: f32, material: String },
+    Torus { center: [f32; 3], major_radius: f32, minor_radius: f32, axis: Option<[f32; 3]>, material: String },
+    Triangle { vertices: [[f32; 3]; 3], uvs: Option<[[f32; 2]; 3]>, normal: Option<[f32; 3]>, material: String },
+    // Modelo OBJ (ruta relativa al archivo de escena); sin `material` se usan los del MTL.
+    // Cada archivo se carga una sola vez y sus copias comparten los triángulos
+    Mesh {
+        path: String,
+        material: Option<String>,
+        #[serde(default)]
+        position: [f32; 3],
+        rotation: Option<[f32; 3]>,
+        scale: Option<f32>,
+        // Recalcula normales suaves entre caras que difieren menos de este ángulo (grados)
+        smooth_angle: Option<f32>,
+    },
+    // Modelo glTF 2.0 (.gltf/.glb) con su jerarquía de nodos, luces y cámaras
+    Gltf(GltfDef),
+    // Superficie implícita armada con formas y operaciones de distancia con signo
+    Sdf { shape: SdfDef, material: String },
+    // Combinación sólida de esferas, cubos, cilindros u otras csg; cada una conserva su material.
+    // En `difference` a la primera se le restan las demás
+    Csg { operation: CsgOpDef, shapes: Vec<ObjectEntry> },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CsgOpDef {
+    Union,
+    Intersection,
+    Difference,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum SdfDef {
+    Sphere { center: [f32; 3], radius: f32 },
+    Box {
+        center: [f32; 3],
+        size: [f32; 3],
+        #[serde(default)]
+        rounding: f32,
+    },
+    Capsule { from: [f32; 3], to: [f32; 3], radius: f32 },
+    Cylinder { center: [f32; 3], radius: f32, height: f32 },
+    Union { shapes: Vec<SdfDef> },
+    // `blend`: radio en el que las formas se funden
+    SmoothUnion { shapes: Vec<SdfDef>, blend: f32 },
+    Subtract { base: Box<SdfDef>, remove: Box<SdfDef> },
+    // Ruido sobre la superficie; `flow` (bloques por segundo) lo hace correr con el tiempo
+    Displace {
+        shape: Box<SdfDef>,
+        amplitude: f32,
+        frequency: f32,
+        #[serde(default)]
+        flow: [f32; 3],
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GltfDef {
+    path: String,
+    material: Option<String>,
+    #[serde(default)]
+    position: [f32; 3],
+    rotation: Option<[f32; 3]>,
+    scale: Option<f32>,
+    smooth_angle: Option<f32>,
+    // Agregar las luces del modelo a la escena
+    #[serde(default = "default_true")]
+    lights: bool,
+}
+
+// Relleno de bloques entre dos esquinas (inclusivo) en la grilla de vóxeles de la escena
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BlockFillDef {
+    block: String,
+    from: [i32; 3],
+    to: [i32; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StructureDef {
+    path: String,
+    #[serde(default)]
+    at: [i32; 3],
+    #[serde(default)]
+    rotate: i32,
+}
+
+// Terreno del Nether generado con una semilla en la región de `size` bloques desde `from`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TerrainDef {
+    seed: u32,
+    #[serde(default)]
+    from: [i32; 3],
+    size: [i32; 3],
+    lava_level: Option<i32>,
+}
+
+// Resultado de cargar el archivo: la escena y la vista de cámara si el archivo la define
+pub struct LoadedScene {
+    pub scene: Scene,
+    pub view: Option<CameraView>,
+}
+
+// Revisa cada tanto la fecha de modificación del archivo para recargar la escena en vivo
+pub struct SceneWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+impl SceneWatcher {
+    pub fn new(path: &Path) -> Self {
+        SceneWatcher {
+            path: path.to_path_buf(),
+            modified: modified_time(path),
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Some(...) solo cuando el archivo cambió desde la última carga
+    pub fn poll(&mut self) -> Option<io::Result<LoadedScene>> {
+        if self.last_check.elapsed() < WATCH_INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+
+        let modified = modified_time(&self.path);
+        // Mientras el editor reemplaza el archivo puede no existir por un instante
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(load_scene_file(&self.path))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+pub fn load_scene_file(path: &Path) -> io::Result<LoadedScene> {
+    if matches!(path.extension().and_then(|e| e.to_str()), Some("gltf" | "glb")) {
+        return load_gltf_scene(path);
+    }
+    let text = fs::read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    parse_scene(&text, base_dir)
+}
+
+// Los errores de sintaxis y de validación indican línea y columna del archivo
+pub fn parse_scene(text: &str, base_dir: &Path) -> io::Result<LoadedScene> {
+    let located = |span: Range<usize>, message: &str| {
+        let (line, column) = line_column(text, span.start);
+        io::Error::new(io::ErrorKind::InvalidData, format!("línea {}, columna {}: {}", line, column, message))
+    };
+
+    let def: SceneDef = toml::from_str(text).map_err(|e| match e.span() {
+        Some(span) => located(span, e.message()),
+        None => io::Error::new(io::ErrorKind::InvalidData, e.message().to_string()),
+    })?;
+
+    let mut scene = Scene::new();
+    let mut materials = SceneMaterials { registry: &mut scene.materials, names: HashMap::new() };
+    for (name, material) in &def.materials {
+        let built = build_material(material.get_ref()).map_err(|e| located(material.span(), &format!("material '{}': {}", name, e)))?;
+        materials.names.insert(name.clone(), materials.registry.add(built));
+    }
+
+    scene.day_cycle = def.day_cycle;
+    if let Some(ambient) = def.ambient {
+        scene.ambient_light = vec3(ambient);
+    }
+    if let Some(skybox) = &def.skybox {
+        scene.skybox = Some(build_skybox(skybox));
+    }
+    scene.fog = def.fog.map(|fog| match fog {
+        FogDef::Linear { color, start, end } => Fog::linear(vec3(color), start, end),
+        FogDef::Exponential { color, density } => Fog::exponential(vec3(color), density),
+    });
+    scene.lights = def.lights.iter().map(build_light).collect();
+
+    // Sin [camera] se usa la primera cámara de los modelos glTF
+    let mut imported_view = None;
+    let mut meshes = MeshCache::new();
+    for object in &def.objects {
+        let ObjectEntry { object: object_def, transform, area_light } = object.get_ref();
+        let transform = transform.as_ref().map(build_transform).transpose().map_err(|e| located(object.span(), &e))?;
+        let primitive: Box<dyn Primitive> = match object_def {
+            ObjectDef::Gltf(gltf) => {
+                let imported = import_gltf(gltf, transform, &mut materials, base_dir).map_err(|e| located(object.span(), &e))?;
+                if gltf.lights {
+                    scene.lights.extend(imported.lights);
+                }
+                imported_view = imported_view.or(imported.cameras.first().copied());
+                if imported.triangles.is_empty() {
+                    continue;
+                }
+                Box::new(Mesh::new(imported.triangles))
+            }
+            other => {
+                let primitive = build_object(other, &mut materials, base_dir, &mut meshes).map_err(|e| located(object.span(), &e))?;
+                match transform {
+                    Some(transform) => Box::new(Transformed::new(primitive, transform)),
+                    None => primitive,
+                }
+            }
+        };
+        scene.objects.push(if *area_light { primitive } else { Box::new(WithoutAreaLights(primitive)) });
+    }
+
+    // Terreno, bloques y estructuras comparten una grilla centrada en coordenadas enteras, como la
+    // escena del Nether; los rellenos y estructuras se colocan encima del terreno
+    if !def.blocks.is_empty() || !def.structures.is_empty() || def.terrain.is_some() {
+        let mut world = VoxelWorld::new(Vec3::new(-0.5, -0.5, -0.5));
+        let mut registry = BlockRegistry::new(materials.registry);
+        if let Some(terrain) = &def.terrain {
+            let mut generator = TerrainGenerator::new(terrain.seed);
+            if let Some(level) = terrain.lava_level {
+                generator = generator.with_lava_level(level);
+            }
+            let [x, y, z] = terrain.from;
+            let [width, height, depth] = terrain.size;
+            generator.place(&mut world, &mut registry, (x, y, z), (width, height, depth));
+        }
+        for fill in &def.blocks {
+            let BlockFillDef { block, from, to } = fill.get_ref();
+            let id = registry.id_for(&mut world, block);
+            for x in from[0].min(to[0])..=from[0].max(to[0]) {
+                for y in from[1].min(to[1])..=from[1].max(to[1]) {
+                    for z in from[2].min(to[2])..=from[2].max(to[2]) {
+                        world.set_block(x, y, z, id);
+                    }
+                }
+            }
+        }
+        for structure in &def.structures {
+            let StructureDef { path, at, rotate } = structure.get_ref();
+            let rotation = Rotation::from_degrees(*rotate)
+                .ok_or_else(|| located(structure.span(), "rotate solo acepta múltiplos de 90"))?;
+            let schematic = Schematic::load(&base_dir.join(path))
+                .map_err(|e| located(structure.span(), &format!("{}: {}", path, e)))?;
+            schematic.place(&mut world, &mut registry, (at[0], at[1], at[2]), rotation);
+        }
+        if !world.is_empty() {
+            scene.objects.push(Box::new(world));
+        }
+    }
+
+    let view = def.camera.map(|camera| {
+        let mut view = CameraView::new(vec3(camera.position), vec3(camera.target));
+        if let Some(fov) = camera.fov { view.fov = fov; }
+        view
+    }).or(imported_view);
+    Ok(LoadedScene { scene, view })
+}
+
+// Escena armada solo con el contenido del glTF; sin luces ni cámara en el archivo
+// se agrega un sol fijo y se encuadra el modelo completo
+fn load_gltf_scene(path: &Path) -> io::Result<LoadedScene> {
+    let mut scene = Scene::new();
+    let imported = gltf::load_gltf(path, Mat4::identity(), None, &mut scene.materials)?;
+    println!("Loaded {} ({} triangles, {} lights, {} cameras)", path.display(), imported.triangles.len(), imported.lights.len(), imported.cameras.len());
+
+    scene.skybox = Some(Skybox::gradient(Vec3::new(0.53, 0.81, 0.92), Vec3::new(0.85, 0.85, 0.9)));
+    scene.lights = imported.lights;
+    if scene.lights.is_empty() {
+        scene.lights.push(Light::directional(Vec3::new(-0.4, -1.0, -0.3), Vec3::new(1.0, 1.0, 1.0), 1.0));
+    }
+
+    let mut view = imported.cameras.first().copied();
+    if !imported.triangles.is_empty() {
+        let mesh = Mesh::new(imported.triangles);
+        if view.is_none() {
+            let (min, max) = mesh.get_bounds(0.0);
+            let center = (min + max) * 0.5;
+            let radius = ((max - min).length() * 0.5).max(0.1);
+            view = Some(CameraView::new(center + Vec3::new(1.0, 0.6, 1.0).normalize() * radius * 2.5, center));
+        }
+        scene.objects.push(Box::new(mesh));
+    }
+    Ok(LoadedScene { scene, view })
+}
+
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+    (line, column)
+}
+
+fn vec3(v: [f32; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn preset_material(name: &str) -> Option<Material> {
+    Some(match name {
+        "default" => Material::new(),
+        "netherrack" => Material::netherrack(),
+        "lava" => Material::lava(),
+        "obsidian" => Material::obsidian(),
+        "nether_portal" => Material::nether_portal(),
+        "glowstone" => Material::glowstone(),
+        "stone" => Material::stone(),
+        _ => return None,
+    })
+}
+
+// Materiales por nombre: los definidos en el archivo y los presets, cada uno registrado una
+// sola vez en la tabla de la escena aunque lo usen muchos objetos
+struct SceneMaterials<'a> {
+    registry: &'a mut MaterialRegistry,
+    names: HashMap<String, MaterialId>,
+}
+
+impl SceneMaterials<'_> {
+    // Un nombre de material puede ser uno definido en el archivo o un preset
+    fn get(&mut self, name: &str) -> Result<MaterialId, String> {
+        if let Some(&id) = self.names.get(name) {
+            return Ok(id);
+        }
+        let preset = preset_material(name).ok_or_else(|| format!("material desconocido '{}'", name))?;
+        let id = self.registry.add(preset);
+        self.names.insert(name.to_string(), id);
+        Ok(id)
+    }
+}
+
+fn build_material(def: &MaterialDef) -> Result<Material, String> {
+    let mut material = match &def.preset {
+        Some(preset) => preset_material(preset).ok_or_else(|| format!("preset desconocido '{}'", preset))?,
+        None => Material::new(),
+    };
+    if let Some(albedo) = def.albedo { material.albedo = vec3(albedo); }
+    if let Some(specular) = def.specular { material.specular = specular; }
+    if let Some(transparency) = def.transparency { material.transparency = transparency; }
+    if let Some(metallic) = def.metallic.or(def.reflectivity) { material.metallic = metallic; }
+    if let Some(refraction_index) = def.refraction_index { material.refraction_index = refraction_index; }
+    if let Some(roughness) = def.roughness { material.roughness = roughness; }
+    if let Some(absorption) = def.absorption {
+        let distance = def.absorption_distance.unwrap_or(1.0);
+        if absorption.iter().any(|c| !(0.0..=1.0).contains(c)) || distance <= 0.0 {
+            return Err("absorption debe estar entre 0 y 1 y absorption_distance ser positiva".to_string());
+        }
+        material = material.with_absorption(vec3(absorption), distance);
+    }
+    if let Some(emissive) = def.emissive { material.emissive = vec3(emissive); }
+    if let Some(texture) = &def.texture {
+        material.texture = Some(match texture.as_str() {
+            "fire" => Texture::animated_fire(),
+            "portal" => Texture::nether_portal(),
+            "stone" => Texture::minecraft_stone(),
+            "glowstone" => Texture::minecraft_glowstone(),
+            "obsidian" => Texture::minecraft_obsidian(),
+            other => return Err(format!("textura desconocida '{}' (fire, portal, stone, glowstone, obsidian)", other)),
+        });
+    }
+    if [material.transparency, material.metallic, material.roughness].iter().any(|v| !(0.0..=1.0).contains(v)) {
+        return Err("transparency, metallic y roughness deben estar entre 0 y 1".to_string());
+    }
+    Ok(material)
+}
+
+fn build_skybox(def: &SkyboxDef) -> Skybox {
+    let mut skybox = match (def.night_top, def.night_horizon) {
+        (Some(top), Some(horizon)) => Skybox::textured(vec3(def.day_top), vec3(def.day_horizon), vec3(top), vec3(horizon)),
+        _ => Skybox::gradient(vec3(def.day_top), vec3(def.day_horizon)),
+    };
+    if let Some(sun_color) = def.sun_color { skybox.sun_color = vec3(sun_color); }
+    if let Some(sun_size) = def.sun_size { skybox.sun_size = sun_size; }
+    skybox
+}
+
+fn build_light(def: &LightDef) -> Light {
+    match *def {
+        LightDef::Point { position, color, intensity } => Light::point(vec3(position), vec3(color), intensity),
+        LightDef::Directional { direction, color, intensity } => Light::directional(vec3(direction), vec3(color), intensity),
+        LightDef::Spot { position, direction, color, intensity, inner_cone, outer_cone } => Light::spot(
+            vec3(position), vec3(direction), vec3(color), intensity,
+            inner_cone.to_radians(), outer_cone.to_radians(),
+        ),
+        LightDef::Sphere { position, radius, color, intensity } => Light::sphere(vec3(position), radius, vec3(color), intensity),
+        LightDef::Rect { position, u, v, color, intensity } => Light::rect(vec3(position), vec3(u), vec3(v), vec3(color), intensity),
+        LightDef::Disk { position, direction, radius, color, intensity } => Light::disk(vec3(position), vec3(direction), radius, vec3(color), intensity),
+    }
+}
+
+// Mallas ya cargadas, por archivo, material y ángulo de suavizado
+type MeshCache = HashMap<(String, Option<String>, Option<u32>), Arc<dyn Primitive>>;
+
+fn build_object(def: &ObjectDef, materials: &mut SceneMaterials, base_dir: &Path, meshes: &mut MeshCache) -> Result<Box<dyn Primitive>, String> {
+    let positive = |name: &str, value: f32| {
+        if value > 0.0 { Ok(value) } else { Err(format!("{} debe ser mayor que cero", name)) }
+    };
+    Ok(match def {
+        ObjectDef::Sphere { center, radius, material: m } => {
+            Box::new(Sphere::new(vec3(*center), positive("radius", *radius)?, materials.get(m)?))
+        }
+        ObjectDef::Cube { center, size, rotation, material: m } => {
+            let rotation = rotation.map(|r| vec3(r.map(f32::to_radians))).unwrap_or(Vec3::zero());
+            Box::new(Cube::new(vec3(*center), positive("size", *size)?, materials.get(m)?).with_rotation(rotation))
+        }
+        ObjectDef::SpinningCube { center, size, speed, material: m } => {
+            Box::new(SpinningCube::new(vec3(*center), positive("size", *size)?, materials.get(m)?, *speed))
+        }
+        ObjectDef::Plane { point, normal, size, material: m } => {
+            if vec3(*normal).length_squared() < 1e-6 {
+                return Err("la normal del plano no puede ser cero".to_string());
+            }
+            let plane = Plane::new(vec3(*point), vec3(*normal), materials.get(m)?);
+            match size {
+                Some([w, h]) => Box::new(plane.with_size(positive("width", *w)?, positive("height", *h)?)),
+                None => Box::new(plane),
+            }
+        }
+        ObjectDef::Cylinder { center, radius, height, material: m } => {
+            Box::new(Cylinder::new(vec3(*center), positive("radius", *radius)?, positive("height", *height)?, materials.get(m)?))
+        }
+        ObjectDef::Torus { center, major_radius, minor_radius, axis, material: m } => {
+            if minor_radius >= major_radius {
+                return Err("minor_radius debe ser menor que major_radius".to_string());
+            }
+            let torus = Torus::new(vec3(*center), positive("major_radius", *major_radius)?, positive("minor_radius", *minor_radius)?, materials.get(m)?);
+            match axis {
+                Some(axis) if vec3(*axis).length_squared() < 1e-6 => return Err("el eje del toro no puede ser cero".to_string()),
+                Some(axis) => Box::new(torus.with_axis(vec3(*axis))),
+                None => Box::new(torus),
+            }
+        }
+        ObjectDef::Triangle { vertices, uvs, normal, material: m } => {
+            let [v0, v1, v2] = vertices.map(vec3);
+            if (v1 - v0).cross(v2 - v0).length_squared() < 1e-12 {
+                return Err("triángulo degenerado".to_string());
+            }
+            let mut triangle = match normal {
+                Some(normal) => Triangle::new_with_normal(v0, v1, v2, vec3(*normal), materials.get(m)?),
+                None => Triangle::new(v0, v1, v2, materials.get(m)?),
+            };
+            if let Some([uv0, uv1, uv2]) = uvs {
+                triangle.uv0 = (uv0[0], uv0[1]);
+                triangle.uv1 = (uv1[0], uv1[1]);
+                triangle.uv2 = (uv2[0], uv2[1]);
+            }
+            Box::new(triangle)
+        }
+        ObjectDef::Mesh { path, material: m, position, rotation, scale, smooth_angle } => {
+            let transform = Transform::new(placement(*position, *rotation, *scale)?)
+                .ok_or_else(|| "la ubicación de la malla no es invertible".to_string())?;
+            let key = (path.clone(), m.clone(), smooth_angle.map(f32::to_bits));
+            let mesh = match meshes.get(&key) {
+                Some(mesh) => mesh.clone(),
+                None => {
+                    let material_override = m.as_deref().map(|name| materials.get(name)).transpose()?;
+                    let mut triangles = obj::load_obj(&base_dir.join(path), material_override, materials.registry)
+                        .map_err(|e| format!("{}: {}", path, e))?;
+                    if let Some(angle) = smooth_angle {
+                        smooth_normals(&mut triangles, *angle);
+                    }
+                    println!("Loaded {} ({} triangles)", path, triangles.len());
+                    let mesh: Arc<dyn Primitive> = Arc::new(Mesh::new(triangles));
+                    meshes.insert(key, mesh.clone());
+                    mesh
+                }
+            };
+            Box::new(Instance::new(mesh, transform))
+        }
+        ObjectDef::Sdf { shape, material: m } => Box::new(SdfPrimitive::new(build_sdf(shape)?, materials.get(m)?)),
+        ObjectDef::Csg { operation, shapes } => {
+            let op = match operation {
+                CsgOpDef::Union => CsgOp::Union,
+                CsgOpDef::Intersection => CsgOp::Intersection,
+                CsgOpDef::Difference => CsgOp::Difference,
+            };
+            if shapes.len() < 2 {
+                return Err("csg necesita al menos dos formas".to_string());
+            }
+            let mut result = build_solid(&shapes[0], materials, base_dir, meshes)?;
+            for shape in &shapes[1..] {
+                result = Box::new(Csg::new(op, result, build_solid(shape, materials, base_dir, meshes)?));
+            }
+            result
+        }
+        ObjectDef::Gltf(_) => unreachable!("los modelos glTF se importan en parse_scene"),
+    })
+}
+
+// Operando de una csg: solo primitivas que encierran un volumen
+fn build_solid(entry: &ObjectEntry, materials: &mut SceneMaterials, base_dir: &Path, meshes: &mut MeshCache) -> Result<Box<dyn Primitive>, String> {
+    if !matches!(entry.object, ObjectDef::Sphere { .. } | ObjectDef::Cube { .. } | ObjectDef::Cylinder { .. } | ObjectDef::Csg { .. }) {
+        return Err("csg solo combina sphere, cube, cylinder y csg".to_string());
+    }
+    let primitive = build_object(&entry.object, materials, base_dir, meshes)?;
+    Ok(match entry.transform.as_ref().map(build_transform).transpose()? {
+        Some(transform) => Box::new(Transformed::new(primitive, transform)),
+        None => primitive,
+    })
+}
+
+// `transform` es la transformación general del objeto, aplicada después de la ubicación
+fn import_gltf(def: &GltfDef, transform: Option<Transform>, materials: &mut SceneMaterials, base_dir: &Path) -> Result<gltf::GltfScene, String> {
+    let material_override = def.material.as_deref().map(|name| materials.get(name)).transpose()?;
+    let placement = placement(def.position, def.rotation, def.scale)?;
+    let transform = match transform {
+        Some(outer) => outer.matrix * placement,
+        None => placement,
+    };
+    let mut imported = gltf::load_gltf(&base_dir.join(&def.path), transform, material_override, materials.registry)
+        .map_err(|e| format!("{}: {}", def.path, e))?;
+    if let Some(angle) = def.smooth_angle {
+        smooth_normals(&mut imported.triangles, angle);
+    }
+    println!("Loaded {} ({} triangles, {} lights, {} cameras)", def.path, imported.triangles.len(), imported.lights.len(), imported.cameras.len());
+    Ok(imported)
+}
+
+fn build_sdf(def: &SdfDef) -> Result<Sdf, String> {
+    let positive = |name: &str, value: f32| {
+        if value > 0.0 { Ok(value) } else { Err(format!("{} debe ser mayor que cero", name)) }
+    };
+    let build_all = |shapes: &[SdfDef]| -> Result<Vec<Sdf>, String> {
+        if shapes.is_empty() {
+            return Err("shapes no puede estar vacío".to_string());
+        }
+        shapes.iter().map(build_sdf).collect()
+    };
+    Ok(match def {
+        SdfDef::Sphere { center, radius } => Sdf::Sphere { center: vec3(*center), radius: positive("radius", *radius)? },
+        SdfDef::Box { center, size, rounding } => {
+            let half_size = vec3(*size) * 0.5;
+            if half_size.x.min(half_size.y).min(half_size.z) <= 0.0 {
+                return Err("size debe ser mayor que cero en los tres ejes".to_string());
+            }
+            if *rounding < 0.0 || *rounding > half_size.x.min(half_size.y).min(half_size.z) {
+                return Err("rounding debe estar entre 0 y la mitad del lado más corto".to_string());
+            }
+            Sdf::Box { center: vec3(*center), half_size, rounding: *rounding }
+        }
+        SdfDef::Capsule { from, to, radius } => Sdf::Capsule { a: vec3(*from), b: vec3(*to), radius: positive("radius", *radius)? },
+        SdfDef::Cylinder { center, radius, height } => Sdf::Cylinder {
+            center: vec3(*center),
+            radius: positive("radius", *radius)?,
+            half_height: positive("height", *height)? * 0.5,
+        },
+        SdfDef::Union { shapes } => Sdf::Union(build_all(shapes)?),
+        SdfDef::SmoothUnion { shapes, blend } => Sdf::SmoothUnion(build_all(shapes)?, positive("blend", *blend)?),
+        SdfDef::Subtract { base, remove } => Sdf::Subtraction(Box::new(build_sdf(base)?), Box::new(build_sdf(remove)?)),
+        SdfDef::Displace { shape, amplitude, frequency, flow } => Sdf::Displace {
+            shape: Box::new(build_sdf(shape)?),
+            amplitude: *amplitude,
+            frequency: positive("frequency", *frequency)?,
+            flow: vec3(*flow),
+        },
+    })
+}
+
+// Escala, luego rotación en grados (mismo orden de ejes que Cube) y traslación
+fn placement(position: [f32; 3], rotation: Option<[f32; 3]>, scale: Option<f32>) -> Result<Mat4, String> {
+    let scale = scale.unwrap_or(1.0);
+    if scale <= 0.0 {
+        return Err("scale debe ser mayor que cero".to_string());
+    }
+    Ok(affine(position, rotation, Vec3::new(scale, scale, scale)))
+}
+
+fn affine(translate: [f32; 3], rotate: Option<[f32; 3]>, scale: Vec3) -> Mat4 {
+    let rotation = rotate.map(|r| vec3(r.map(f32::to_radians))).unwrap_or(Vec3::zero());
+    Mat4::translation(vec3(translate)) * Mat4::rotation_euler(rotation) * Mat4::scale(scale)
+}
+
+fn build_transform(def: &TransformDef) -> Result<Transform, String> {
+    let scale = match def.scale {
+        Some(ScaleDef::Uniform(s)) => Vec3::new(s, s, s),
+        Some(ScaleDef::Axes(axes)) => vec3(axes),
+        None => Vec3::new(1.0, 1.0, 1.0),
+    };
+    Transform::new(affine(def.translate, def.rotate, scale)).ok_or_else(|| "transform no puede tener escala cero".to_string())
+}
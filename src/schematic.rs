@@ -0,0 +1,251 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::blocks::{block_name, BlockRegistry};
+use crate::nbt::{self, Tag, invalid_data};
+use crate::voxel::VoxelWorld;
+
+// Construcciones guardadas como esquemático Sponge (.schem, versiones 1-3) o como
+// estructura de vanilla (.nbt, el formato de los bloques de estructura)
+pub struct Schematic {
+    pub size: (i32, i32, i32),
+    pub palette: Vec<String>,
+    pub blocks: Vec<((i32, i32, i32), usize)>, // Posición relativa e índice en la paleta
+}
+
+// Giro alrededor del eje Y, en sentido horario visto desde arriba
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rotation {
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+impl Rotation {
+    pub fn from_degrees(degrees: i32) -> Option<Self> {
+        match degrees.rem_euclid(360) {
+            0 => Some(Rotation::None),
+            90 => Some(Rotation::Clockwise90),
+            180 => Some(Rotation::Clockwise180),
+            270 => Some(Rotation::Clockwise270),
+            _ => None,
+        }
+    }
+
+    // Rota (x, z) dentro de una huella de size_x por size_z, manteniéndola en el cuadrante positivo
+    fn apply(self, x: i32, z: i32, size_x: i32, size_z: i32) -> (i32, i32) {
+        match self {
+            Rotation::None => (x, z),
+            Rotation::Clockwise90 => (size_z - 1 - z, x),
+            Rotation::Clockwise180 => (size_x - 1 - x, size_z - 1 - z),
+            Rotation::Clockwise270 => (z, size_x - 1 - x),
+        }
+    }
+}
+
+// Una construcción a colocar en la escena (--schem/--at/--rotate)
+pub struct StructurePlacement {
+    pub path: PathBuf,
+    pub origin: (i32, i32, i32),
+    pub rotation: Rotation,
+}
+
+pub struct BlockPlacement<'a> {
+    pub pos: (i32, i32, i32),
+    pub name: &'a str,
+}
+
+impl Schematic {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let root = nbt::parse_compressed(&fs::read(path)?)?;
+        // Sponge v3 envuelve todo en un compound "Schematic"
+        if let Some(inner) = root.get("Schematic") {
+            return Self::from_sponge(inner);
+        }
+        if root.get("BlockData").is_some() || root.get("Blocks").is_some() {
+            return Self::from_sponge(&root);
+        }
+        if root.get("palette").is_some() || root.get("palettes").is_some() {
+            return Self::from_structure(&root);
+        }
+        Err(invalid_data("no es un esquemático Sponge ni una estructura de Minecraft"))
+    }
+
+    fn from_sponge(root: &Tag) -> io::Result<Self> {
+        let dim = |name: &str| {
+            root.get(name).and_then(Tag::as_i32)
+                .map(|v| v & 0xffff) // Se guardan como short sin signo
+                .ok_or_else(|| invalid_data(format!("esquemático sin {}", name)))
+        };
+        let size = (dim("Width")?, dim("Height")?, dim("Length")?);
+
+        // v3: Blocks { Palette, Data }; v1/v2: Palette y BlockData en la raíz
+        let (palette_tag, data_tag) = match root.get("Blocks") {
+            Some(blocks) => (blocks.get("Palette"), blocks.get("Data")),
+            None => (root.get("Palette"), root.get("BlockData")),
+        };
+        let palette_map = palette_tag.and_then(Tag::as_compound)
+            .ok_or_else(|| invalid_data("esquemático sin paleta"))?;
+        let data = data_tag.and_then(Tag::as_byte_array)
+            .ok_or_else(|| invalid_data("esquemático sin datos de bloques"))?;
+
+        let mut palette = vec![String::new(); palette_map.len()];
+        for (name, index) in palette_map {
+            let index = index.as_i32().filter(|&i| i >= 0 && (i as usize) < palette.len())
+                .ok_or_else(|| invalid_data(format!("índice de paleta inválido para {}", name)))?;
+            palette[index as usize] = name.clone();
+        }
+
+        // Índices de paleta como varints, en orden (y, z, x)
+        let mut blocks = Vec::new();
+        let mut bytes = data.iter().map(|&b| b as u8);
+        let volume = size.0 as usize * size.1 as usize * size.2 as usize;
+        for index in 0..volume {
+            let value = read_varint(&mut bytes)?;
+            if value >= palette.len() {
+                return Err(invalid_data(format!("índice de paleta {} fuera de rango", value)));
+            }
+            let index = index as i32;
+            let x = index % size.0;
+            let z = (index / size.0) % size.2;
+            let y = index / (size.0 * size.2);
+            blocks.push(((x, y, z), value));
+        }
+
+        Ok(Schematic { size, palette, blocks })
+    }
+
+    fn from_structure(root: &Tag) -> io::Result<Self> {
+        let size = root.get("size").and_then(Tag::as_int_vec).filter(|s| s.len() == 3)
+            .ok_or_else(|| invalid_data("estructura sin tamaño"))?;
+
+        // Las estructuras con variantes (naufragios) traen varias paletas; se usa la primera
+        let palette_list = match root.get("palette") {
+            Some(palette) => palette.as_list(),
+            None => root.get("palettes").and_then(Tag::as_list).and_then(|p| p.first()).and_then(Tag::as_list),
+        }.ok_or_else(|| invalid_data("estructura sin paleta"))?;
+        let palette: Vec<String> = palette_list.iter()
+            .map(|entry| entry.get("Name").and_then(Tag::as_str).unwrap_or("minecraft:air").to_string())
+            .collect();
+
+        let mut blocks = Vec::new();
+        for block in root.get("blocks").and_then(Tag::as_list).unwrap_or(&[]) {
+            let pos = block.get("pos").and_then(Tag::as_int_vec).filter(|p| p.len() == 3)
+                .ok_or_else(|| invalid_data("bloque de estructura sin posición"))?;
+            let state = block.get("state").and_then(Tag::as_i32)
+                .filter(|&s| s >= 0 && (s as usize) < palette.len())
+                .ok_or_else(|| invalid_data("bloque de estructura con estado inválido"))?;
+            blocks.push(((pos[0], pos[1], pos[2]), state as usize));
+        }
+
+        Ok(Schematic { size: (size[0], size[1], size[2]), palette, blocks })
+    }
+
+    // Bloques en coordenadas de mundo: `origin` es la esquina mínima de la construcción ya rotada
+    pub fn placements(&self, origin: (i32, i32, i32), rotation: Rotation) -> impl Iterator<Item = BlockPlacement<'_>> {
+        self.blocks.iter().map(move |&((x, y, z), state)| {
+            let (rx, rz) = rotation.apply(x, z, self.size.0, self.size.2);
+            BlockPlacement {
+                pos: (origin.0 + rx, origin.1 + y, origin.2 + rz),
+                name: &self.palette[state],
+            }
+        })
+    }
+
+    // Escribe la construcción en el mundo; el aire reemplaza lo que hubiera (para tallar
+    // habitaciones en el terreno) y structure_void deja el bloque existente
+    pub fn place(&self, world: &mut VoxelWorld, registry: &mut BlockRegistry, origin: (i32, i32, i32), rotation: Rotation) -> usize {
+        let mut placed = 0;
+        for block in self.placements(origin, rotation) {
+            if block_name(block.name) == "structure_void" { continue; }
+            let id = registry.id_for(world, block.name);
+            world.set_block(block.pos.0, block.pos.1, block.pos.2, id);
+            placed += 1;
+        }
+        placed
+    }
+}
+
+// Entero de 7 bits por byte, el menos significativo primero; el bit alto indica que sigue otro
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> io::Result<usize> {
+    let mut value = 0usize;
+    let mut shift = 0;
+    loop {
+        let byte = bytes.next().ok_or_else(|| invalid_data("datos de bloques truncados"))?;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 { return Ok(value); }
+        shift += 7;
+        if shift > 28 {
+            return Err(invalid_data("varint demasiado largo en los datos de bloques"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varints(data: &[u8]) -> io::Result<Vec<usize>> {
+        let mut bytes = data.iter().copied();
+        let mut values = Vec::new();
+        while bytes.len() > 0 {
+            values.push(read_varint(&mut bytes)?);
+        }
+        Ok(values)
+    }
+
+    #[test]
+    fn decodes_varints() {
+        assert_eq!(varints(&[0, 1, 0x7f]).unwrap(), vec![0, 1, 127]);
+        assert_eq!(varints(&[0x80, 0x01, 0xac, 0x02]).unwrap(), vec![128, 300]);
+        assert_eq!(varints(&[0xff, 0xff, 0xff, 0xff, 0x0f]).unwrap(), vec![(1 << 32) - 1]);
+    }
+
+    #[test]
+    fn rejects_bad_varints() {
+        assert!(varints(&[0x80]).is_err(), "varint truncado");
+        assert!(varints(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x01]).is_err(), "varint de más de 5 bytes");
+    }
+
+    fn sponge(size: (i32, i32, i32), palette: &[String], data: &[u8]) -> Tag {
+        let palette = palette.iter().enumerate()
+            .map(|(i, name)| (name.to_string(), Tag::Int(i as i32)))
+            .collect();
+        Tag::Compound([
+            ("Width".to_string(), Tag::Short(size.0 as i16)),
+            ("Height".to_string(), Tag::Short(size.1 as i16)),
+            ("Length".to_string(), Tag::Short(size.2 as i16)),
+            ("Palette".to_string(), Tag::Compound(palette)),
+            ("BlockData".to_string(), Tag::ByteArray(data.iter().map(|&b| b as i8).collect())),
+        ].into_iter().collect())
+    }
+
+    #[test]
+    fn decodes_sponge_palette_indices() {
+        // 2x2x1 bloques en orden (y, z, x); el índice 130 ocupa dos bytes
+        let palette: Vec<String> = (0..131).map(|i| format!("minecraft:block_{}", i)).collect();
+        let schematic = Schematic::from_sponge(&sponge((2, 2, 1), &palette, &[1, 0, 0x82, 0x01, 1])).unwrap();
+        assert_eq!(schematic.size, (2, 2, 1));
+        assert_eq!(schematic.blocks, vec![((0, 0, 0), 1), ((1, 0, 0), 0), ((0, 1, 0), 130), ((1, 1, 0), 1)]);
+        assert_eq!(schematic.palette, palette);
+    }
+
+    #[test]
+    fn rejects_bad_sponge_data() {
+        let palette = ["minecraft:air".to_string(), "minecraft:stone".to_string()];
+        assert!(Schematic::from_sponge(&sponge((2, 1, 1), &palette, &[1])).is_err(), "faltan bloques");
+        assert!(Schematic::from_sponge(&sponge((1, 1, 1), &palette, &[2])).is_err(), "índice fuera de la paleta");
+    }
+
+    #[test]
+    fn rotation_keeps_footprint_in_place() {
+        // Esquina (0, 0) de una huella de 3x2 en cada giro
+        assert_eq!(Rotation::Clockwise90.apply(0, 0, 3, 2), (1, 0));
+        assert_eq!(Rotation::Clockwise180.apply(0, 0, 3, 2), (2, 1));
+        assert_eq!(Rotation::Clockwise270.apply(0, 0, 3, 2), (0, 2));
+        assert_eq!(Rotation::from_degrees(-90), Some(Rotation::Clockwise270));
+        assert_eq!(Rotation::from_degrees(45), None);
+    }
+}
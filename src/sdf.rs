@@ -0,0 +1,206 @@
+use crate::geometry::*;
+use crate::materials::MaterialId;
+use crate::raytracer::{Emitter, HitInfo};
+use crate::shapes::Primitive;
+
+// Superficies implícitas dadas por una función de distancia con signo (negativa adentro),
+// trazadas con sphere tracing: se avanza a lo largo del rayo lo que indica la distancia,
+// que nunca atraviesa la superficie
+
+pub trait DistanceField: Send + Sync {
+    fn distance(&self, p: Vec3, time: f32) -> f32;
+    fn bounds(&self) -> (Vec3, Vec3);
+    // Cuánto puede crecer la distancia por unidad recorrida; si es mayor que 1 los pasos se acortan
+    fn lipschitz(&self) -> f32 {
+        1.0
+    }
+}
+
+// Pendiente máxima de geometry::noise llevado a [-1, 1]: 1.5 por eje (smoothstep) en tres ejes, por 2
+const NOISE_SLOPE: f32 = 5.2;
+
+// Árbol de formas básicas y operaciones
+pub enum Sdf {
+    Sphere { center: Vec3, radius: f32 },
+    // Caja con esquinas redondeadas por `rounding`
+    Box { center: Vec3, half_size: Vec3, rounding: f32 },
+    Capsule { a: Vec3, b: Vec3, radius: f32 },
+    // Cilindro vertical con tapas
+    Cylinder { center: Vec3, radius: f32, half_height: f32 },
+    Union(Vec<Sdf>),
+    // Unión con las uniones redondeadas en un radio `k`
+    SmoothUnion(Vec<Sdf>, f32),
+    // La primera forma menos la segunda
+    Subtraction(Box<Sdf>, Box<Sdf>),
+    // Superficie desplazada por ruido; `flow` desliza el ruido con el tiempo (lava que cae)
+    Displace { shape: Box<Sdf>, amplitude: f32, frequency: f32, flow: Vec3 },
+}
+
+fn smooth_min(a: f32, b: f32, k: f32) -> f32 {
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    b * (1.0 - h) + a * h - k * h * (1.0 - h)
+}
+
+fn merge_bounds(shapes: &[Sdf]) -> (Vec3, Vec3) {
+    shapes.iter().map(Sdf::bounds).fold(
+        (Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY), Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY)),
+        |(min, max), (bmin, bmax)| (min.min(bmin), max.max(bmax)),
+    )
+}
+
+fn expand((min, max): (Vec3, Vec3), amount: f32) -> (Vec3, Vec3) {
+    let margin = Vec3::new(amount, amount, amount);
+    (min - margin, max + margin)
+}
+
+impl DistanceField for Sdf {
+    fn distance(&self, p: Vec3, time: f32) -> f32 {
+        match self {
+            Sdf::Sphere { center, radius } => (p - *center).length() - radius,
+            Sdf::Box { center, half_size, rounding } => {
+                let local = p - *center;
+                let q = Vec3::new(local.x.abs(), local.y.abs(), local.z.abs()) - (*half_size - Vec3::new(*rounding, *rounding, *rounding));
+                q.max(Vec3::zero()).length() + q.x.max(q.y).max(q.z).min(0.0) - rounding
+            }
+            Sdf::Capsule { a, b, radius } => {
+                let pa = p - *a;
+                let ba = *b - *a;
+                let h = (pa.dot(ba) / ba.length_squared().max(1e-12)).clamp(0.0, 1.0);
+                (pa - ba * h).length() - radius
+            }
+            Sdf::Cylinder { center, radius, half_height } => {
+                let local = p - *center;
+                let dx = (local.x * local.x + local.z * local.z).sqrt() - radius;
+                let dy = local.y.abs() - half_height;
+                dx.max(dy).min(0.0) + (dx.max(0.0) * dx.max(0.0) + dy.max(0.0) * dy.max(0.0)).sqrt()
+            }
+            Sdf::Union(shapes) => shapes.iter().map(|s| s.distance(p, time)).fold(f32::INFINITY, f32::min),
+            Sdf::SmoothUnion(shapes, k) => shapes.iter()
+                .map(|s| s.distance(p, time))
+                .reduce(|a, b| smooth_min(a, b, *k))
+                .unwrap_or(f32::INFINITY),
+            Sdf::Subtraction(base, removed) => base.distance(p, time).max(-removed.distance(p, time)),
+            Sdf::Displace { shape, amplitude, frequency, flow } => {
+                let sample = (p - *flow * time) * *frequency;
+                shape.distance(p, time) + amplitude * (noise(sample) * 2.0 - 1.0)
+            }
+        }
+    }
+
+    fn bounds(&self) -> (Vec3, Vec3) {
+        match self {
+            Sdf::Sphere { center, radius } => expand((*center, *center), *radius),
+            Sdf::Box { center, half_size, .. } => (*center - *half_size, *center + *half_size),
+            Sdf::Capsule { a, b, radius } => expand((a.min(*b), a.max(*b)), *radius),
+            Sdf::Cylinder { center, radius, half_height } => {
+                let half = Vec3::new(*radius, *half_height, *radius);
+                (*center - half, *center + half)
+            }
+            Sdf::Union(shapes) => merge_bounds(shapes),
+            // La unión suave agrega como mucho k/4 de material entre las formas
+            Sdf::SmoothUnion(shapes, k) => expand(merge_bounds(shapes), k * 0.25),
+            Sdf::Subtraction(base, _) => base.bounds(),
+            Sdf::Displace { shape, amplitude, .. } => expand(shape.bounds(), amplitude.abs()),
+        }
+    }
+
+    fn lipschitz(&self) -> f32 {
+        match self {
+            Sdf::Union(shapes) | Sdf::SmoothUnion(shapes, _) => shapes.iter().map(Sdf::lipschitz).fold(1.0, f32::max),
+            Sdf::Subtraction(base, removed) => base.lipschitz().max(removed.lipschitz()),
+            Sdf::Displace { shape, amplitude, frequency, .. } => shape.lipschitz() + amplitude.abs() * frequency.abs() * NOISE_SLOPE,
+            _ => 1.0,
+        }
+    }
+}
+
+pub struct SdfPrimitive<F: DistanceField> {
+    pub field: F,
+    pub material: MaterialId,
+    bounds: (Vec3, Vec3),
+}
+
+const MAX_STEPS: usize = 256;
+// Distancia a la que se considera que el rayo tocó la superficie (por unidad de recorrido);
+// menor que el desplazamiento de 0.001 con que salen los rayos secundarios
+const SURFACE_EPSILON: f32 = 1e-4;
+
+impl<F: DistanceField> SdfPrimitive<F> {
+    pub fn new(field: F, material: MaterialId) -> Self {
+        let bounds = expand(field.bounds(), 1e-3);
+        SdfPrimitive { field, material, bounds }
+    }
+
+    // Normal por gradiente con cuatro muestras en tetraedro
+    fn normal_at(&self, p: Vec3, time: f32) -> Vec3 {
+        let h = 1e-3;
+        let offsets = [Vec3::new(1.0, -1.0, -1.0), Vec3::new(-1.0, -1.0, 1.0), Vec3::new(-1.0, 1.0, -1.0), Vec3::new(1.0, 1.0, 1.0)];
+        offsets.iter()
+            .fold(Vec3::zero(), |sum, &k| sum + k * self.field.distance(p + k * h, time))
+            .normalize()
+    }
+}
+
+// Tramo del rayo dentro de la caja, si lo hay
+fn slab_interval(ray: &Ray, (min, max): (Vec3, Vec3)) -> Option<(f32, f32)> {
+    let mut t_enter = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    for (origin, direction, lo, hi) in [
+        (ray.origin.x, ray.direction.x, min.x, max.x),
+        (ray.origin.y, ray.direction.y, min.y, max.y),
+        (ray.origin.z, ray.direction.z, min.z, max.z),
+    ] {
+        if direction.abs() < 1e-8 {
+            if origin < lo || origin > hi {
+                return None;
+            }
+            continue;
+        }
+        let (t0, t1) = ((lo - origin) / direction, (hi - origin) / direction);
+        t_enter = t_enter.max(t0.min(t1));
+        t_exit = t_exit.min(t0.max(t1));
+    }
+    (t_enter <= t_exit && t_exit > 0.0).then_some((t_enter, t_exit))
+}
+
+impl<F: DistanceField> Primitive for SdfPrimitive<F> {
+    fn intersect(&self, ray: &Ray, time: f32) -> Option<HitInfo> {
+        let (t_enter, t_exit) = slab_interval(ray, self.bounds)?;
+        let step_scale = 1.0 / self.field.lipschitz();
+        let mut t = t_enter.max(0.001);
+
+        // Se avanza con el valor absoluto: un rayo que arranca adentro (refracción) busca la salida
+        for _ in 0..MAX_STEPS {
+            if t > t_exit {
+                return None;
+            }
+            let point = ray.position_at(t);
+            let distance = self.field.distance(point, time).abs() * step_scale;
+            if distance < SURFACE_EPSILON * t.max(1.0) {
+                let normal = self.normal_at(point, time);
+                // Proyección según el eje dominante de la normal, como una caja
+                let (u, v) = if normal.y.abs() > normal.x.abs().max(normal.z.abs()) {
+                    (point.x, point.z)
+                } else if normal.x.abs() > normal.z.abs() {
+                    (point.z, point.y)
+                } else {
+                    (point.x, point.y)
+                };
+                return Some(HitInfo {
+                    t,
+                    point,
+                    normal,
+                    material: self.material,
+                    uv: (u.rem_euclid(1.0), v.rem_euclid(1.0)),
+                    emitter: Emitter::Unsampled,
+                });
+            }
+            t += distance;
+        }
+        None
+    }
+
+    fn get_bounds(&self, _time: f32) -> (Vec3, Vec3) {
+        self.bounds
+    }
+}
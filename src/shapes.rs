@@ -1,26 +1,55 @@
+use crate::area_lights::{face_uvs, AreaLight};
 use crate::geometry::*;
-use crate::materials::Material;
-use crate::raytracer::HitInfo;
+use crate::materials::{MaterialId, MaterialRegistry};
+use crate::raytracer::{Emitter, HitInfo};
 
 pub trait Primitive: Send + Sync {
     fn intersect(&self, ray: &Ray, time: f32) -> Option<HitInfo>;
-    fn get_bounds(&self) -> (Vec3, Vec3);
+    // Caja que contiene a la primitiva en el instante `time`
+    fn get_bounds(&self, time: f32) -> (Vec3, Vec3);
+    // Si la caja cambia con el tiempo; el BVH de la escena reajusta cada cuadro solo los
+    // nodos de estas primitivas en vez de reconstruirse
+    fn is_animated(&self) -> bool {
+        false
+    }
+    // Tramos de toda la recta del rayo (también con t negativo) que quedan dentro del sólido,
+    // ordenados; None si la primitiva no encierra un volumen y no sirve para CSG
+    fn spans(&self, _ray: &Ray, _time: f32) -> Option<Vec<Span>> {
+        None
+    }
+    // Si algo corta el rayo entre 0.001 y `max_t`, para rayos de sombra: no hace falta el
+    // impacto más cercano ni su material, así que las primitivas que pueden lo responden
+    // sin armar un HitInfo
+    fn occluded(&self, ray: &Ray, max_t: f32, time: f32) -> bool {
+        self.intersect(ray, time).is_some_and(|hit| hit.t > 0.001 && hit.t < max_t)
+    }
+    // Superficies emisivas que se pueden muestrear como luces de área; las formas que no
+    // saben hacerlo (planos, SDF, CSG, objetos animados) solo iluminan cuando un rebote las
+    // encuentra. Los impactos de `intersect` marcan en `emitter` lo que se entrega acá
+    fn area_lights(&self, _materials: &MaterialRegistry, _out: &mut Vec<AreaLight>) {}
+}
+
+// Entrada y salida del rayo en un sólido, ambas con la normal hacia afuera
+pub struct Span {
+    pub enter: HitInfo,
+    pub exit: HitInfo,
 }
 
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f32,
-    pub material: Material,
+    pub material: MaterialId,
 }
 
 impl Sphere {
-    pub fn new(center: Vec3, radius: f32, material: Material) -> Self {
+    pub fn new(center: Vec3, radius: f32, material: MaterialId) -> Self {
         Self { center, radius, material }
     }
 }
 
-impl Primitive for Sphere {
-    fn intersect(&self, ray: &Ray, _time: f32) -> Option<HitInfo> {
+impl Sphere {
+    // Raíces de la recta del rayo con la esfera, ordenadas
+    fn roots(&self, ray: &Ray) -> Option<(f32, f32)> {
         let oc = ray.origin - self.center;
         let a = ray.direction.dot(ray.direction);
         let b = 2.0 * oc.dot(ray.direction);
@@ -32,11 +61,10 @@ impl Primitive for Sphere {
         }
         
         let sqrt_d = discriminant.sqrt();
-        let t1 = (-b - sqrt_d) / (2.0 * a);
-        let t2 = (-b + sqrt_d) / (2.0 * a);
-        
-        let t = if t1 > 0.001 { t1 } else if t2 > 0.001 { t2 } else { return None; };
-        
+        Some(((-b - sqrt_d) / (2.0 * a), (-b + sqrt_d) / (2.0 * a)))
+    }
+
+    fn hit_at(&self, ray: &Ray, t: f32) -> HitInfo {
         let point = ray.position_at(t);
         let normal = (point - self.center).normalize();
         
@@ -46,30 +74,56 @@ impl Primitive for Sphere {
         let u = phi / (2.0 * std::f32::consts::PI);
         let v = theta / std::f32::consts::PI;
         
-        Some(HitInfo {
+        HitInfo {
             t,
             point,
             normal,
-            material: self.material.clone(),
+            material: self.material,
             uv: (u, v),
-        })
+            emitter: Emitter::Sphere,
+        }
+    }
+}
+
+impl Primitive for Sphere {
+    fn intersect(&self, ray: &Ray, _time: f32) -> Option<HitInfo> {
+        let (t1, t2) = self.roots(ray)?;
+        let t = if t1 > 0.001 { t1 } else if t2 > 0.001 { t2 } else { return None; };
+        Some(self.hit_at(ray, t))
     }
     
-    fn get_bounds(&self) -> (Vec3, Vec3) {
+    fn get_bounds(&self, _time: f32) -> (Vec3, Vec3) {
         let r = Vec3::new(self.radius, self.radius, self.radius);
         (self.center - r, self.center + r)
     }
+
+    fn occluded(&self, ray: &Ray, max_t: f32, _time: f32) -> bool {
+        self.roots(ray).is_some_and(|(t1, t2)| {
+            let t = if t1 > 0.001 { t1 } else { t2 };
+            t > 0.001 && t < max_t
+        })
+    }
+
+    fn spans(&self, ray: &Ray, _time: f32) -> Option<Vec<Span>> {
+        Some(self.roots(ray).map(|(t1, t2)| Span { enter: self.hit_at(ray, t1), exit: self.hit_at(ray, t2) }).into_iter().collect())
+    }
+
+    fn area_lights(&self, materials: &MaterialRegistry, out: &mut Vec<AreaLight>) {
+        if materials[self.material].is_emissive() {
+            out.push(AreaLight::sphere(self.center, self.radius, self.material));
+        }
+    }
 }
 
 pub struct Plane {
     pub point: Vec3,
     pub normal: Vec3,
-    pub material: Material,
+    pub material: MaterialId,
     pub size: Option<(f32, f32)>, // Tamaño opcional
 }
 
 impl Plane {
-    pub fn new(point: Vec3, normal: Vec3, material: Material) -> Self {
+    pub fn new(point: Vec3, normal: Vec3, material: MaterialId) -> Self {
         Self {
             point,
             normal: normal.normalize(),
@@ -82,6 +136,18 @@ impl Plane {
         self.size = Some((width, height));
         self
     }
+
+    // Ejes del rectángulo sobre el plano (ancho, alto)
+    fn axes(&self) -> (Vec3, Vec3) {
+        let u_axis = if self.normal.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let u_axis = u_axis.cross(self.normal).normalize();
+        let v_axis = self.normal.cross(u_axis).normalize();
+        (u_axis, v_axis)
+    }
 }
 
 impl Primitive for Plane {
@@ -103,13 +169,7 @@ impl Primitive for Plane {
         // Verificar límites de tamaño si están especificados
         if let Some((width, height)) = self.size {
             let local_point = point - self.point;
-            let u_axis = if self.normal.x.abs() > 0.9 {
-                Vec3::new(0.0, 1.0, 0.0)
-            } else {
-                Vec3::new(1.0, 0.0, 0.0)
-            };
-            let u_axis = u_axis.cross(self.normal).normalize();
-            let v_axis = self.normal.cross(u_axis).normalize();
+            let (u_axis, v_axis) = self.axes();
             
             let u_coord = local_point.dot(u_axis);
             let v_coord = local_point.dot(v_axis);
@@ -127,20 +187,25 @@ impl Primitive for Plane {
             t,
             point,
             normal: self.normal,
-            material: self.material.clone(),
+            material: self.material,
             uv: (u, v),
+            emitter: Emitter::Unsampled,
         })
     }
     
-    fn get_bounds(&self) -> (Vec3, Vec3) {
-        // Retornar límites muy grandes para plano infinito
-        if self.size.is_none() {
-            (Vec3::new(-1000.0, -1000.0, -1000.0), Vec3::new(1000.0, 1000.0, 1000.0))
-        } else {
-            let (w, h) = self.size.unwrap();
-            let half_w = Vec3::new(w * 0.5, 0.0, 0.0);
-            let half_h = Vec3::new(0.0, h * 0.5, 0.0);
-            (self.point - half_w - half_h, self.point + half_w + half_h)
+    fn get_bounds(&self, _time: f32) -> (Vec3, Vec3) {
+        // El plano infinito no tiene límites: el BVH lo deja fuera del árbol
+        match self.size {
+            None => (
+                Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+                Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            ),
+            Some((w, h)) => {
+                let (u_axis, v_axis) = self.axes();
+                let half = |axis: Vec3, size: f32| Vec3::new(axis.x.abs(), axis.y.abs(), axis.z.abs()) * (size * 0.5);
+                let extent = half(u_axis, w) + half(v_axis, h);
+                (self.point - extent, self.point + extent)
+            }
         }
     }
 }
@@ -148,12 +213,12 @@ impl Primitive for Plane {
 pub struct Cube {
     pub center: Vec3,
     pub size: f32,
-    pub material: Material,
+    pub material: MaterialId,
     pub rotation: Vec3, // Ángulos de Euler
 }
 
 impl Cube {
-    pub fn new(center: Vec3, size: f32, material: Material) -> Self {
+    pub fn new(center: Vec3, size: f32, material: MaterialId) -> Self {
         Self {
             center,
             size,
@@ -168,18 +233,21 @@ impl Cube {
     }
 }
 
-impl Primitive for Cube {
-    fn intersect(&self, ray: &Ray, _time: f32) -> Option<HitInfo> {
-        // Transformar rayo al espacio local del cubo si está rotado
-        let (local_ray, inv_rotation) = if self.rotation != Vec3::zero() {
+impl Cube {
+    // Rayo en el espacio local del cubo si está rotado
+    fn local_ray(&self, ray: &Ray) -> Ray {
+        if self.rotation != Vec3::zero() {
             let inv_rot = -self.rotation;
             let local_origin = (ray.origin - self.center).rotate_y(inv_rot.y).rotate_x(inv_rot.x).rotate_z(inv_rot.z);
             let local_dir = ray.direction.rotate_y(inv_rot.y).rotate_x(inv_rot.x).rotate_z(inv_rot.z);
-            (Ray::new(local_origin + self.center, local_dir), Some(inv_rot))
+            Ray::new(local_origin + self.center, local_dir)
         } else {
-            (Ray::new(ray.origin, ray.direction), None)
-        };
-        
+            Ray::new(ray.origin, ray.direction)
+        }
+    }
+
+    // Tramo de la recta del rayo local dentro de la caja
+    fn slabs(&self, local_ray: &Ray) -> Option<(f32, f32)> {
         let half_size = self.size / 2.0;
         let min = self.center - Vec3::new(half_size, half_size, half_size);
         let max = self.center + Vec3::new(half_size, half_size, half_size);
@@ -200,11 +268,17 @@ impl Primitive for Cube {
         let t_min = t_min_x.max(t_min_y).max(t_min_z);
         let t_max = t_max_x.min(t_max_y).min(t_max_z);
         
-        if t_max < 0.0 || t_min > t_max {
+        if t_min > t_max {
             return None;
         }
-        
-        let t = if t_min > 0.001 { t_min } else if t_max > 0.001 { t_max } else { return None; };
+        Some((t_min, t_max))
+    }
+
+    fn hit_at(&self, local_ray: &Ray, t: f32) -> HitInfo {
+        let half_size = self.size / 2.0;
+        let min = self.center - Vec3::new(half_size, half_size, half_size);
+        let max = self.center + Vec3::new(half_size, half_size, half_size);
+        let rotated = self.rotation != Vec3::zero();
         
         let local_point = local_ray.position_at(t);
         
@@ -227,12 +301,12 @@ impl Primitive for Cube {
         }
         
         // Transformar normal de vuelta al espacio mundial si está rotado
-        if let Some(_) = inv_rotation {
+        if rotated {
             normal = normal.rotate_z(self.rotation.z).rotate_x(self.rotation.x).rotate_y(self.rotation.y);
         }
         
         // Transformar punto de vuelta al espacio mundial
-        let world_point = if inv_rotation.is_some() {
+        let world_point = if rotated {
             let local_offset = local_point - self.center;
             let world_offset = local_offset.rotate_z(self.rotation.z).rotate_x(self.rotation.x).rotate_y(self.rotation.y);
             self.center + world_offset
@@ -253,16 +327,30 @@ impl Primitive for Cube {
             ((local_point_centered.x + half_size) / self.size, (local_point_centered.y + half_size) / self.size)
         };
         
-        Some(HitInfo {
+        HitInfo {
             t,
             point: world_point,
             normal,
-            material: self.material.clone(),
+            material: self.material,
             uv: (u, v),
-        })
+            emitter: Emitter::Surface,
+        }
+    }
+}
+
+impl Primitive for Cube {
+    fn intersect(&self, ray: &Ray, _time: f32) -> Option<HitInfo> {
+        let local_ray = self.local_ray(ray);
+        let (t_min, t_max) = self.slabs(&local_ray)?;
+        if t_max < 0.0 {
+            return None;
+        }
+        
+        let t = if t_min > 0.001 { t_min } else if t_max > 0.001 { t_max } else { return None; };
+        Some(self.hit_at(&local_ray, t))
     }
     
-    fn get_bounds(&self) -> (Vec3, Vec3) {
+    fn get_bounds(&self, _time: f32) -> (Vec3, Vec3) {
         let half_size = self.size / 2.0;
         let extent = Vec3::new(half_size, half_size, half_size);
         
@@ -274,18 +362,47 @@ impl Primitive for Cube {
             (self.center - expanded, self.center + expanded)
         }
     }
+
+    fn occluded(&self, ray: &Ray, max_t: f32, _time: f32) -> bool {
+        self.slabs(&self.local_ray(ray)).is_some_and(|(t_min, t_max)| {
+            let t = if t_min > 0.001 { t_min } else { t_max };
+            t > 0.001 && t < max_t
+        })
+    }
+
+    fn spans(&self, ray: &Ray, _time: f32) -> Option<Vec<Span>> {
+        let local_ray = self.local_ray(ray);
+        Some(self.slabs(&local_ray).map(|(t_min, t_max)| Span { enter: self.hit_at(&local_ray, t_min), exit: self.hit_at(&local_ray, t_max) }).into_iter().collect())
+    }
+
+    // Una luz por cara, con la misma rotación que usa la intersección
+    fn area_lights(&self, materials: &MaterialRegistry, out: &mut Vec<AreaLight>) {
+        if !materials[self.material].is_emissive() {
+            return;
+        }
+        let rotate = |v: Vec3| v.rotate_z(self.rotation.z).rotate_x(self.rotation.x).rotate_y(self.rotation.y);
+        let half_size = self.size / 2.0;
+        let axes = [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)];
+        for axis in 0..3 {
+            let (u, v) = (axes[(axis + 1) % 3], axes[(axis + 2) % 3]);
+            for sign in [-1.0, 1.0] {
+                let corner = (axes[axis] * sign - u - v) * half_size;
+                out.push(AreaLight::quad(self.center + rotate(corner), rotate(u * self.size), rotate(v * self.size), self.material).with_uvs(face_uvs(axis)));
+            }
+        }
+    }
 }
 
 // Cubo giratorio que rota sobre el eje Y
 pub struct SpinningCube {
     pub center: Vec3,
     pub size: f32,
-    pub material: Material,
+    pub material: MaterialId,
     pub rotation_speed: f32,
 }
 
 impl SpinningCube {
-    pub fn new(center: Vec3, size: f32, material: Material, rotation_speed: f32) -> Self {
+    pub fn new(center: Vec3, size: f32, material: MaterialId, rotation_speed: f32) -> Self {
         Self { center, size, material, rotation_speed }
     }
 }
@@ -351,14 +468,22 @@ impl Primitive for SpinningCube {
             ((local_point_centered.x + half_size) / self.size, (local_point_centered.y + half_size) / self.size)
         };
 
-        Some(HitInfo { t, point: world_point, normal, material: self.material.clone(), uv: (u, v) })
+        Some(HitInfo { t, point: world_point, normal, material: self.material, uv: (u, v), emitter: Emitter::Unsampled })
     }
 
-    fn get_bounds(&self) -> (Vec3, Vec3) {
+    // Al girar sobre Y el ancho en X y Z es el del cuadrado rotado en el ángulo actual,
+    // con un margen para el redondeo del giro
+    fn get_bounds(&self, time: f32) -> (Vec3, Vec3) {
         let half_size = self.size / 2.0;
-        let extent = Vec3::new(half_size, half_size, half_size) * 1.73; // límites conservadores
+        let angle = time * self.rotation_speed;
+        let spread = half_size * (angle.cos().abs() + angle.sin().abs());
+        let extent = Vec3::new(spread, half_size, spread) + Vec3::new(1e-3, 1e-3, 1e-3);
         (self.center - extent, self.center + extent)
     }
+
+    fn is_animated(&self) -> bool {
+        self.rotation_speed != 0.0
+    }
 }
 
 pub struct Triangle {
@@ -366,36 +491,39 @@ pub struct Triangle {
     pub v1: Vec3,
     pub v2: Vec3,
     pub normal: Vec3,
-    pub material: Material,
+    pub material: MaterialId,
     pub uv0: (f32, f32),
     pub uv1: (f32, f32),
     pub uv2: (f32, f32),
+    pub vertex_normals: Option<[Vec3; 3]>, // Normales por vértice para sombreado suave
 }
 
 impl Triangle {
-    pub fn new(v0: Vec3, v1: Vec3, v2: Vec3, material: Material) -> Self {
+    pub fn new(v0: Vec3, v1: Vec3, v2: Vec3, material: MaterialId) -> Self {
         let normal = (v1 - v0).cross(v2 - v0).normalize();
         Triangle {
             v0, v1, v2, normal, material,
             uv0: (0.0, 0.0),
             uv1: (1.0, 0.0),
             uv2: (0.5, 1.0),
+            vertex_normals: None,
         }
     }
     
     pub fn new_with_uvs(
         v0: Vec3, v1: Vec3, v2: Vec3,
         uv0: (f32, f32), uv1: (f32, f32), uv2: (f32, f32),
-        material: Material
+        material: MaterialId
     ) -> Self {
         let normal = (v1 - v0).cross(v2 - v0).normalize();
         Triangle {
             v0, v1, v2, normal, material,
             uv0, uv1, uv2,
+            vertex_normals: None,
         }
     }
     
-    pub fn new_with_normal(v0: Vec3, v1: Vec3, v2: Vec3, normal: Vec3, material: Material) -> Self {
+    pub fn new_with_normal(v0: Vec3, v1: Vec3, v2: Vec3, normal: Vec3, material: MaterialId) -> Self {
         Triangle {
             v0, v1, v2, 
             normal: normal.normalize(), 
@@ -403,13 +531,17 @@ impl Triangle {
             uv0: (0.0, 0.0),
             uv1: (1.0, 0.0),
             uv2: (0.5, 1.0),
+            vertex_normals: None,
         }
     }
-}
-
-impl Primitive for Triangle {
-    fn intersect(&self, ray: &Ray, _time: f32) -> Option<HitInfo> {
-        // Algoritmo de intersección Möller-Trumbore
+    
+    pub fn with_vertex_normals(mut self, n0: Vec3, n1: Vec3, n2: Vec3) -> Self {
+        self.vertex_normals = Some([n0.normalize(), n1.normalize(), n2.normalize()]);
+        self
+    }
+    
+    // Algoritmo de intersección Möller-Trumbore: distancia y coordenadas baricéntricas (u, v)
+    fn barycentric_hit(&self, ray: &Ray) -> Option<(f32, f32, f32)> {
         let edge1 = self.v1 - self.v0;
         let edge2 = self.v2 - self.v0;
         let h = ray.direction.cross(edge2);
@@ -440,6 +572,13 @@ impl Primitive for Triangle {
             return None;
         }
         
+        Some((t, u, v))
+    }
+}
+
+impl Primitive for Triangle {
+    fn intersect(&self, ray: &Ray, _time: f32) -> Option<HitInfo> {
+        let (t, u, v) = self.barycentric_hit(ray)?;
         let point = ray.position_at(t);
         
         // Interpolar coordenadas UV
@@ -447,16 +586,23 @@ impl Primitive for Triangle {
         let interpolated_u = w * self.uv0.0 + u * self.uv1.0 + v * self.uv2.0;
         let interpolated_v = w * self.uv0.1 + u * self.uv1.1 + v * self.uv2.1;
         
+        // Con normales por vértice se interpolan con las mismas baricéntricas
+        let normal = match self.vertex_normals {
+            Some([n0, n1, n2]) => (n0 * w + n1 * u + n2 * v).normalize(),
+            None => self.normal,
+        };
+        
         Some(HitInfo {
             t,
             point,
-            normal: self.normal,
-            material: self.material.clone(),
+            normal,
+            material: self.material,
             uv: (interpolated_u, interpolated_v),
+            emitter: Emitter::Surface,
         })
     }
     
-    fn get_bounds(&self) -> (Vec3, Vec3) {
+    fn get_bounds(&self, _time: f32) -> (Vec3, Vec3) {
         let min_x = self.v0.x.min(self.v1.x).min(self.v2.x);
         let min_y = self.v0.y.min(self.v1.y).min(self.v2.y);
         let min_z = self.v0.z.min(self.v1.z).min(self.v2.z);
@@ -467,6 +613,16 @@ impl Primitive for Triangle {
         
         (Vec3::new(min_x, min_y, min_z), Vec3::new(max_x, max_y, max_z))
     }
+
+    fn occluded(&self, ray: &Ray, max_t: f32, _time: f32) -> bool {
+        self.barycentric_hit(ray).is_some_and(|(t, _, _)| t < max_t)
+    }
+
+    fn area_lights(&self, materials: &MaterialRegistry, out: &mut Vec<AreaLight>) {
+        if materials[self.material].is_emissive() {
+            out.push(AreaLight::triangle(self.v0, self.v1, self.v2, self.material).with_uvs([self.uv0, self.uv1, self.uv2]));
+        }
+    }
 }
 
 // Primitiva cilindro para más variedad
@@ -474,15 +630,54 @@ pub struct Cylinder {
     pub center: Vec3,
     pub radius: f32,
     pub height: f32,
-    pub material: Material,
+    pub material: MaterialId,
 }
 
 impl Cylinder {
-    pub fn new(center: Vec3, radius: f32, height: f32, material: Material) -> Self {
+    pub fn new(center: Vec3, radius: f32, height: f32, material: MaterialId) -> Self {
         Self { center, radius, height, material }
     }
 }
 
+impl Cylinder {
+    fn side_hit(&self, ray: &Ray, t: f32) -> HitInfo {
+        let half_height = self.height * 0.5;
+        let point = ray.position_at(t);
+        let local_point = point - self.center;
+        let normal = Vec3::new(local_point.x, 0.0, local_point.z).normalize();
+        
+        // Mapeo UV cilíndrico
+        let theta = local_point.z.atan2(local_point.x);
+        let u = (theta + std::f32::consts::PI) / (2.0 * std::f32::consts::PI);
+        let v = (local_point.y + half_height) / self.height;
+        
+        HitInfo {
+            t,
+            point,
+            normal,
+            material: self.material,
+            uv: (u, v),
+            emitter: Emitter::Unsampled,
+        }
+    }
+
+    fn cap_hit(&self, ray: &Ray, t: f32, normal: Vec3) -> HitInfo {
+        let point = ray.position_at(t);
+        let local_point = point - self.center;
+        let u = (local_point.x / self.radius + 1.0) * 0.5;
+        let v = (local_point.z / self.radius + 1.0) * 0.5;
+        
+        HitInfo {
+            t,
+            point,
+            normal,
+            material: self.material,
+            uv: (u, v),
+            emitter: Emitter::Unsampled,
+        }
+    }
+}
+
 impl Primitive for Cylinder {
     fn intersect(&self, ray: &Ray, _time: f32) -> Option<HitInfo> {
         let half_height = self.height * 0.5;
@@ -510,21 +705,7 @@ impl Primitive for Cylinder {
                 
                 if y_local.abs() <= half_height {
                     // Golpeó la superficie curva
-                    let local_point = point - self.center;
-                    let normal = Vec3::new(local_point.x, 0.0, local_point.z).normalize();
-                    
-                    // Mapeo UV cilíndrico
-                    let theta = local_point.z.atan2(local_point.x);
-                    let u = (theta + std::f32::consts::PI) / (2.0 * std::f32::consts::PI);
-                    let v = (y_local + half_height) / self.height;
-                    
-                    return Some(HitInfo {
-                        t,
-                        point,
-                        normal,
-                        material: self.material.clone(),
-                        uv: (u, v),
-                    });
+                    return Some(self.side_hit(ray, t));
                 }
             }
         }
@@ -541,16 +722,7 @@ impl Primitive for Cylinder {
                 
                 if radius_sq <= self.radius * self.radius {
                     // Golpeó una tapa
-                    let u = (local_point.x / self.radius + 1.0) * 0.5;
-                    let v = (local_point.z / self.radius + 1.0) * 0.5;
-                    
-                    return Some(HitInfo {
-                        t,
-                        point,
-                        normal,
-                        material: self.material.clone(),
-                        uv: (u, v),
-                    });
+                    return Some(self.cap_hit(ray, t, normal));
                 }
             }
         }
@@ -558,13 +730,57 @@ impl Primitive for Cylinder {
         None
     }
     
-    fn get_bounds(&self) -> (Vec3, Vec3) {
+    fn get_bounds(&self, _time: f32) -> (Vec3, Vec3) {
         let half_height = self.height * 0.5;
         (
             self.center - Vec3::new(self.radius, half_height, self.radius),
             self.center + Vec3::new(self.radius, half_height, self.radius)
         )
     }
+
+    // El sólido es el cilindro infinito recortado entre los planos de las tapas
+    fn spans(&self, ray: &Ray, _time: f32) -> Option<Vec<Span>> {
+        let half_height = self.height * 0.5;
+        let oc = ray.origin - self.center;
+        let a = ray.direction.x * ray.direction.x + ray.direction.z * ray.direction.z;
+        let c = oc.x * oc.x + oc.z * oc.z - self.radius * self.radius;
+        
+        // Rayo paralelo al eje: adentro en todo el largo o nunca
+        let (side_in, side_out) = if a < 1e-12 {
+            if c > 0.0 {
+                return Some(Vec::new());
+            }
+            (f32::NEG_INFINITY, f32::INFINITY)
+        } else {
+            let b = 2.0 * (oc.x * ray.direction.x + oc.z * ray.direction.z);
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                return Some(Vec::new());
+            }
+            let sqrt_d = discriminant.sqrt();
+            ((-b - sqrt_d) / (2.0 * a), (-b + sqrt_d) / (2.0 * a))
+        };
+        
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let (cap_in, cap_out, in_normal, out_normal) = if ray.direction.y.abs() < 1e-8 {
+            if oc.y.abs() > half_height {
+                return Some(Vec::new());
+            }
+            (f32::NEG_INFINITY, f32::INFINITY, up, up)
+        } else {
+            let t_top = (half_height - oc.y) / ray.direction.y;
+            let t_bottom = (-half_height - oc.y) / ray.direction.y;
+            if ray.direction.y > 0.0 { (t_bottom, t_top, down, up) } else { (t_top, t_bottom, up, down) }
+        };
+        
+        if side_in.max(cap_in) > side_out.min(cap_out) {
+            return Some(Vec::new());
+        }
+        let enter = if cap_in > side_in { self.cap_hit(ray, cap_in, in_normal) } else { self.side_hit(ray, side_in) };
+        let exit = if cap_out < side_out { self.cap_hit(ray, cap_out, out_normal) } else { self.side_hit(ray, side_out) };
+        Some(vec![Span { enter, exit }])
+    }
 }
 
 // Primitiva toro para geometría avanzada
@@ -572,79 +788,266 @@ pub struct Torus {
     pub center: Vec3,
     pub major_radius: f32,
     pub minor_radius: f32,
-    pub material: Material,
+    pub material: MaterialId,
+    pub axis: Vec3, // Eje de revolución (por defecto Y)
 }
 
 impl Torus {
-    pub fn new(center: Vec3, major_radius: f32, minor_radius: f32, material: Material) -> Self {
-        Self { center, major_radius, minor_radius, material }
+    pub fn new(center: Vec3, major_radius: f32, minor_radius: f32, material: MaterialId) -> Self {
+        Self { center, major_radius, minor_radius, material, axis: Vec3::new(0.0, 1.0, 0.0) }
+    }
+    
+    pub fn with_axis(mut self, axis: Vec3) -> Self {
+        self.axis = axis.normalize();
+        self
+    }
+    
+    // Base local (x, eje, z); con el eje Y es la base del mundo
+    fn local_frame(&self) -> (Vec3, Vec3, Vec3) {
+        let axis = self.axis;
+        let helper = if axis.x.abs() < 0.9 { Vec3::new(1.0, 0.0, 0.0) } else { Vec3::new(0.0, 0.0, 1.0) };
+        let u = (helper - axis * axis.dot(helper)).normalize();
+        (u, axis, u.cross(axis))
     }
 }
 
 impl Primitive for Torus {
     fn intersect(&self, ray: &Ray, _time: f32) -> Option<HitInfo> {
-        // Transformar rayo al espacio local del toro
-        let oc = ray.origin - self.center;
+        let (u_axis, axis, w_axis) = self.local_frame();
+        let to_local = |v: Vec3| [v.dot(u_axis) as f64, v.dot(axis) as f64, v.dot(w_axis) as f64];
+        let major = self.major_radius as f64;
+        let minor = self.minor_radius as f64;
         
-        // Intersección de toro es compleja - usando aproximación para rendimiento
-        // Esta es una versión simplificada que funciona razonablemente bien
-        let R = self.major_radius;
-        let r = self.minor_radius;
-        
-        // Coeficientes para ecuación cuártica (simplificada)
-        let ox = oc.x;
-        let oy = oc.y;
-        let oz = oc.z;
-        let dx = ray.direction.x;
-        let dy = ray.direction.y;
-        let dz = ray.direction.z;
-        
-        let _sum_d_sqr = dx*dx + dy*dy + dz*dz;
-        let _e = ox*ox + oy*oy + oz*oz - R*R - r*r;
-        let _f = ox*dx + oy*dy + oz*dz;
-        let _four_a_sqr = 4.0 * R*R;
-        
-        // Resolución cuártica simplificada - usar método iterativo
-        for i in 0..100 {
-            let t = 0.1 + i as f32 * 0.1;
-            let point = ray.position_at(t);
-            let local = point - self.center;
-            
-            let xy_dist = (local.x*local.x + local.z*local.z).sqrt();
-            let torus_center_dist = (xy_dist - R).abs();
-            let torus_surface_dist = (torus_center_dist*torus_center_dist + local.y*local.y).sqrt();
-            
-            if torus_surface_dist <= r && t > 0.001 {
-                // Normal aproximada
-                let torus_center = Vec3::new(
-                    local.x / xy_dist * R,
-                    0.0,
-                    local.z / xy_dist * R
-                );
-                let normal = (local - torus_center).normalize();
-                
-                // Mapeo UV del toro (simplificado)
-                let u = (local.z.atan2(local.x) + std::f32::consts::PI) / (2.0 * std::f32::consts::PI);
-                let v = (local.y.atan2(xy_dist - R) + std::f32::consts::PI) / (2.0 * std::f32::consts::PI);
-                
-                return Some(HitInfo {
-                    t,
-                    point,
-                    normal,
-                    material: self.material.clone(),
-                    uv: (u, v),
-                });
-            }
+        // Descartar con la esfera envolvente y arrancar desde su borde: con el origen
+        // cerca del toro los coeficientes de la cuártica pierden menos precisión
+        let oc = to_local(ray.origin - self.center);
+        let d = to_local(ray.direction);
+        let bound = major + minor;
+        let b = oc[0] * d[0] + oc[1] * d[1] + oc[2] * d[2];
+        let c = oc[0] * oc[0] + oc[1] * oc[1] + oc[2] * oc[2] - bound * bound;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let t_exit = -b + discriminant.sqrt();
+        if t_exit <= 0.001 {
+            return None;
         }
+        let t_start = (-b - discriminant.sqrt()).max(0.0);
+        let o = [oc[0] + d[0] * t_start, oc[1] + d[1] * t_start, oc[2] + d[2] * t_start];
         
-        None
+        // (|p|² + R² - r²)² = 4R²(px² + pz²) con p = o + t·d, |d| = 1
+        let k = o[0] * o[0] + o[1] * o[1] + o[2] * o[2] + major * major - minor * minor;
+        let od = o[0] * d[0] + o[1] * d[1] + o[2] * d[2];
+        let four_r2 = 4.0 * major * major;
+        let coefficients = [
+            k * k - four_r2 * (o[0] * o[0] + o[2] * o[2]),
+            4.0 * od * k - 2.0 * four_r2 * (o[0] * d[0] + o[2] * d[2]),
+            4.0 * od * od + 2.0 * k - four_r2 * (d[0] * d[0] + d[2] * d[2]),
+            4.0 * od,
+        ];
+        
+        let t = solve_quartic(coefficients)
+            .into_iter()
+            .flatten()
+            .map(|t| t + t_start)
+            .filter(|&t| t > 0.001)
+            .fold(f64::INFINITY, f64::min);
+        if !t.is_finite() {
+            return None;
+        }
+        let t = t as f32;
+        
+        let point = ray.position_at(t);
+        let local = point - self.center;
+        let (lx, ly, lz) = (local.dot(u_axis), local.dot(axis), local.dot(w_axis));
+        let ring_dist = (lx * lx + lz * lz).sqrt().max(1e-6);
+        // La normal apunta desde el círculo central del tubo hacia el punto
+        let ring_point = u_axis * (lx / ring_dist * self.major_radius) + w_axis * (lz / ring_dist * self.major_radius);
+        let normal = (local - ring_point).normalize();
+        
+        // u alrededor del eje, v alrededor del tubo
+        let u = (lz.atan2(lx) + std::f32::consts::PI) / (2.0 * std::f32::consts::PI);
+        let v = (ly.atan2(ring_dist - self.major_radius) + std::f32::consts::PI) / (2.0 * std::f32::consts::PI);
+        
+        Some(HitInfo {
+            t,
+            point,
+            normal,
+            material: self.material,
+            uv: (u, v),
+            emitter: Emitter::Unsampled,
+        })
     }
     
-    fn get_bounds(&self) -> (Vec3, Vec3) {
-        let outer_radius = self.major_radius + self.minor_radius;
-        (
-            self.center - Vec3::new(outer_radius, self.minor_radius, outer_radius),
-            self.center + Vec3::new(outer_radius, self.minor_radius, outer_radius)
-        )
+    fn get_bounds(&self, _time: f32) -> (Vec3, Vec3) {
+        // El anillo de radio R ocupa R·sqrt(1 - n_i²) en cada eje, más el radio del tubo
+        let extent = |n: f32| self.major_radius * (1.0 - n * n).max(0.0).sqrt() + self.minor_radius;
+        let half = Vec3::new(extent(self.axis.x), extent(self.axis.y), extent(self.axis.z));
+        (self.center - half, self.center + half)
+    }
+}
+
+// Raíces reales de t⁴ + c[3]·t³ + c[2]·t² + c[1]·t + c[0] por el método de Ferrari,
+// refinadas con Newton sobre la ecuación original
+fn solve_quartic(c: [f64; 4]) -> [Option<f64>; 4] {
+    let [c0, c1, c2, c3] = c;
+    // Cambio t = y - c3/4 para quitar el término cúbico: y⁴ + p·y² + q·y + r = 0
+    let shift = c3 / 4.0;
+    let p = c2 - 6.0 * shift * shift;
+    let q = c1 - 2.0 * c2 * shift + 8.0 * shift * shift * shift;
+    let r = c0 - c1 * shift + c2 * shift * shift - 3.0 * shift * shift * shift * shift;
+    
+    let mut roots = [None; 4];
+    if q.abs() < 1e-12 {
+        // Bicuadrática: y² = z
+        for (i, z) in solve_quadratic(p, r).into_iter().enumerate() {
+            if let Some(z) = z.filter(|&z| z >= 0.0) {
+                roots[2 * i] = Some(z.sqrt());
+                roots[2 * i + 1] = Some(-z.sqrt());
+            }
+        }
+    } else {
+        // Cúbica resolvente: con su raíz positiva m la cuártica se parte en dos cuadráticas
+        let m = largest_cubic_root(p, p * p / 4.0 - r, -q * q / 8.0);
+        if m <= 0.0 {
+            return roots;
+        }
+        let s = (2.0 * m).sqrt();
+        let offset = s * q / (4.0 * m);
+        let [a, b] = solve_quadratic(-s, p / 2.0 + m + offset);
+        let [e, f] = solve_quadratic(s, p / 2.0 + m - offset);
+        roots = [a, b, e, f];
+    }
+    
+    let polynomial = |t: f64| (((t + c3) * t + c2) * t + c1) * t + c0;
+    let derivative = |t: f64| ((4.0 * t + 3.0 * c3) * t + 2.0 * c2) * t + c1;
+    roots.map(|root| {
+        root.map(|y| {
+            let mut t = y - shift;
+            for _ in 0..2 {
+                let slope = derivative(t);
+                if slope.abs() > 1e-12 {
+                    t -= polynomial(t) / slope;
+                }
+            }
+            t
+        })
+    })
+}
+
+// Raíces reales de y² + b·y + c = 0
+fn solve_quadratic(b: f64, c: f64) -> [Option<f64>; 2] {
+    let discriminant = b * b - 4.0 * c;
+    if discriminant < 0.0 {
+        return [None, None];
+    }
+    // Forma estable: evita restar dos números casi iguales
+    let sqrt_d = discriminant.sqrt();
+    let q = -0.5 * (b + b.signum() * sqrt_d);
+    if q == 0.0 {
+        return [Some(0.0), Some(0.0)];
+    }
+    [Some(q), Some(c / q)]
+}
+
+// Mayor raíz real de m³ + a·m² + b·m + c = 0 (Cardano o forma trigonométrica)
+fn largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    let q = (a * a - 3.0 * b) / 9.0;
+    let r = (2.0 * a * a * a - 9.0 * a * b + 27.0 * c) / 54.0;
+    let mut m = if r * r < q * q * q {
+        let theta = (r / (q * q * q).sqrt()).clamp(-1.0, 1.0).acos();
+        -2.0 * q.sqrt() * (theta / 3.0).cos() - a / 3.0
+    } else {
+        let big = -r.signum() * (r.abs() + (r * r - q * q * q).sqrt()).cbrt();
+        let small = if big != 0.0 { q / big } else { 0.0 };
+        big + small - a / 3.0
+    };
+    for _ in 0..2 {
+        let slope = (3.0 * m + 2.0 * a) * m + b;
+        if slope.abs() > 1e-12 {
+            m -= (((m + a) * m + b) * m + c) / slope;
+        }
+    }
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Coeficientes [c0, c1, c2, c3] del polinomio mónico con esas cuatro raíces
+    fn quartic_with_roots(roots: [f64; 4]) -> [f64; 4] {
+        let mut c = [1.0, 0.0, 0.0, 0.0, 0.0]; // c[i] acompaña a t^i
+        for root in roots {
+            for i in (0..4).rev() {
+                c[i + 1] = c[i];
+            }
+            c[0] = 0.0;
+            for i in 0..4 {
+                c[i] -= root * c[i + 1];
+            }
+        }
+        [c[0], c[1], c[2], c[3]]
+    }
+
+    fn real_roots(c: [f64; 4]) -> Vec<f64> {
+        let mut roots: Vec<f64> = solve_quartic(c).into_iter().flatten().collect();
+        roots.sort_by(f64::total_cmp);
+        roots
+    }
+
+    fn assert_roots(c: [f64; 4], expected: &[f64], tolerance: f64) {
+        let roots = real_roots(c);
+        assert_eq!(roots.len(), expected.len(), "raíces {:?}, se esperaban {:?}", roots, expected);
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!((root - expected).abs() < tolerance, "raíces {:?}, se esperaban {:?}", roots, expected);
+        }
+    }
+
+    #[test]
+    fn quartic_distinct_roots() {
+        assert_roots(quartic_with_roots([1.0, 2.0, 3.0, 4.0]), &[1.0, 2.0, 3.0, 4.0], 1e-9);
+        assert_roots(quartic_with_roots([-7.5, -0.25, 0.5, 12.0]), &[-7.5, -0.25, 0.5, 12.0], 1e-9);
+    }
+
+    #[test]
+    fn quartic_biquadratic_roots() {
+        // Sin término cúbico ni lineal: (t² - 1)(t² - 4)
+        assert_roots([4.0, 0.0, -5.0, 0.0], &[-2.0, -1.0, 1.0, 2.0], 1e-9);
+    }
+
+    #[test]
+    fn quartic_two_real_roots() {
+        // (t - 1)(t - 3)(t² + 1)
+        assert_roots([3.0, -4.0, 4.0, -4.0], &[1.0, 3.0], 1e-9);
+    }
+
+    #[test]
+    fn quartic_tangent_roots() {
+        // Raíz doble en 2, como un rayo que roza el toro
+        assert_roots(quartic_with_roots([2.0, 2.0, 5.0, 6.0]), &[2.0, 2.0, 5.0, 6.0], 1e-6);
+        // Dos raíces dobles: (t - 1)²(t - 3)²
+        assert_roots(quartic_with_roots([1.0, 1.0, 3.0, 3.0]), &[1.0, 1.0, 3.0, 3.0], 1e-6);
+    }
+
+    #[test]
+    fn quartic_without_real_roots() {
+        // (t² + 1)(t² + 4) y (t² + 1)((t - 2)² + 1)
+        assert!(real_roots([4.0, 0.0, 5.0, 0.0]).is_empty());
+        assert!(real_roots([5.0, -4.0, 6.0, -4.0]).is_empty());
+    }
+
+    #[test]
+    fn torus_hits_nearest_surface() {
+        let torus = Torus::new(Vec3::zero(), 2.0, 0.5, 0);
+        // Atraviesa el anillo por el plano del toro: entra a x = -2.5
+        let hit = torus.intersect(&Ray::new(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)), 0.0).unwrap();
+        assert!((hit.t - 7.5).abs() < 1e-4, "t = {}", hit.t);
+        assert!((hit.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-4);
+        // Por el agujero del centro, a lo largo del eje, no toca nada
+        assert!(torus.intersect(&Ray::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0)), 0.0).is_none());
+        // Por encima del tubo tampoco
+        assert!(torus.intersect(&Ray::new(Vec3::new(-10.0, 0.6, 0.0), Vec3::new(1.0, 0.0, 0.0)), 0.0).is_none());
     }
 }
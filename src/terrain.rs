@@ -0,0 +1,233 @@
+use crate::blocks::BlockRegistry;
+use crate::geometry::{noise, Vec3};
+use crate::voxel::VoxelWorld;
+
+// Terreno del Nether a partir de una semilla: suelo y techo de netherrack tallados por
+// cavernas de ruido 3D, un mar de lava a altura fija, racimos de piedra luminosa colgando
+// del techo y deltas de basalto. La misma semilla y región dan siempre los mismos bloques.
+// La región pedida se toma como el alto completo del Nether, del fondo al techo.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TerrainBlock {
+    Netherrack,
+    Lava,
+    Glowstone,
+    Basalt,
+}
+
+impl TerrainBlock {
+    pub fn name(self) -> &'static str {
+        match self {
+            TerrainBlock::Netherrack => "minecraft:netherrack",
+            TerrainBlock::Lava => "minecraft:lava",
+            TerrainBlock::Glowstone => "minecraft:glowstone",
+            TerrainBlock::Basalt => "minecraft:basalt",
+        }
+    }
+}
+
+// Tamaño de las cavernas en bloques y cuánto tallan el suelo y el techo
+const CAVE_SCALE: f32 = 14.0;
+const CAVE_STRENGTH: f32 = 1.0;
+// Bloques en los que el suelo y el techo pasan de macizos a abiertos
+const SHELL_FALLOFF: f32 = 4.0;
+// Umbrales del ruido para deltas de basalto (una quinta parte del suelo) y para que
+// nazca un racimo de piedra luminosa en el techo
+const DELTA_THRESHOLD: f32 = 0.15;
+const GLOWSTONE_THRESHOLD: f32 = 0.36;
+
+pub struct TerrainGenerator {
+    pub seed: u32,
+    // Altura del mar de lava; sin valor, al 30% de la región desde abajo
+    pub lava_level: Option<i32>,
+}
+
+impl TerrainGenerator {
+    pub fn new(seed: u32) -> Self {
+        TerrainGenerator { seed, lava_level: None }
+    }
+
+    pub fn with_lava_level(mut self, level: i32) -> Self {
+        self.lava_level = Some(level);
+        self
+    }
+
+    // Número en [0, 1) fijo para cada semilla y celda
+    fn random(&self, x: i32, y: i32, z: i32) -> f32 {
+        let mut h = self.seed
+            ^ (x as u32).wrapping_mul(0x8da6_b343)
+            ^ (y as u32).wrapping_mul(0xd816_3841)
+            ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x7feb_352d);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x846c_a68b);
+        h ^= h >> 16;
+        (h >> 8) as f32 / (1 << 24) as f32
+    }
+
+    // Desplazamiento del ruido para cada capa: distintas semillas muestrean zonas distintas
+    fn offset(&self, layer: i32) -> Vec3 {
+        Vec3::new(self.random(layer, 0, 0), self.random(layer, 1, 0), self.random(layer, 2, 0)) * 1000.0
+    }
+
+    // Bloques de la región de `size` bloques con esquina mínima `min`; el resto es aire
+    pub fn generate(&self, min: (i32, i32, i32), size: (i32, i32, i32)) -> Vec<((i32, i32, i32), TerrainBlock)> {
+        let (width, height, depth) = (size.0.max(0), size.1.max(0), size.2.max(0));
+        let lava_level = self.lava_level.unwrap_or(min.1 + (height as f32 * 0.3) as i32);
+        let [floor_offset, ceiling_offset, cave_offset, delta_offset, cluster_offset] = [0, 1, 2, 3, 4].map(|layer| self.offset(layer));
+        let bottom = min.1 as f32;
+        let top = (min.1 + height - 1) as f32;
+
+        let mut placements = Vec::new();
+        let mut column: Vec<Option<TerrainBlock>> = vec![None; height as usize];
+        for x in min.0..min.0 + width {
+            for z in min.2..min.2 + depth {
+                let flat = Vec3::new(x as f32, 0.0, z as f32);
+                let floor_top = bottom + height as f32 * (0.28 + 0.14 * relief(fbm(flat / 24.0 + floor_offset, 3)));
+                let ceiling_bottom = top - height as f32 * (0.22 + 0.1 * relief(fbm(flat / 20.0 + ceiling_offset, 3)));
+                let middle = (floor_top + ceiling_bottom) * 0.5;
+
+                // Roca donde domina el suelo o el techo, con cavernas que la tallan;
+                // los huecos bajo el nivel del mar quedan llenos de lava
+                for (cell, y) in column.iter_mut().zip(min.1..) {
+                    let fy = y as f32;
+                    let shell = (floor_top - fy).max(fy - ceiling_bottom) / SHELL_FALLOFF;
+                    let cave = relief(fbm(Vec3::new(x as f32, fy * 1.5, z as f32) / CAVE_SCALE + cave_offset, 3));
+                    *cell = if shell + cave * CAVE_STRENGTH > 0.0 {
+                        Some(TerrainBlock::Netherrack)
+                    } else if y <= lava_level {
+                        Some(TerrainBlock::Lava)
+                    } else {
+                        None
+                    };
+                }
+
+                // Piedra luminosa: de arriba hacia abajo, cada bloque cuelga del de encima
+                for i in (0..column.len().saturating_sub(1)).rev() {
+                    let y = min.1 + i as i32;
+                    if column[i].is_some() || (y as f32) < middle {
+                        continue;
+                    }
+                    let cluster = fbm(Vec3::new(x as f32, y as f32, z as f32) / 5.0 + cluster_offset, 2);
+                    let grows = match column[i + 1] {
+                        Some(TerrainBlock::Netherrack) => cluster > GLOWSTONE_THRESHOLD,
+                        Some(TerrainBlock::Glowstone) => cluster > GLOWSTONE_THRESHOLD - 0.06 && self.random(x, y, z) < 0.6,
+                        _ => false,
+                    };
+                    if grows {
+                        column[i] = Some(TerrainBlock::Glowstone);
+                    }
+                }
+
+                // Deltas: el suelo expuesto se vuelve basalto, con columnas que asoman
+                if fbm(flat / 32.0 + delta_offset, 2) > DELTA_THRESHOLD {
+                    for i in 0..column.len().saturating_sub(1) {
+                        let y = min.1 + i as i32;
+                        if (y as f32) > middle || column[i] != Some(TerrainBlock::Netherrack) || column[i + 1].is_some() {
+                            continue;
+                        }
+                        for cell in column[i.saturating_sub(2)..=i].iter_mut() {
+                            if *cell == Some(TerrainBlock::Netherrack) {
+                                *cell = Some(TerrainBlock::Basalt);
+                            }
+                        }
+                        let roll = self.random(x, y, z);
+                        if roll < 0.12 {
+                            let pillar = 1 + (roll * 40.0) as usize;
+                            for cell in column.iter_mut().skip(i + 1).take(pillar) {
+                                if cell.is_some() {
+                                    break;
+                                }
+                                *cell = Some(TerrainBlock::Basalt);
+                            }
+                        }
+                    }
+                }
+
+                for (cell, y) in column.iter().zip(min.1..) {
+                    if let Some(block) = cell {
+                        placements.push(((x, y, z), *block));
+                    }
+                }
+            }
+        }
+        placements
+    }
+
+    // Genera la región dentro de la grilla; devuelve la cantidad de bloques colocados
+    pub fn place(&self, world: &mut VoxelWorld, registry: &mut BlockRegistry, min: (i32, i32, i32), size: (i32, i32, i32)) -> usize {
+        let placements = self.generate(min, size);
+        let ids = [TerrainBlock::Netherrack, TerrainBlock::Lava, TerrainBlock::Glowstone, TerrainBlock::Basalt]
+            .map(|block| (block, registry.id_for(world, block.name())));
+        for ((x, y, z), block) in &placements {
+            if let Some((_, id)) = ids.iter().find(|(b, _)| b == block) {
+                world.set_block(*x, *y, *z, *id);
+            }
+        }
+        placements.len()
+    }
+}
+
+// El ruido fractal rara vez pasa de ±0.3: se estira a [-1, 1]
+fn relief(value: f32) -> f32 {
+    (value * 3.5).clamp(-1.0, 1.0)
+}
+
+// Ruido fractal en [-1, 1]: octavas que duplican la frecuencia y reducen a la mitad la amplitud
+fn fbm(p: Vec3, octaves: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut total = 0.0;
+    let mut point = p;
+    for _ in 0..octaves {
+        sum += noise(point) * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        point = point * 2.0;
+    }
+    sum / total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN: (i32, i32, i32) = (-8, -20, -8);
+    const SIZE: (i32, i32, i32) = (16, 40, 16);
+
+    #[test]
+    fn same_seed_gives_same_blocks() {
+        let placements = TerrainGenerator::new(42).generate(MIN, SIZE);
+        assert!(!placements.is_empty());
+        assert_eq!(TerrainGenerator::new(42).generate(MIN, SIZE), placements);
+        for &((x, y, z), _) in &placements {
+            assert!((MIN.0..MIN.0 + SIZE.0).contains(&x) && (MIN.1..MIN.1 + SIZE.1).contains(&y) && (MIN.2..MIN.2 + SIZE.2).contains(&z));
+        }
+    }
+
+    #[test]
+    fn different_seeds_differ() {
+        let a = TerrainGenerator::new(1).generate(MIN, SIZE);
+        let b = TerrainGenerator::new(2).generate(MIN, SIZE);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn columns_do_not_depend_on_the_region_width() {
+        // Con el mismo alto, cada columna sale igual aunque la región sea más angosta
+        let wide = TerrainGenerator::new(7).generate(MIN, SIZE);
+        let narrow = TerrainGenerator::new(7).generate((0, MIN.1, MIN.2), (4, SIZE.1, SIZE.2));
+        let inside: Vec<_> = wide.into_iter().filter(|((x, _, _), _)| (0..4).contains(x)).collect();
+        assert!(!narrow.is_empty());
+        assert_eq!(narrow, inside);
+    }
+
+    #[test]
+    fn empty_regions_have_no_blocks() {
+        let generator = TerrainGenerator::new(42);
+        for size in [(0, 40, 16), (16, 0, 16), (16, 40, 0), (-4, 40, 16), (16, -1, 16), (-3, -3, -3)] {
+            assert!(generator.generate(MIN, size).is_empty(), "tamaño {:?}", size);
+        }
+    }
+}
@@ -0,0 +1,551 @@
+use std::collections::HashMap;
+
+use crate::area_lights::{face_uvs, AreaLight};
+use crate::geometry::*;
+use crate::materials::{MaterialId, MaterialRegistry};
+use crate::raytracer::{Emitter, HitInfo};
+use crate::shapes::Primitive;
+
+pub type BlockId = u16;
+pub const AIR: BlockId = 0;
+
+pub const CHUNK_SIZE: i32 = 16;
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+// Un chunk de 16x16x16 bloques. Los chunks de un solo tipo de bloque (aire, piedra)
+// no guardan arreglo.
+enum Chunk {
+    Uniform(BlockId),
+    Dense { blocks: Box<[BlockId]>, solid: u32 },
+}
+
+impl Chunk {
+    fn get(&self, index: usize) -> BlockId {
+        match self {
+            Chunk::Uniform(id) => *id,
+            Chunk::Dense { blocks, .. } => blocks[index],
+        }
+    }
+
+    fn set(&mut self, index: usize, id: BlockId) {
+        if let Chunk::Uniform(current) = self {
+            if *current == id { return; }
+            let solid = if *current == AIR { 0 } else { CHUNK_VOLUME as u32 };
+            *self = Chunk::Dense { blocks: vec![*current; CHUNK_VOLUME].into_boxed_slice(), solid };
+        }
+        if let Chunk::Dense { blocks, solid } = self {
+            let old = blocks[index];
+            if old == AIR && id != AIR { *solid += 1; }
+            if old != AIR && id == AIR { *solid -= 1; }
+            blocks[index] = id;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Chunk::Uniform(id) => *id == AIR,
+            Chunk::Dense { solid, .. } => *solid == 0,
+        }
+    }
+}
+
+// Mundo de bloques estilo Minecraft: grilla 3D de IDs de bloque agrupada en chunks,
+// recorrida con DDA (Amanatides-Woo) en vez de un cubo por bloque en el BVH.
+// El bloque (x, y, z) ocupa [origin + (x,y,z), origin + (x,y,z) + 1].
+pub struct VoxelWorld {
+    pub origin: Vec3,
+    palette: Vec<MaterialId>, // palette[id]; la entrada 0 es el aire y no se usa
+    chunks: HashMap<(i32, i32, i32), Chunk>,
+    min_block: [i32; 3],
+    max_block: [i32; 3],
+}
+
+impl VoxelWorld {
+    pub fn new(origin: Vec3) -> Self {
+        VoxelWorld {
+            origin,
+            palette: vec![0],
+            chunks: HashMap::new(),
+            min_block: [i32::MAX; 3],
+            max_block: [i32::MIN; 3],
+        }
+    }
+
+    pub fn add_block_type(&mut self, material: MaterialId) -> BlockId {
+        self.palette.push(material);
+        (self.palette.len() - 1) as BlockId
+    }
+
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, id: BlockId) {
+        debug_assert!((id as usize) < self.palette.len(), "bloque sin registrar");
+        let (key, index) = chunk_coords(x, y, z);
+        if id == AIR && !self.chunks.contains_key(&key) { return; }
+        self.chunks.entry(key).or_insert(Chunk::Uniform(AIR)).set(index, id);
+        if id != AIR {
+            for (axis, v) in [x, y, z].into_iter().enumerate() {
+                self.min_block[axis] = self.min_block[axis].min(v);
+                self.max_block[axis] = self.max_block[axis].max(v);
+            }
+        }
+    }
+
+    // Reemplaza un chunk completo de una vez; `blocks` va en orden (y, z, x), igual que
+    // las secciones de los mundos Anvil
+    pub fn set_chunk(&mut self, key: (i32, i32, i32), blocks: Vec<BlockId>) {
+        assert_eq!(blocks.len(), CHUNK_VOLUME, "un chunk tiene 16x16x16 bloques");
+        let base = [key.0 * CHUNK_SIZE, key.1 * CHUNK_SIZE, key.2 * CHUNK_SIZE];
+        let mut solid = 0;
+        for (index, &id) in blocks.iter().enumerate() {
+            if id == AIR { continue; }
+            solid += 1;
+            let index = index as i32;
+            let local = [index % CHUNK_SIZE, index / (CHUNK_SIZE * CHUNK_SIZE), (index / CHUNK_SIZE) % CHUNK_SIZE];
+            for axis in 0..3 {
+                self.min_block[axis] = self.min_block[axis].min(base[axis] + local[axis]);
+                self.max_block[axis] = self.max_block[axis].max(base[axis] + local[axis]);
+            }
+        }
+        let chunk = if blocks.iter().all(|&id| id == blocks[0]) {
+            Chunk::Uniform(blocks[0])
+        } else {
+            Chunk::Dense { blocks: blocks.into_boxed_slice(), solid }
+        };
+        self.chunks.insert(key, chunk);
+    }
+
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> BlockId {
+        let (key, index) = chunk_coords(x, y, z);
+        self.chunks.get(&key).map(|c| c.get(index)).unwrap_or(AIR)
+    }
+
+    // Altura del bloque más alto de la columna (x, z), si hay alguno
+    pub fn top_block(&self, x: i32, z: i32) -> Option<i32> {
+        if self.is_empty() { return None; }
+        (self.min_block[1]..=self.max_block[1]).rev().find(|&y| self.get_block(x, y, z) != AIR)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min_block[0] > self.max_block[0]
+    }
+
+    fn chunk_is_empty(&self, key: (i32, i32, i32)) -> bool {
+        self.chunks.get(&key).map(|c| c.is_empty()).unwrap_or(true)
+    }
+
+    fn block_hit(&self, ray: &Ray, t: f32, cell: [i32; 3], normal: Vec3, id: BlockId) -> HitInfo {
+        let point = ray.position_at(t);
+        // Mapeo UV por cara igual que Cube: coordenadas locales dentro del bloque
+        let local = point - self.origin - Vec3::new(cell[0] as f32, cell[1] as f32, cell[2] as f32);
+        let uv = if normal.x.abs() > 0.5 {
+            (local.z, local.y)
+        } else if normal.y.abs() > 0.5 {
+            (local.x, local.z)
+        } else {
+            (local.x, local.y)
+        };
+        HitInfo {
+            t,
+            point,
+            normal,
+            material: self.palette[id as usize],
+            uv: (uv.0.clamp(0.0, 1.0), uv.1.clamp(0.0, 1.0)),
+            emitter: Emitter::Surface,
+        }
+    }
+
+}
+
+// Cara de salida del bloque `medium` al pasar a la celda `cell` por `axis`;
+// la normal apunta hacia afuera del bloque que dejamos
+fn exit_face(t: f32, cell: [i32; 3], axis: usize, step: i32, medium: BlockId) -> (f32, [i32; 3], Vec3, BlockId) {
+    let mut prev = cell;
+    prev[axis] -= step;
+    (t, prev, axis_normal(axis, step as f32), medium)
+}
+
+fn chunk_coords(x: i32, y: i32, z: i32) -> ((i32, i32, i32), usize) {
+    let key = (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE), z.div_euclid(CHUNK_SIZE));
+    let (lx, ly, lz) = (x.rem_euclid(CHUNK_SIZE), y.rem_euclid(CHUNK_SIZE), z.rem_euclid(CHUNK_SIZE));
+    (key, ((ly * CHUNK_SIZE + lz) * CHUNK_SIZE + lx) as usize)
+}
+
+fn axis_normal(axis: usize, sign: f32) -> Vec3 {
+    match axis {
+        0 => Vec3::new(sign, 0.0, 0.0),
+        1 => Vec3::new(0.0, sign, 0.0),
+        _ => Vec3::new(0.0, 0.0, sign),
+    }
+}
+
+// Estado del recorrido DDA en coordenadas de grilla
+struct Dda {
+    cell: [i32; 3],
+    step: [i32; 3],
+    t_max: [f32; 3],
+    t_delta: [f32; 3],
+}
+
+impl Dda {
+    fn new(origin: [f32; 3], dir: [f32; 3], cell: [i32; 3]) -> Self {
+        let mut dda = Dda { cell, step: [0; 3], t_max: [f32::INFINITY; 3], t_delta: [f32::INFINITY; 3] };
+        for (axis, &d) in dir.iter().enumerate() {
+            if d > 0.0 {
+                dda.step[axis] = 1;
+                dda.t_delta[axis] = 1.0 / d;
+            } else if d < 0.0 {
+                dda.step[axis] = -1;
+                dda.t_delta[axis] = -1.0 / d;
+            }
+        }
+        dda.reset_t_max(origin, dir);
+        dda
+    }
+
+    fn reset_t_max(&mut self, origin: [f32; 3], dir: [f32; 3]) {
+        for axis in 0..3 {
+            self.t_max[axis] = match self.step[axis] {
+                1 => (self.cell[axis] as f32 + 1.0 - origin[axis]) / dir[axis],
+                -1 => (self.cell[axis] as f32 - origin[axis]) / dir[axis],
+                _ => f32::INFINITY,
+            };
+        }
+    }
+
+    // Avanza a la celda vecina; devuelve el eje cruzado y el t del cruce
+    fn advance(&mut self) -> (usize, f32) {
+        let axis = if self.t_max[0] < self.t_max[1] {
+            if self.t_max[0] < self.t_max[2] { 0 } else { 2 }
+        } else if self.t_max[1] < self.t_max[2] { 1 } else { 2 };
+        let t = self.t_max[axis];
+        self.cell[axis] += self.step[axis];
+        self.t_max[axis] += self.t_delta[axis];
+        (axis, t)
+    }
+}
+
+impl VoxelWorld {
+    // Recorre la grilla hasta la primera cara antes de `max_t`: distancia, celda del bloque
+    // golpeado, normal de la cara y bloque; el HitInfo se arma solo si hace falta
+    fn march(&self, ray: &Ray, max_t: f32) -> Option<(f32, [i32; 3], Vec3, BlockId)> {
+        if self.is_empty() { return None; }
+
+        let o = ray.origin - self.origin;
+        let origin = [o.x, o.y, o.z];
+        let dir = [ray.direction.x, ray.direction.y, ray.direction.z];
+
+        // Recortar el rayo contra la caja del mundo
+        let mut t_enter = 0.0f32;
+        let mut t_exit = f32::INFINITY;
+        let mut enter_axis = None;
+        for axis in 0..3 {
+            let lo = self.min_block[axis] as f32;
+            let hi = self.max_block[axis] as f32 + 1.0;
+            if dir[axis].abs() < 1e-12 {
+                if origin[axis] < lo || origin[axis] > hi { return None; }
+                continue;
+            }
+            let (mut t0, mut t1) = ((lo - origin[axis]) / dir[axis], (hi - origin[axis]) / dir[axis]);
+            if t0 > t1 { std::mem::swap(&mut t0, &mut t1); }
+            if t0 > t_enter { t_enter = t0; enter_axis = Some(axis); }
+            t_exit = t_exit.min(t1);
+        }
+        if t_enter > t_exit.min(max_t) { return None; }
+
+        let start = [
+            origin[0] + dir[0] * t_enter,
+            origin[1] + dir[1] * t_enter,
+            origin[2] + dir[2] * t_enter,
+        ];
+        let mut cell = [0i32; 3];
+        for axis in 0..3 {
+            // Al entrar por una cara, la celda inicial es la de adentro aunque el redondeo diga otra cosa
+            cell[axis] = (start[axis].floor() as i32).clamp(self.min_block[axis], self.max_block[axis]);
+        }
+        let mut dda = Dda::new(origin, dir, cell);
+
+        // Si el rayo nace dentro de un bloque (refracción en portal o vidrio), ese bloque
+        // es el medio actual: se atraviesan los bloques iguales y se reporta la cara de salida
+        let medium = if enter_axis.is_none() { self.get_block(cell[0], cell[1], cell[2]) } else { AIR };
+
+        // Eje y t por donde el rayo entró a la celda actual (None si nace en ella)
+        let mut entered = enter_axis.map(|axis| (axis, t_enter));
+        loop {
+            if entered.is_some_and(|(_, t)| t >= max_t) {
+                return None;
+            }
+            let c = dda.cell;
+            if (0..3).any(|a| c[a] < self.min_block[a] || c[a] > self.max_block[a]) {
+                // Salida del mundo estando dentro de un bloque
+                if medium != AIR && let Some((axis, t)) = entered.filter(|&(_, t)| t > 0.001) {
+                    return Some(exit_face(t, c, axis, dda.step[axis], medium));
+                }
+                return None;
+            }
+
+            let (key, _) = chunk_coords(c[0], c[1], c[2]);
+            if medium == AIR && self.chunk_is_empty(key) {
+                // Saltar el chunk vacío completo y reiniciar el DDA en la celda de salida
+                entered = Some(skip_chunk(&mut dda, key, origin, dir)?);
+                continue;
+            }
+
+            let id = self.get_block(c[0], c[1], c[2]);
+            if let Some((axis, t)) = entered && id != medium && t > 0.001 {
+                if id == AIR {
+                    return Some(exit_face(t, c, axis, dda.step[axis], medium));
+                }
+                return Some((t, c, axis_normal(axis, -(dda.step[axis] as f32)), id));
+            }
+            entered = Some(dda.advance());
+        }
+    }
+}
+
+impl Primitive for VoxelWorld {
+    fn intersect(&self, ray: &Ray, _time: f32) -> Option<HitInfo> {
+        let (t, cell, normal, id) = self.march(ray, f32::INFINITY)?;
+        Some(self.block_hit(ray, t, cell, normal, id))
+    }
+
+    fn occluded(&self, ray: &Ray, max_t: f32, _time: f32) -> bool {
+        self.march(ray, max_t).is_some_and(|(t, ..)| t < max_t)
+    }
+
+    fn get_bounds(&self, _time: f32) -> (Vec3, Vec3) {
+        if self.is_empty() {
+            return (self.origin, self.origin);
+        }
+        let min = Vec3::new(self.min_block[0] as f32, self.min_block[1] as f32, self.min_block[2] as f32);
+        let max = Vec3::new(self.max_block[0] as f32, self.max_block[1] as f32, self.max_block[2] as f32)
+            + Vec3::new(1.0, 1.0, 1.0);
+        (self.origin + min, self.origin + max)
+    }
+
+    // Cada cara visible de un bloque emisivo (hacia aire o hacia un bloque transparente) es una
+    // luz; las caras entre bloques iguales o tapadas por bloques opacos nunca se ven
+    fn area_lights(&self, materials: &MaterialRegistry, out: &mut Vec<AreaLight>) {
+        let emissive: Vec<bool> = self.palette.iter().enumerate()
+            .map(|(id, &material)| id != AIR as usize && materials[material].is_emissive())
+            .collect();
+        if !emissive.contains(&true) { return; }
+        let shows_face = |neighbour: BlockId, id: BlockId| {
+            neighbour == AIR || (neighbour != id && materials[self.palette[neighbour as usize]].is_transparent())
+        };
+
+        // Chunks en orden fijo: el orden de las luces decide qué número al azar elige cada una
+        let mut keys: Vec<_> = self.chunks.keys().copied().collect();
+        keys.sort_unstable();
+        for key in keys {
+            let chunk = &self.chunks[&key];
+            if let Chunk::Uniform(id) = chunk && !emissive[*id as usize] { continue; }
+            let base = [key.0 * CHUNK_SIZE, key.1 * CHUNK_SIZE, key.2 * CHUNK_SIZE];
+            for index in 0..CHUNK_VOLUME {
+                let id = chunk.get(index);
+                if !emissive[id as usize] { continue; }
+                let index = index as i32;
+                let cell = [
+                    base[0] + index % CHUNK_SIZE,
+                    base[1] + index / (CHUNK_SIZE * CHUNK_SIZE),
+                    base[2] + (index / CHUNK_SIZE) % CHUNK_SIZE,
+                ];
+                let block_min = self.origin + Vec3::new(cell[0] as f32, cell[1] as f32, cell[2] as f32);
+                for axis in 0..3 {
+                    let (u, v) = (axis_normal((axis + 1) % 3, 1.0), axis_normal((axis + 2) % 3, 1.0));
+                    for step in [-1, 1] {
+                        let mut neighbour = cell;
+                        neighbour[axis] += step;
+                        if !shows_face(self.get_block(neighbour[0], neighbour[1], neighbour[2]), id) { continue; }
+                        let corner = if step > 0 { block_min + axis_normal(axis, 1.0) } else { block_min };
+                        out.push(AreaLight::quad(corner, u, v, self.palette[id as usize]).with_uvs(face_uvs(axis)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Mueve el DDA directo a la primera celda fuera del chunk `key`
+fn skip_chunk(dda: &mut Dda, key: (i32, i32, i32), origin: [f32; 3], dir: [f32; 3]) -> Option<(usize, f32)> {
+    let chunk_min = [key.0 * CHUNK_SIZE, key.1 * CHUNK_SIZE, key.2 * CHUNK_SIZE];
+
+    // Pasos restantes hasta el borde del chunk en cada eje
+    let mut best_axis = 0;
+    let mut best_t = f32::INFINITY;
+    for (axis, &lo) in chunk_min.iter().enumerate() {
+        let steps_left = match dda.step[axis] {
+            1 => lo + CHUNK_SIZE - 1 - dda.cell[axis],
+            -1 => dda.cell[axis] - lo,
+            _ => continue,
+        };
+        let t = dda.t_max[axis] + dda.t_delta[axis] * steps_left as f32;
+        if t < best_t {
+            best_t = t;
+            best_axis = axis;
+        }
+    }
+    if best_t == f32::INFINITY { return None; }
+
+    for (axis, &lo) in chunk_min.iter().enumerate() {
+        dda.cell[axis] = if axis == best_axis {
+            if dda.step[axis] > 0 { lo + CHUNK_SIZE } else { lo - 1 }
+        } else {
+            let p = origin[axis] + dir[axis] * best_t;
+            (p.floor() as i32).clamp(lo, lo + CHUNK_SIZE - 1)
+        };
+    }
+    dda.reset_t_max(origin, dir);
+    Some((best_axis, best_t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generador fijo para que las pruebas sean reproducibles
+    fn lcg(state: &mut u32) -> f32 {
+        *state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+        (*state >> 8) as f32 / (1 << 24) as f32
+    }
+
+    // Primer corte del rayo con alguno de los bloques, probando cada uno como caja
+    fn brute_force(blocks: &[[i32; 3]], ray: &Ray) -> Option<f32> {
+        let origin = [ray.origin.x, ray.origin.y, ray.origin.z];
+        let dir = [ray.direction.x, ray.direction.y, ray.direction.z];
+        blocks.iter().filter_map(|block| {
+            let (mut t_enter, mut t_exit) = (f32::NEG_INFINITY, f32::INFINITY);
+            for axis in 0..3 {
+                let (lo, hi) = (block[axis] as f32, block[axis] as f32 + 1.0);
+                if dir[axis] == 0.0 {
+                    if origin[axis] < lo || origin[axis] > hi { return None; }
+                    continue;
+                }
+                let (t0, t1) = ((lo - origin[axis]) / dir[axis], (hi - origin[axis]) / dir[axis]);
+                t_enter = t_enter.max(t0.min(t1));
+                t_exit = t_exit.min(t0.max(t1));
+            }
+            (t_enter <= t_exit && t_enter > 0.001).then_some(t_enter)
+        }).min_by(f32::total_cmp)
+    }
+
+    #[test]
+    fn hits_block_across_chunk_boundaries() {
+        let mut world = VoxelWorld::new(Vec3::zero());
+        let stone = world.add_block_type(0);
+        world.set_block(40, 2, 3, stone);
+        world.set_block(-20, 2, 3, stone);
+
+        // Recorre dos chunks vacíos antes de llegar al bloque
+        let hit = world.intersect(&Ray::new(Vec3::new(0.5, 2.5, 3.5), Vec3::new(1.0, 0.0, 0.0)), 0.0).unwrap();
+        assert!((hit.t - 39.5).abs() < 1e-4, "t = {}", hit.t);
+        assert_eq!(hit.normal, Vec3::new(-1.0, 0.0, 0.0));
+        // Hacia las coordenadas negativas
+        let hit = world.intersect(&Ray::new(Vec3::new(0.5, 2.5, 3.5), Vec3::new(-1.0, 0.0, 0.0)), 0.0).unwrap();
+        assert!((hit.t - 19.5).abs() < 1e-4, "t = {}", hit.t);
+        assert_eq!(hit.normal, Vec3::new(1.0, 0.0, 0.0));
+        // Un rayo de sombra corto no llega
+        assert!(!world.occluded(&Ray::new(Vec3::new(0.5, 2.5, 3.5), Vec3::new(1.0, 0.0, 0.0)), 30.0, 0.0));
+        assert!(world.occluded(&Ray::new(Vec3::new(0.5, 2.5, 3.5), Vec3::new(1.0, 0.0, 0.0)), 40.0, 0.0));
+    }
+
+    #[test]
+    fn matches_brute_force_on_random_rays() {
+        let mut state = 7;
+        let mut world = VoxelWorld::new(Vec3::zero());
+        let stone = world.add_block_type(0);
+        let mut blocks = Vec::new();
+        let mut hits = 0;
+        for _ in 0..60 {
+            let block = [0, 1, 2].map(|_| (lcg(&mut state) * 48.0) as i32 - 24);
+            world.set_block(block[0], block[1], block[2], stone);
+            blocks.push(block);
+        }
+        for i in 0..500 {
+            let origin = Vec3::new(lcg(&mut state), lcg(&mut state), lcg(&mut state)) * 80.0 - Vec3::new(40.0, 40.0, 40.0);
+            if blocks.contains(&[origin.x.floor() as i32, origin.y.floor() as i32, origin.z.floor() as i32]) {
+                continue;
+            }
+            let mut direction = Vec3::new(lcg(&mut state), lcg(&mut state), lcg(&mut state)) * 2.0 - Vec3::new(1.0, 1.0, 1.0);
+            // Uno de cada tres rayos con alguna componente nula
+            if i % 3 == 0 { direction.y = 0.0; }
+            if i % 6 == 0 { direction.z = 0.0; }
+            if direction.length() < 0.1 { continue; }
+            // Apuntar a un bloque la mitad de las veces, si no casi ningún rayo acierta
+            if i % 2 == 0 {
+                let b = blocks[i % blocks.len()];
+                direction = Vec3::new(b[0] as f32 + 0.5, b[1] as f32 + 0.5, b[2] as f32 + 0.5) - origin;
+            }
+            let ray = Ray::new(origin, direction);
+            let expected = brute_force(&blocks, &ray);
+            let found = world.intersect(&ray, 0.0).map(|hit| hit.t);
+            match (expected, found) {
+                (Some(a), Some(b)) => {
+                    assert!((a - b).abs() < 1e-3, "rayo {}: {} contra {}", i, a, b);
+                    hits += 1;
+                }
+                (None, None) => {}
+                _ => panic!("rayo {}: {:?} contra {:?}", i, expected, found),
+            }
+        }
+        assert!(hits > 100, "solo {} rayos acertaron", hits);
+    }
+
+    #[test]
+    fn ray_starting_inside_block_reports_exit_or_next_block() {
+        let mut world = VoxelWorld::new(Vec3::zero());
+        let glass = world.add_block_type(0);
+        let stone = world.add_block_type(1);
+        for x in 2..=4 {
+            world.set_block(x, 0, 0, glass);
+        }
+        // Dentro del vidrio: atraviesa los bloques iguales y sale por la cara x = 5
+        let hit = world.intersect(&Ray::new(Vec3::new(2.5, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0)), 0.0).unwrap();
+        assert!((hit.t - 2.5).abs() < 1e-4, "t = {}", hit.t);
+        assert_eq!(hit.normal, Vec3::new(1.0, 0.0, 0.0));
+
+        // Con otro bloque pegado, lo siguiente es ese bloque
+        world.set_block(5, 0, 0, stone);
+        let hit = world.intersect(&Ray::new(Vec3::new(2.5, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0)), 0.0).unwrap();
+        assert!((hit.t - 2.5).abs() < 1e-4, "t = {}", hit.t);
+        assert_eq!(hit.material, 1);
+    }
+
+    #[test]
+    fn axis_aligned_rays() {
+        let mut world = VoxelWorld::new(Vec3::zero());
+        let stone = world.add_block_type(0);
+        world.set_block(3, 0, 3, stone);
+        world.set_block(3, 20, 3, stone);
+
+        // Recto hacia abajo entre los dos bloques
+        let hit = world.intersect(&Ray::new(Vec3::new(3.5, 10.0, 3.5), Vec3::new(0.0, -1.0, 0.0)), 0.0).unwrap();
+        assert!((hit.t - 9.0).abs() < 1e-4, "t = {}", hit.t);
+        assert_eq!(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+        // Paralelo a un eje pero fuera de la caja del mundo en otro
+        assert!(world.intersect(&Ray::new(Vec3::new(10.0, 10.0, 3.5), Vec3::new(0.0, 0.0, 1.0)), 0.0).is_none());
+        // Dentro de la caja pero por una columna vacía
+        assert!(world.intersect(&Ray::new(Vec3::new(3.5, 10.0, 0.5), Vec3::new(0.0, -1.0, 0.0)), 0.0).is_none());
+    }
+
+    #[test]
+    fn set_chunk_updates_bounds_and_top_block() {
+        let mut world = VoxelWorld::new(Vec3::zero());
+        let stone = world.add_block_type(0);
+        assert_eq!(world.top_block(0, 0), None);
+
+        // Chunk (-1, 0, 0) con solo la capa y = 3 llena
+        let mut blocks = vec![AIR; CHUNK_VOLUME];
+        blocks[3 * 256..4 * 256].fill(stone);
+        world.set_chunk((-1, 0, 0), blocks);
+        assert_eq!(world.get_block(-16, 3, 0), stone);
+        assert_eq!(world.get_block(-1, 4, 15), AIR);
+        assert_eq!(world.top_block(-5, 7), Some(3));
+        assert_eq!(world.top_block(0, 7), None);
+        assert_eq!(world.get_bounds(0.0), (Vec3::new(-16.0, 3.0, 0.0), Vec3::new(0.0, 4.0, 16.0)));
+
+        // Un chunk uniforme
+        world.set_chunk((0, 1, 0), vec![stone; CHUNK_VOLUME]);
+        assert_eq!(world.top_block(0, 0), Some(31));
+        let hit = world.intersect(&Ray::new(Vec3::new(-8.5, 40.0, 4.5), Vec3::new(0.0, -1.0, 0.0)), 0.0).unwrap();
+        assert!((hit.t - 36.0).abs() < 1e-4, "t = {}", hit.t);
+    }
+}